@@ -0,0 +1,363 @@
+//! `Accumulator` computes the aggregates of a column (like the sum or
+//! the mean) over a stream of values, so the column can be processed
+//! in chunks by `Conn::aggregate` without keeping the values in memory.
+
+use std::cmp::Ordering;
+
+use tokio::io::{Error, ErrorKind, Result as TokioResult};
+
+use crate::datatype::{Datatype, Dataunit};
+use crate::predicate::compare;
+use crate::record::{RecordTrait, RecordWriter, RecordReader};
+
+
+/// Aggregate function. The null values are ignored by all the functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AggFn {
+    /// Number of the values that are not null.
+    Count,
+
+    /// Sum of the values, it is zero if there are no values. The sum of
+    /// integers must fit into 64-bit integer. The sums of `Decimal` and
+    /// `Int128` values are exact and given as strings like the values.
+    Sum,
+
+    /// Minimum value.
+    Min,
+
+    /// Maximum value.
+    Max,
+
+    /// Arithmetic mean as float.
+    Mean,
+
+    /// Sample standard deviation as float, it needs two values at least.
+    Stddev,
+}
+
+
+impl std::fmt::Display for AggFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Mean => "mean",
+            Self::Stddev => "stddev",
+        })
+    }
+}
+
+
+/// State of the aggregation of a column. Integers, decimals (as the scaled
+/// integers) and 128-bit integers are summed exactly in 128-bit integer, 
+/// floats are summed with the compensation of the rounding errors 
+/// (Kahan-Babuska summation), the variance is updated by Welford's
+/// algorithm. The states of the parts of a column can be merged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accumulator {
+    datatype: Datatype,
+    count: usize,
+    num_count: usize,
+    int_sum: Option<i128>,
+    float_sum: f64,
+    compensation: f64,
+    min: Option<Dataunit>,
+    max: Option<Dataunit>,
+    mean: f64,
+    m2: f64,
+}
+
+
+impl Accumulator {
+    /// Create an empty accumulator for a column of the datatype `datatype`.
+    pub fn new(datatype: &Datatype) -> Self {
+        Self {
+            datatype: datatype.clone(),
+            count: 0,
+            num_count: 0,
+            int_sum: Some(0),
+            float_sum: 0.0,
+            compensation: 0.0,
+            min: None,
+            max: None,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    /// Get the datatype of the column.
+    pub fn datatype(&self) -> &Datatype {
+        &self.datatype
+    }
+
+    /// Add the value, nulls are skipped. The values that are not numbers
+    /// are only counted, they do not change the mean and the deviation.
+    pub fn push(&mut self, unit: &Dataunit) {
+        let x = match (unit, self.datatype.to_i128(unit)) {
+            (Dataunit::Null, _) => return,
+            (_, Some(value)) => {
+                self._add_int(value);
+                value as f64 / self._factor()
+            },
+            (Dataunit::I(x), _) => {
+                self._add_int(*x as i128);
+                *x as f64
+            },
+            (Dataunit::F(x), _) => {
+                self._add_float(*x);
+                *x
+            },
+            _ => {
+                self.count += 1;
+                return;
+            },
+        };
+
+        self.count += 1;
+        self.num_count += 1;
+
+        let datatype = Some(&self.datatype);
+        if self.min.as_ref()
+                .is_none_or(|min| compare(unit, min, datatype) ==
+                                  Some(Ordering::Less)) {
+            self.min = Some(unit.clone());
+        }
+        if self.max.as_ref()
+                .is_none_or(|max| compare(unit, max, datatype) ==
+                                  Some(Ordering::Greater)) {
+            self.max = Some(unit.clone());
+        }
+
+        let delta = x - self.mean;
+        self.mean += delta / self.num_count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Merge the state of the other part of the column.
+    pub fn merge(&mut self, other: &Self) {
+        if other.count == 0 {
+            return;
+        }
+        self.count += other.count;
+
+        // The mean and the deviation are weighted by the numbers
+        if other.num_count > 0 {
+            let num_count = self.num_count + other.num_count;
+            let delta = other.mean - self.mean;
+            self.mean += delta * other.num_count as f64 / num_count as f64;
+            self.m2 += other.m2 + delta * delta *
+                       (self.num_count as f64 * other.num_count as f64) /
+                       num_count as f64;
+            self.num_count = num_count;
+        }
+
+        match other.int_sum {
+            Some(value) => self._add_int(value),
+            None => self.int_sum = None,
+        }
+        self._add_float(other.float_sum);
+        self._add_float(other.compensation);
+
+        let datatype = Some(&self.datatype);
+        if let Some(min) = &other.min &&
+                self.min.as_ref().is_none_or(|x| compare(min, x, datatype) ==
+                                                 Some(Ordering::Less)) {
+            self.min = Some(min.clone());
+        }
+        if let Some(max) = &other.max &&
+                self.max.as_ref().is_none_or(|x| compare(max, x, datatype) ==
+                                                 Some(Ordering::Greater)) {
+            self.max = Some(max.clone());
+        }
+    }
+
+    /// Get the result of the aggregate function `agg_fn`. Min, max, mean
+    /// and standard deviation are null if there are not enough values.
+    /// It returns an error if the sum of integers overflows.
+    pub fn get(&self, agg_fn: AggFn) -> TokioResult<Dataunit> {
+        let is_float = self._is_float();
+        Ok(match agg_fn {
+            AggFn::Count => Dataunit::I(self.count as i64),
+            AggFn::Sum if is_float => {
+                Dataunit::F(self.float_sum + self.compensation)
+            },
+            AggFn::Sum => {
+                let overflow = || Error::new(
+                    ErrorKind::InvalidData, "Integer overflow"
+                );
+                let sum = self.int_sum.ok_or_else(overflow)?;
+                match self.datatype.from_i128(sum) {
+                    Some(unit) => unit,
+                    None => Dataunit::I(
+                        i64::try_from(sum).map_err(|_| overflow())?
+                    ),
+                }
+            },
+            AggFn::Min => self.min.clone().unwrap_or(Dataunit::Null),
+            AggFn::Max => self.max.clone().unwrap_or(Dataunit::Null),
+            AggFn::Mean if self.num_count == 0 => Dataunit::Null,
+            AggFn::Mean if is_float => {
+                Dataunit::F((self.float_sum + self.compensation) /
+                            self.num_count as f64)
+            },
+            AggFn::Mean => Dataunit::F(self.int_sum.map_or(
+                self.mean, 
+                |sum| sum as f64 / self._factor() / self.num_count as f64
+            )),
+            AggFn::Stddev if self.num_count < 2 => Dataunit::Null,
+            AggFn::Stddev => {
+                Dataunit::F((self.m2 / (self.num_count - 1) as f64).sqrt())
+            },
+        })
+    }
+
+    fn _is_float(&self) -> bool {
+        matches!(self.datatype, Datatype::Float64 | Datatype::Float32)
+    }
+
+    fn _factor(&self) -> f64 {
+        match self.datatype {
+            Datatype::Decimal(_, scale) => 10f64.powi(scale as i32),
+            _ => 1.0,
+        }
+    }
+
+    fn _add_int(&mut self, x: i128) {
+        // The sum becomes none if it overflows
+        self.int_sum = self.int_sum.and_then(|sum| sum.checked_add(x));
+    }
+
+    fn _add_float(&mut self, x: f64) {
+        let sum = self.float_sum + x;
+        if self.float_sum.abs() >= x.abs() {
+            self.compensation += (self.float_sum - sum) + x;
+        } else {
+            self.compensation += (x - sum) + self.float_sum;
+        }
+        self.float_sum = sum;
+    }
+}
+
+
+impl RecordTrait for Accumulator {
+    fn encode(&self, writer: &mut RecordWriter) {
+        writer.put_str(&self.datatype.to_string());
+        writer.put_u64(self.count as u64);
+        writer.put_u64(self.num_count as u64);
+        writer.put_bool(self.int_sum.is_some());
+        writer.put_bytes(&self.int_sum.unwrap_or(0).to_le_bytes());
+        writer.put_u64(self.float_sum.to_bits());
+        writer.put_u64(self.compensation.to_bits());
+        writer.put_unit(self.min.as_ref().unwrap_or(&Dataunit::Null));
+        writer.put_unit(self.max.as_ref().unwrap_or(&Dataunit::Null));
+        writer.put_u64(self.mean.to_bits());
+        writer.put_u64(self.m2.to_bits());
+    }
+
+    fn decode(reader: &mut RecordReader, _version: u8) -> TokioResult<Self> {
+        let datatype = reader.get_str()?.parse::<Datatype>()
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        let count = reader.get_u64()? as usize;
+        let num_count = reader.get_u64()? as usize;
+        let has_int_sum = reader.get_bool()?;
+        let int_sum = reader.get_bytes()?.try_into()
+            .map(i128::from_le_bytes)
+            .map_err(|_| Error::from(ErrorKind::InvalidData))?;
+        let int_sum = has_int_sum.then_some(int_sum);
+        let float_sum = f64::from_bits(reader.get_u64()?);
+        let compensation = f64::from_bits(reader.get_u64()?);
+        let min = Some(reader.get_unit()?)
+            .filter(|unit| *unit != Dataunit::Null);
+        let max = Some(reader.get_unit()?)
+            .filter(|unit| *unit != Dataunit::Null);
+        let mean = f64::from_bits(reader.get_u64()?);
+        let m2 = f64::from_bits(reader.get_u64()?);
+        Ok(Self {
+            datatype, count, num_count, int_sum, float_sum, compensation,
+            min, max, mean, m2,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::{encode_record, decode_record};
+
+    #[test]
+    fn test_accumulator() -> TokioResult<()> {
+        let mut acc = Accumulator::new(&Datatype::Int64);
+        for x in [2, 6, 4] {
+            acc.push(&Dataunit::I(x));
+        }
+        acc.push(&Dataunit::Null);
+        assert_eq!(acc.get(AggFn::Count)?, Dataunit::I(3));
+        assert_eq!(acc.get(AggFn::Sum)?, Dataunit::I(12));
+        assert_eq!(acc.get(AggFn::Min)?, Dataunit::I(2));
+        assert_eq!(acc.get(AggFn::Max)?, Dataunit::I(6));
+        assert_eq!(acc.get(AggFn::Mean)?, Dataunit::F(4.0));
+        assert_eq!(acc.get(AggFn::Stddev)?, Dataunit::F(2.0));
+
+        let mut other = Accumulator::new(&Datatype::Int64);
+        other.push(&Dataunit::I(i64::MAX));
+        let mut merged = acc.clone();
+        merged.merge(&other);
+        assert_eq!(merged.get(AggFn::Max)?, Dataunit::I(i64::MAX));
+        assert!(merged.get(AggFn::Sum).is_err());
+        assert_eq!(Accumulator::new(&Datatype::Int64).get(AggFn::Mean)?, 
+                   Dataunit::Null);
+
+        let mut acc = Accumulator::new(&Datatype::Float64);
+        let mut other = Accumulator::new(&Datatype::Float64);
+        acc.push(&Dataunit::F(1e16));
+        for _ in 0..10 {
+            acc.push(&Dataunit::F(1.0));
+            other.push(&Dataunit::F(1.0));
+        }
+        acc.push(&Dataunit::F(-1e16));
+        acc.merge(&other);
+        assert_eq!(acc.get(AggFn::Sum)?, Dataunit::F(20.0));
+        assert_eq!(acc.get(AggFn::Count)?, Dataunit::I(22));
+
+        let mut acc = Accumulator::new(&Datatype::Int64);
+        let mut other = Accumulator::new(&Datatype::Int64);
+        acc.push(&Dataunit::S("qwe".to_string()));
+        acc.push(&Dataunit::I(2));
+        other.push(&Dataunit::I(4));
+        other.push(&Dataunit::I(6));
+        acc.merge(&other);
+        assert_eq!(acc.get(AggFn::Count)?, Dataunit::I(4));
+        assert_eq!(acc.get(AggFn::Mean)?, Dataunit::F(4.0));
+        assert_eq!(acc.get(AggFn::Stddev)?, Dataunit::F(2.0));
+
+        let block = encode_record(&merged);
+        assert_eq!(decode_record::<Accumulator>(&block)?, merged);
+
+        let s = |x: &str| Dataunit::S(x.to_string());
+        let mut acc = Accumulator::new(&Datatype::Decimal(10, 2));
+        for x in ["0.10", "0.20", "10.00", "-0.05"] {
+            acc.push(&s(x));
+        }
+        assert_eq!(acc.get(AggFn::Sum)?, s("10.25"));
+        assert_eq!(acc.get(AggFn::Min)?, s("-0.05"));
+        assert_eq!(acc.get(AggFn::Max)?, s("10.00"));
+        assert_eq!(acc.get(AggFn::Mean)?, Dataunit::F(2.5625));
+
+        let mut acc = Accumulator::new(&Datatype::Int128);
+        let mut other = Accumulator::new(&Datatype::Int128);
+        acc.push(&s("9"));
+        other.push(&s("10"));
+        other.push(&s(&i128::MAX.to_string()));
+        acc.merge(&other);
+        assert_eq!(acc.get(AggFn::Min)?, s("9"));
+        assert_eq!(acc.get(AggFn::Max)?, s(&i128::MAX.to_string()));
+        assert!(acc.get(AggFn::Sum).is_err());
+        let block = encode_record(&acc);
+        assert_eq!(decode_record::<Accumulator>(&block)?, acc);
+
+        Ok(())
+    }
+}
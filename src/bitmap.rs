@@ -0,0 +1,194 @@
+//! `Bitmap` stores a sequence of bits in a file, one bit per row. It is
+//! a wrapper over `Seq` with one byte blocks, the bits are packed starting
+//! from the least significant one.
+
+use std::path::Path;
+
+use tokio::io::Result as TokioResult;
+
+use crate::utils::CHUNK_SIZE;
+use crate::seq::Seq;
+
+
+/// `Bitmap` implements access to the bits by their indices. The bits beyond
+/// the size are always kept zero, so the new bits are zeros after growing.
+pub struct Bitmap {
+    seq: Seq,
+    size: usize,
+}
+
+
+impl Bitmap {
+    /// Create a `Bitmap` object located at `path` having `size` bits. If no
+    /// file exists, it creates one with `size` zero bits.
+    pub async fn new(path: impl AsRef<Path>, size: usize) ->
+                     TokioResult<Self> {
+        let seq = Seq::new(path, 1).await?;
+        Self::_open(seq, size).await
+    }
+
+    /// Create a segmented `Bitmap` object that stores the bits in
+    /// the segments of `segment_size` bits, it must be multiple of 8.
+    pub async fn new_segmented(path: impl AsRef<Path>, size: usize,
+                               segment_size: usize) -> TokioResult<Self> {
+        let seq = Seq::new_segmented(path, 1, segment_size / 8).await?;
+        Self::_open(seq, size).await
+    }
+
+    /// Get size in bits.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Resize the bitmap setting a new size `new_size` in bits. The new bits
+    /// are zeros.
+    pub async fn resize(&mut self, new_size: usize) -> TokioResult<()> {
+        self.seq.resize(Self::_byte_size(new_size)).await?;
+        if new_size < self.size && !new_size.is_multiple_of(8) {
+            let mut block = [0u8];
+            self.seq.get(new_size / 8, &mut block).await?;
+            block[0] &= (1u8 << (new_size % 8)) - 1;
+            self.seq.update(new_size / 8, &block).await?;
+        }
+        self.size = new_size;
+        Ok(())
+    }
+
+    /// Get `count` bits located from `ix`.
+    pub async fn get(&mut self, ix: usize, count: usize) ->
+                     TokioResult<Vec<bool>> {
+        if count == 0 {
+            return Ok(vec![]);
+        }
+        let block = self._get_block(ix, count).await?;
+        Ok((ix..ix + count)
+            .map(|i| block[i / 8 - ix / 8] & (1u8 << (i % 8)) > 0)
+            .collect())
+    }
+
+    /// Set the bits located from `ix` with `bits`.
+    pub async fn set(&mut self, ix: usize, bits: &[bool]) -> TokioResult<()> {
+        if bits.is_empty() {
+            return Ok(());
+        }
+        let mut block = self._get_block(ix, bits.len()).await?;
+        for (i, &bit) in (ix..).zip(bits.iter()) {
+            let byte = &mut block[i / 8 - ix / 8];
+            if bit {
+                *byte |= 1u8 << (i % 8);
+            } else {
+                *byte &= !(1u8 << (i % 8));
+            }
+        }
+        self.seq.update(ix / 8, &block).await
+    }
+
+    /// Set `count` bits located from `ix` with the same value `bit`. The
+    /// whole bytes inside the range are written directly in chunks, only
+    /// the edge bits are set one by one.
+    pub async fn fill(&mut self, ix: usize, count: usize, bit: bool) ->
+                      TokioResult<()> {
+        let end = ix + count;
+        let first = ix.div_ceil(8);
+        let last = end / 8;
+
+        if first >= last {
+            return self.set(ix, &vec![bit; count]).await;
+        }
+
+        self.set(ix, &vec![bit; first * 8 - ix]).await?;
+
+        let byte = if bit { 0xFFu8 } else { 0u8 };
+        for start in (first..last).step_by(CHUNK_SIZE) {
+            let size = CHUNK_SIZE.min(last - start);
+            self.seq.update(start, &vec![byte; size]).await?;
+        }
+
+        self.set(last * 8, &vec![bit; end - last * 8]).await
+    }
+
+    /// Release the disk space of the whole bytes covered by `count` bits
+    /// located from `ix`, the bits of these bytes become zeros.
+    pub async fn punch(&mut self, ix: usize, count: usize) ->
+                       TokioResult<()> {
+        let first = ix.div_ceil(8);
+        let last = (ix + count) / 8;
+        if first < last {
+            self.seq.punch(first, last - first).await?;
+        }
+        Ok(())
+    }
+
+    /// Seal the segments that contain whole bytes only, so they cannot be
+    /// changed.
+    pub async fn seal(&mut self) -> TokioResult<()> {
+        self.seq.seal(self.size / 8).await
+    }
+
+    async fn _open(seq: Seq, size: usize) -> TokioResult<Self> {
+        let byte_size = seq.size().await?;
+        let mut bitmap = Self { seq, size: byte_size * 8 };
+        bitmap.resize(size).await?;
+        Ok(bitmap)
+    }
+
+    async fn _get_block(&mut self, ix: usize, count: usize) ->
+                        TokioResult<Vec<u8>> {
+        let mut block = vec![0u8; (ix + count - 1) / 8 - ix / 8 + 1];
+        self.seq.get(ix / 8, &mut block).await?;
+        Ok(block)
+    }
+
+    fn _byte_size(size: usize) -> usize {
+        size.div_ceil(8)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bitmap() -> TokioResult<()> {
+        let path = "./tmp/test_bitmap.bitmap";
+        let _ = tokio::fs::remove_file(path).await;
+
+        let mut bitmap = Bitmap::new(path, 10).await?;
+        assert_eq!(bitmap.get(0, 10).await?, vec![false; 10]);
+
+        bitmap.set(6, &[true, false, true, true]).await?;
+        bitmap.fill(0, 2, true).await?;
+        assert_eq!(bitmap.get(5, 5).await?,
+                   vec![false, true, false, true, true]);
+
+        bitmap.resize(8).await?;
+        bitmap.resize(12).await?;
+        assert_eq!(bitmap.get(0, 12).await?, vec![
+            true, true, false, false, false, false, true, false,
+            false, false, false, false,
+        ]);
+
+        bitmap.resize(40).await?;
+        bitmap.fill(3, 30, true).await?;
+        bitmap.fill(9, 17, false).await?;
+        let bits = bitmap.get(0, 40).await?;
+        assert_eq!(bits.iter().filter(|&&bit| bit).count(), 15);
+        assert!(bits[3..9].iter().all(|&bit| bit));
+        assert!(bits[9..26].iter().all(|&bit| !bit));
+        assert!(bits[26..33].iter().all(|&bit| bit));
+
+        drop(bitmap);
+        let mut bitmap = Bitmap::new(path, 12).await?;
+        assert_eq!(bitmap.get(6, 1).await?, vec![true]);
+
+        bitmap.resize(24).await?;
+        bitmap.fill(0, 24, true).await?;
+        bitmap.punch(2, 20).await?;
+        assert_eq!(bitmap.get(0, 24).await?, [
+            vec![true; 8], vec![false; 8], vec![true; 8],
+        ].concat());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+//! Casting of the values between datatypes that is used to change
+//! the datatype of an existing column. The supported casts are between
+//! integers, floats, integers and floats, bytes of different sizes and
+//! between string datatypes.
+
+use std::io::{Error, ErrorKind};
+
+use base64::prelude::*;
+
+use crate::datatype::{Datatype, Dataunit};
+
+
+/// Policy that defines what to do with a value that cannot be cast exactly
+/// (out of range integers, floats losing precision or fractional part,
+/// truncated bytes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastPolicy {
+    /// Return an error.
+    Strict,
+
+    /// Saturate integers, round floats and truncate bytes.
+    Lossy,
+
+    /// Replace the value with null, the column must be nullable.
+    Null,
+}
+
+
+/// Kind of the datatype for casting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Int,
+    Float,
+    Bytes,
+    Str,
+}
+
+
+impl Kind {
+    fn of(datatype: &Datatype) -> Option<Self> {
+        match datatype {
+            Datatype::Int32 | Datatype::Int64 |
+                Datatype::Int128 | Datatype::UInt128 => Some(Self::Int),
+            Datatype::Float32 | Datatype::Float64 => Some(Self::Float),
+            Datatype::Bytes(_) | Datatype::VarBytes => Some(Self::Bytes),
+            Datatype::VarStr | Datatype::Category => Some(Self::Str),
+            _ => None,
+        }
+    }
+}
+
+
+/// Integer of any integer datatype, the values above `i128::MAX` are only 
+/// kept as unsigned, so the integers are ordered as the variants go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Int {
+    Signed(i128),
+    Unsigned(u128),
+}
+
+
+impl Int {
+    fn from_u128(x: u128) -> Self {
+        i128::try_from(x).map_or(Self::Unsigned(x), Self::Signed)
+    }
+
+    /// Get the integer equal to the float `x` if there is one.
+    fn from_f64(x: f64) -> Option<Self> {
+        // The bounds are the powers of two, so they are exact floats
+        if x.fract() != 0.0 || !(-(2f64.powi(127))..2f64.powi(128))
+                .contains(&x) {
+            None
+        } else if x < 0.0 {
+            Some(Self::Signed(x as i128))
+        } else {
+            Some(Self::from_u128(x as u128))
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        match self {
+            Self::Signed(x) => x as f64,
+            Self::Unsigned(x) => x as f64,
+        }
+    }
+}
+
+
+impl std::fmt::Display for Int {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Signed(x) => write!(f, "{}", x),
+            Self::Unsigned(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+
+/// Check whether the values of the datatype `from` can be cast to
+/// the datatype `to`.
+pub fn is_castable(from: &Datatype, to: &Datatype) -> bool {
+    match (Kind::of(from), Kind::of(to)) {
+        (Some(Kind::Int | Kind::Float), Some(Kind::Int | Kind::Float)) => true,
+        (Some(from), Some(to)) => from == to,
+        _ => false,
+    }
+}
+
+
+/// Cast the value `unit` of the datatype `from` to the datatype `to`
+/// according to `policy`. Null values stay null.
+pub fn cast(unit: &Dataunit, from: &Datatype, to: &Datatype,
+            policy: CastPolicy) -> std::io::Result<Dataunit> {
+    if *unit == Dataunit::Null {
+        return Ok(Dataunit::Null);
+    }
+
+    let (value, exact) = _cast(unit, from, to).ok_or_else(|| Error::new(
+        ErrorKind::InvalidInput, format!("{} to {}", from, to)
+    ))?;
+
+    if exact || policy == CastPolicy::Lossy {
+        Ok(value)
+    } else if policy == CastPolicy::Null {
+        Ok(Dataunit::Null)
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidData, format!("{:?} to {}", unit, to)
+        ))
+    }
+}
+
+
+/// Cast the value and tell whether the cast is exact.
+fn _cast(unit: &Dataunit, from: &Datatype, to: &Datatype) ->
+         Option<(Dataunit, bool)> {
+    match (Kind::of(from)?, Kind::of(to)?) {
+        (Kind::Int, Kind::Int) => {
+            let x = _get_int(unit)?;
+            let (min, max) = _int_range(to);
+            let y = x.clamp(min, max);
+            Some((_make_int(y, to), x == y))
+        },
+        (Kind::Int, Kind::Float) => {
+            let x = _get_int(unit)?;
+            let y = _round_float(x.to_f64(), to);
+            Some((Dataunit::F(y), Int::from_f64(y) == Some(x)))
+        },
+        (Kind::Float, Kind::Float) => {
+            let x = _get_float(unit)?;
+            let y = _round_float(x, to);
+            Some((Dataunit::F(y), y == x || x.is_nan()))
+        },
+        (Kind::Float, Kind::Int) => {
+            // The floats out of any integer range saturate, the exactness
+            // is checked against the unclamped value
+            let x = _get_float(unit)?;
+            let (min, max) = _int_range(to);
+            let y = match Int::from_f64(x.round()) {
+                Some(y) => y.clamp(min, max),
+                None if x > 0.0 => max,
+                None if x < 0.0 => min,
+                None => Int::Signed(0),
+            };
+            Some((_make_int(y, to), Int::from_f64(x) == Some(y)))
+        },
+        (Kind::Bytes, Kind::Bytes) => {
+            let mut block = match unit {
+                Dataunit::S(x) => BASE64_STANDARD.decode(x).ok()?,
+                _ => return None,
+            };
+            let exact = if let Datatype::Bytes(len) = to {
+                let exact = block.iter().skip(*len).all(|&b| b == 0);
+                block.resize(*len, 0);
+                exact
+            } else {
+                true
+            };
+            Some((Dataunit::S(BASE64_STANDARD.encode(block)), exact))
+        },
+        (Kind::Str, Kind::Str) => Some((unit.clone(), true)),
+        _ => None,
+    }
+}
+
+
+fn _get_int(unit: &Dataunit) -> Option<Int> {
+    match unit {
+        Dataunit::I(x) => Some(Int::Signed(*x as i128)),
+        Dataunit::S(x) => x.parse::<i128>().map(Int::Signed).ok()
+            .or_else(|| x.parse::<u128>().map(Int::from_u128).ok()),
+        _ => None,
+    }
+}
+
+
+fn _get_float(unit: &Dataunit) -> Option<f64> {
+    match unit {
+        Dataunit::F(x) => Some(*x),
+        _ => None,
+    }
+}
+
+
+fn _int_range(datatype: &Datatype) -> (Int, Int) {
+    let (min, max) = match datatype {
+        Datatype::Int32 => (i32::MIN as i128, i32::MAX as i128),
+        Datatype::Int64 => (i64::MIN as i128, i64::MAX as i128),
+        Datatype::UInt128 => return (Int::Signed(0), Int::Unsigned(u128::MAX)),
+        _ => (i128::MIN, i128::MAX),
+    };
+    (Int::Signed(min), Int::Signed(max))
+}
+
+
+fn _make_int(x: Int, datatype: &Datatype) -> Dataunit {
+    match (datatype, x) {
+        (Datatype::Int32 | Datatype::Int64, Int::Signed(x)) => {
+            Dataunit::I(x as i64)
+        },
+        _ => Dataunit::S(x.to_string()),
+    }
+}
+
+
+fn _round_float(x: f64, datatype: &Datatype) -> f64 {
+    match datatype {
+        Datatype::Float32 => x as f32 as f64,
+        _ => x,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast() {
+        let strict = CastPolicy::Strict;
+        let s = |x: &str| Dataunit::S(x.to_string());
+
+        assert_eq!(cast(&Dataunit::I(5), &Datatype::Int32, &Datatype::Int64,
+                        strict).unwrap(), Dataunit::I(5));
+        assert_eq!(cast(&Dataunit::I(5), &Datatype::Int64, &Datatype::Int128,
+                        strict).unwrap(), s("5"));
+        assert!(cast(&s("5000000000"), &Datatype::Int128, &Datatype::Int32,
+                     strict).is_err());
+        assert_eq!(cast(&s("5000000000"), &Datatype::Int128,
+                        &Datatype::Int32, CastPolicy::Lossy).unwrap(),
+                   Dataunit::I(i32::MAX as i64));
+        assert_eq!(cast(&Dataunit::I(-1), &Datatype::Int64,
+                        &Datatype::UInt128, CastPolicy::Null).unwrap(),
+                   Dataunit::Null);
+
+        assert_eq!(cast(&Dataunit::I(3), &Datatype::Int32,
+                        &Datatype::Float32, strict).unwrap(),
+                   Dataunit::F(3.0));
+        assert!(cast(&Dataunit::I(16777217), &Datatype::Int32,
+                     &Datatype::Float32, strict).is_err());
+
+        assert_eq!(cast(&Dataunit::F(0.5), &Datatype::Float64,
+                        &Datatype::Float32, strict).unwrap(),
+                   Dataunit::F(0.5));
+        assert!(cast(&Dataunit::F(0.1), &Datatype::Float64,
+                     &Datatype::Float32, strict).is_err());
+        assert!(matches!(cast(&Dataunit::F(f64::NAN), &Datatype::Float64,
+                              &Datatype::Float32, strict).unwrap(),
+                         Dataunit::F(x) if x.is_nan()));
+
+        assert_eq!(cast(&Dataunit::F(2.0), &Datatype::Float64,
+                        &Datatype::Int64, strict).unwrap(), Dataunit::I(2));
+        assert!(cast(&Dataunit::F(2.5), &Datatype::Float64,
+                     &Datatype::Int64, strict).is_err());
+        assert_eq!(cast(&Dataunit::F(2.6), &Datatype::Float64,
+                        &Datatype::Int64, CastPolicy::Lossy).unwrap(),
+                   Dataunit::I(3));
+
+        assert!(cast(&Dataunit::F(2f64.powi(63)), &Datatype::Float64,
+                     &Datatype::Int64, strict).is_err());
+        assert_eq!(cast(&Dataunit::F(2f64.powi(63)), &Datatype::Float64,
+                        &Datatype::Int64, CastPolicy::Lossy).unwrap(),
+                   Dataunit::I(i64::MAX));
+        assert!(cast(&Dataunit::I(i64::MAX), &Datatype::Int64,
+                     &Datatype::Float64, strict).is_err());
+        assert!(cast(&s(&i128::MAX.to_string()), &Datatype::Int128,
+                     &Datatype::Float64, strict).is_err());
+        assert_eq!(cast(&Dataunit::F(2f64.powi(127)), &Datatype::Float64,
+                        &Datatype::UInt128, strict).unwrap(),
+                   s(&(1u128 << 127).to_string()));
+        assert!(cast(&Dataunit::F(2f64.powi(127)), &Datatype::Float64,
+                     &Datatype::Int128, strict).is_err());
+        assert_eq!(cast(&Dataunit::F(1e39), &Datatype::Float64,
+                        &Datatype::UInt128, CastPolicy::Lossy).unwrap(),
+                   s(&u128::MAX.to_string()));
+        assert_eq!(cast(&Dataunit::F(-1e39), &Datatype::Float64,
+                        &Datatype::Int128, CastPolicy::Lossy).unwrap(),
+                   s(&i128::MIN.to_string()));
+        assert_eq!(cast(&s(&u128::MAX.to_string()), &Datatype::UInt128,
+                        &Datatype::UInt128, strict).unwrap(),
+                   s(&u128::MAX.to_string()));
+        assert_eq!(cast(&s(&u128::MAX.to_string()), &Datatype::UInt128,
+                        &Datatype::Int128, CastPolicy::Lossy).unwrap(),
+                   s(&i128::MAX.to_string()));
+
+        assert_eq!(cast(&s("+uwgVQA="), &Datatype::Bytes(5),
+                        &Datatype::Bytes(4), strict).unwrap(), s("+uwgVQ=="));
+        assert!(cast(&s("+uwgVQA="), &Datatype::Bytes(5),
+                     &Datatype::Bytes(3), strict).is_err());
+        assert_eq!(cast(&s("+uwg"), &Datatype::Bytes(3),
+                        &Datatype::Bytes(4), strict).unwrap(), s("+uwgAA=="));
+
+        assert_eq!(cast(&Dataunit::Null, &Datatype::Int32, &Datatype::Int64,
+                        strict).unwrap(), Dataunit::Null);
+        assert!(cast(&s("qwe"), &Datatype::VarStr, &Datatype::Int64,
+                     strict).is_err());
+        assert!(is_castable(&Datatype::VarStr, &Datatype::Category));
+        assert!(!is_castable(&Datatype::VarStr, &Datatype::Bytes(4)));
+    }
+}
@@ -106,13 +106,13 @@ impl<T: Clone> Col<T> {
     /// Update the instance located at `ix` with the data `x`.
     pub async fn update(&mut self, ix: usize, x: &T) -> TokioResult<()> {
         let block = to_bytes(x);
-        self.seq.update(ix, &block).await
+        self.seq.update(ix, block).await
     }
 
     /// Update the instances located from `ix` with the data in the slice `x`.
     pub async fn update_many(&mut self, ix: usize, x: &[T]) -> TokioResult<()> {
         let block = to_bytes_many(x);
-        self.seq.update(ix, &block).await
+        self.seq.update(ix, block).await
     }
 
     /// Update instances with raw bytes starting from `ix`.
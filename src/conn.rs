@@ -2,20 +2,46 @@
 //! interface to the DBMS.
 
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeMap};
+use std::path::PathBuf;
+use std::ops::Range;
 
 use tokio::io::{Result as TokioResult};
 use tokio::task::JoinSet;
-use tokio::fs::{create_dir_all, remove_dir_all, rename};
-use tokio::sync::{Mutex, RwLock};
+use tokio::fs::{create_dir_all, remove_dir, remove_file, read_dir, rename, 
+                try_exists};
+use tokio::sync::{Mutex, OwnedRwLockReadGuard, RwLock};
 
 use crate::validate;
 use crate::path_concat;
-use crate::seq::Seq;
+use crate::utils::{CHUNK_SIZE, validate_allowed_name};
+use crate::series::Series;
+use crate::bitmap::Bitmap;
 use crate::list::List;
-use crate::items::{FeedItem, ColItem};
-use crate::datatype::Dataunit;
-use crate::dataset::{Dataset, get_dataset_size};
+use crate::items::{FeedItem, ColItem, ColOptions, TemplateItem, Meta, 
+                   TrimMode, PartitionItem};
+use crate::datatype::{Datatype, Dataunit};
+use crate::dataset::{Dataset, ReadOptions, get_dataset_size};
+use crate::remap::IndexRemap;
+use crate::predicate::Predicate;
+use crate::aggregate::{AggFn, Accumulator};
+use crate::group::{GroupTable, GroupOptions};
+use crate::cast::{CastPolicy, cast, is_castable};
+use crate::schema::{FeedSchema, SchemaDiff};
+use crate::history::{History, HistoryItem, HistoryOp};
+use crate::utils::timestamp;
+
+
+/// Double map feed key -> col key -> series.
+type SeriesMapping = HashMap<String, HashMap<String, Arc<Mutex<Series>>>>;
+
+/// Piece of a row range of a partitioned feed: the partition feed, the index
+/// of its first row in the feed, the index in the partition and the number
+/// of the rows.
+type PartitionPiece = (String, usize, usize, usize);
+
+/// Maximum gap between the indices that `data_take` reads by one range.
+const TAKE_GAP: usize = 256;
 
 
 /// Connection object that manages all the entities. Since it interacts with 
@@ -37,8 +63,25 @@ pub struct Conn {
     // Col mapping as double map feed key -> col key -> col
     col_map_mapping: RwLock<HashMap<String, HashMap<String, ColItem>>>,
 
-    // Seq mapping as double map feed key -> col key -> seq
-    seq_mapping: RwLock<HashMap<String, HashMap<String, Arc<Mutex<Seq>>>>>,
+    // Series mapping as double map feed key -> col key -> series
+    series_mapping: RwLock<SeriesMapping>,
+
+    // Tombstone mapping feed key -> bitmap of the deleted rows
+    tombstone_mapping: RwLock<HashMap<String, Mutex<Bitmap>>>,
+
+    // Guard mapping feed key -> lock that the readers and the writers of
+    // the feed share and the compaction takes exclusively
+    guard_mapping: RwLock<HashMap<String, Arc<RwLock<()>>>>,
+
+    // Partition list objects of the partitioned feeds that is a mapping
+    // feed key -> the list
+    partition_mapping: RwLock<HashMap<String, List<PartitionItem, i64>>>,
+
+    // History of the structure changes
+    history: Mutex<History>,
+
+    // Template list object to manage the stored schemas
+    template_list: RwLock<List<TemplateItem, String>>,
 }
 
 
@@ -54,6 +97,14 @@ impl Conn {
             Self::_get_feed_list_path(path)
         ).await?;
 
+        // History of the structure changes
+        let history = History::new(Self::_get_history_path(path)).await?;
+
+        // List of templates
+        let template_list = List::<TemplateItem, String>::new(
+            Self::_get_template_list_path(path)
+        ).await?;
+
         // Create instance
         let instance = Self {
             path: path.to_string(),
@@ -61,7 +112,12 @@ impl Conn {
             feed_map: RwLock::new(HashMap::new()),
             col_list_mapping: RwLock::new(HashMap::new()),
             col_map_mapping: RwLock::new(HashMap::new()),
-            seq_mapping: RwLock::new(HashMap::new()),
+            series_mapping: RwLock::new(HashMap::new()),
+            tombstone_mapping: RwLock::new(HashMap::new()),
+            guard_mapping: RwLock::new(HashMap::new()),
+            partition_mapping: RwLock::new(HashMap::new()),
+            history: Mutex::new(history),
+            template_list: RwLock::new(template_list),
         };
 
         // Open all feeds
@@ -78,9 +134,16 @@ impl Conn {
         self.path.clone()
     }
 
-    /// List the feeds.
+    /// List the feeds. The partitions of the partitioned feeds are not
+    /// listed.
     pub async fn feed_list(&self) -> Vec<FeedItem> {
-        self.feed_map.read().await.values().cloned().collect()
+        let feed_map = self.feed_map.read().await;
+        feed_map.iter()
+            .filter(|(feed_name, _)| {
+                !Self::_is_partition_in(&feed_map, feed_name)
+            })
+            .map(|(_, feed_item)| feed_item.clone())
+            .collect()
     }
 
     /// Check if the feed exists.
@@ -90,53 +153,269 @@ impl Conn {
 
     /// Add a new feed by its name.
     pub async fn feed_add(&self, feed_name: &str) -> TokioResult<()> {
+        self.feed_add_with_schema(feed_name, &FeedSchema::new()).await
+    }
+
+    /// Add a new feed with the columns declared in `schema`. The column
+    /// list and the column files are created in one batch.
+    pub async fn feed_add_with_schema(&self, feed_name: &str, 
+                                      schema: &FeedSchema) -> 
+                                      TokioResult<()> {
+        self._feed_add(FeedItem::new(feed_name)?, schema).await
+    }
+
+    /// Add a new capped feed with the columns declared in `schema`. It keeps
+    /// at most `cap` most recent rows: when it is full, `data_push` 
+    /// overwrites the oldest rows, so the size of the files stays constant.
+    /// The columns must have fixed size datatypes and the rows cannot be
+    /// deleted or trimmed.
+    pub async fn feed_add_capped(&self, feed_name: &str, cap: usize, 
+                                 schema: &FeedSchema) -> TokioResult<()> {
+        validate!(cap > 0, InvalidInput, cap.to_string())?;
+        let mut feed_item = FeedItem::new(feed_name)?;
+        feed_item.cap = cap;
+        self._feed_add(feed_item, schema).await
+    }
+
+    /// Add a new segmented feed with the columns declared in `schema`. Its
+    /// rows are split into segments of `segment_size` rows and every column
+    /// stores each segment in a separate file. The full segments are sealed,
+    /// so their rows cannot be changed anymore, and `feed_trim_front` drops
+    /// the whole segments removing their files. The columns must have fixed
+    /// size datatypes (not `Category`) and `segment_size` must be multiple 
+    /// of 8.
+    pub async fn feed_add_segmented(&self, feed_name: &str, 
+                                    segment_size: usize, 
+                                    schema: &FeedSchema) -> TokioResult<()> {
+        validate!(segment_size > 0 && segment_size.is_multiple_of(8), 
+                  InvalidInput, segment_size.to_string())?;
+        let mut feed_item = FeedItem::new(feed_name)?;
+        feed_item.segment = segment_size;
+        self._feed_add(feed_item, schema).await
+    }
+
+    /// Add a new partitioned feed with the columns declared in `schema`. 
+    /// The rows are stored in the partitions by the time periods of 
+    /// the length `period` according to the integer column `time_col`. 
+    /// The partitions are the feeds `p<key>` (`m<key>` for the negative
+    /// keys) in the namespace of the feed that are created by `data_push`
+    /// when the rows of a new period come, they are not listed and cannot
+    /// be changed directly. The row indices of the feed go through
+    /// the partitions in the order of their periods, so the rows pushed to
+    /// an older period shift the indices of the later rows. The methods
+    /// taking the row indices work on the partitions that cover the rows,
+    /// the time column cannot be updated. `data_get_time` reads only
+    /// the partitions that match the time range. The columns and the size
+    /// of the feed cannot be changed after that, it cannot be compacted or
+    /// trimmed either.
+    pub async fn feed_add_partitioned(&self, feed_name: &str, 
+                                      time_col: &str, period: u64, 
+                                      schema: &FeedSchema) -> 
+                                      TokioResult<()> {
+        // Check the period and the time column
+        validate!(period > 0 && period <= i64::MAX as u64, 
+                  InvalidInput, period.to_string())?;
+        let datatype = schema.cols.iter()
+            .find(|col| col.name == time_col)
+            .map(|col| col.datatype.clone());
+        validate!(matches!(datatype.as_deref(), Some("Int64" | "Int32")), 
+                  InvalidInput, time_col)?;
+
+        // Create the feed
+        let mut feed_item = FeedItem::new(feed_name)?;
+        feed_item.time_col = time_col.to_string();
+        feed_item.period = period;
+        self._feed_add(feed_item, schema).await
+    }
+
+    async fn _feed_add(&self, feed_item: FeedItem, schema: &FeedSchema) -> 
+                       TokioResult<()> {
+        // The names of the partitions are reserved
+        let feed_name = feed_item.get_name();
+        self._validate_not_partition(&feed_name).await?;
+        if feed_item.is_partitioned() {
+            let taken = self.feed_map.read().await.keys()
+                .any(|name| name.rsplit_once('.').is_some_and(
+                    |(parent, name)| parent == feed_name &&
+                                     Self::_is_partition_name(name)
+                ));
+            validate!(!taken, AlreadyExists, &*feed_name)?;
+        }
+
+        // Create the feed
+        let col_items = self._feed_create(feed_item, schema).await?;
+
+        // Save the operations in the history
+        let mut items = vec![
+            Self::_history_item(HistoryOp::FeedAdd, &feed_name, "", ""),
+        ];
+        items.extend(col_items.iter().map(|col_item| Self::_history_item(
+            HistoryOp::ColAdd, &feed_name, &col_item.name,
+            &col_item.get_datatype()
+        )));
+        self.history.lock().await.push(&items).await?;
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _feed_create(&self, feed_item: FeedItem, schema: &FeedSchema) ->
+                          TokioResult<Vec<ColItem>> {
         // Check whether it exists
+        let feed_name = feed_item.get_name();
+        let feed_name = feed_name.as_str();
         validate!(!self.feed_exists(feed_name).await, 
                   AlreadyExists, feed_name)?;
 
-        // Try to create a feed instance
-        let feed_item = FeedItem::new(feed_name)?;
+        // Try to create col items
+        let col_items = schema.col_items()?;
+
+        // Check whether the datatypes are supported by the feed
+        for col_item in col_items.iter() {
+            Self::_validate_datatype(&feed_item, col_item)?;
+        }
 
         // Create directory for the feed
-        let feed_path = path_concat!(self.path.clone(), feed_name);
+        let feed_path = Self::_get_feed_path(&self.path, feed_name);
         create_dir_all(feed_path).await?;
 
+        // Write all col items into the list at once
+        let col_list_path = Self::_get_col_list_path(&self.path, &feed_item);
+        List::<ColItem, String>::new(col_list_path).await?
+            .add_many(&col_items).await?;
+
         // Insert a new record into the list
         self.feed_list.write().await.add(&feed_item).await?;
 
-        // Open the feed
+        // Open the feed with all its cols
         self._feed_open(feed_name, feed_item).await?;
 
+        // Ok
+        Ok(col_items)
+    }
+
+    /// Add a new feed with the columns of the template `template_name`.
+    pub async fn feed_add_from_template(&self, template_name: &str, 
+                                        feed_name: &str) -> TokioResult<()> {
+        let schema = self.template_get(template_name).await?;
+        self.feed_add_with_schema(feed_name, &schema).await
+    }
+
+    /// List the names of the templates.
+    pub async fn template_list(&self) -> TokioResult<Vec<String>> {
+        Ok(self.template_list.write().await.list().await?.into_iter()
+            .map(|template_item| template_item.name)
+            .collect())
+    }
+
+    /// Save `schema` as the template `template_name` for creating feeds.
+    pub async fn template_add(&self, template_name: &str, 
+                              schema: &FeedSchema) -> TokioResult<()> {
+        // Try to create a template instance
+        let template_item = TemplateItem::new(template_name, 
+                                              schema.col_items()?)?;
+
+        // Insert a new record into the list
+        let mut template_list = self.template_list.write().await;
+        validate!(!template_list.exists(&template_item.name), 
+                  AlreadyExists, template_name)?;
+        template_list.add(&template_item).await?;
+
         // Ok
         Ok(())
     }
 
-    /// Remove the feed by its name.
+    /// Get the schema of the template.
+    pub async fn template_get(&self, template_name: &str) -> 
+                              TokioResult<FeedSchema> {
+        let mut template_list = self.template_list.write().await;
+        validate!(template_list.exists(&template_name.to_string()), 
+                  NotFound, template_name)?;
+        let template_item = template_list.detail(&template_name.to_string())
+            .await?;
+        Ok(FeedSchema::from_items(&template_item.cols))
+    }
+
+    /// Remove the template.
+    pub async fn template_remove(&self, template_name: &str) -> 
+                                 TokioResult<()> {
+        let mut template_list = self.template_list.write().await;
+        validate!(template_list.exists(&template_name.to_string()), 
+                  NotFound, template_name)?;
+        template_list.remove(&template_name.to_string()).await
+    }
+
+    /// Remove the feed by its name. The feeds in its namespace stay 
+    /// unchanged.
     pub async fn feed_remove(&self, feed_name: &str) -> TokioResult<()> {
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        self._feed_remove(feed_name).await
+    }
+
+    async fn _feed_remove(&self, feed_name: &str) -> TokioResult<()> {
         // Check whether it exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
+        // Remove the partitions
+        for partition_name in self._partition_names(feed_name).await? {
+            Box::pin(self._feed_remove(&partition_name)).await?;
+        }
+
         // Close the feed
         self._feed_close(feed_name).await;
 
         // Remove from the list
         self.feed_list.write().await.remove(&feed_name.to_string()).await?;
 
-        // Remove the directory
-        let feed_path = path_concat!(self.path.clone(), feed_name);
-        remove_dir_all(feed_path).await?;
+        // Remove the files of the feed and the empty directories
+        let feed_path = Self::_get_feed_path(&self.path, feed_name);
+        for path in Self::_get_feed_files(&feed_path).await? {
+            remove_file(path).await?;
+        }
+        self._feed_dir_cleanup(feed_name).await;
+
+        // Save the operation in the history, the partitions are internal
+        if !self._is_partition(feed_name).await {
+            self._history_push(HistoryOp::FeedRemove, feed_name, "", "")
+                .await?;
+        }
 
         // Ok
         Ok(())
     }
 
-    /// Rename the feed.
+    /// Rename the feed. The feeds in its namespace stay unchanged except
+    /// the partitions of a partitioned feed.
     pub async fn feed_rename(&self, name: &str, name_new: &str) -> 
                              TokioResult<()> {
+        let _guard = self._feed_guard_read(name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(name).await?;
+        validate!(!self._is_partition(name_new).await,
+                  PermissionDenied, name_new)?;
+
+        self._feed_rename(name, name_new).await
+    }
+
+    async fn _feed_rename(&self, name: &str, name_new: &str) ->
+                              TokioResult<()> {
         // Check whether they exist
         validate!(self.feed_exists(name).await, NotFound, name)?;
         validate!(!self.feed_exists(name_new).await, AlreadyExists, name_new)?;
 
+        // Rename the partitions
+        for partition_name in self._partition_names(name).await? {
+            let partition_name_new = format!("{}{}", name_new, 
+                                             &partition_name[name.len()..]);
+            Box::pin(self._feed_rename(&partition_name, &partition_name_new))
+                .await?;
+        }
+
         // Close the feed
         let mut feed_item = self._feed_close(name).await;
 
@@ -144,13 +423,20 @@ impl Conn {
         let res: TokioResult<()> = {
             // Update feed list
             feed_item.rename(name_new)?;
+            feed_item.meta.touch();
             self.feed_list.write().await
                 .modify(&name.to_string(), &feed_item).await?;
 
-            // Rename the directory
-            let feed_path = path_concat!(self.path.clone(), name);
-            let feed_path_new = path_concat!(self.path.clone(), name_new);
-            rename(feed_path, feed_path_new).await?;
+            // Move the files of the feed into the new directory
+            let feed_path = Self::_get_feed_path(&self.path, name);
+            let feed_path_new = Self::_get_feed_path(&self.path, name_new);
+            create_dir_all(&feed_path_new).await?;
+            for path in Self::_get_feed_files(&feed_path).await? {
+                let path_new = path_concat!(&feed_path_new, 
+                                            path.file_name().unwrap());
+                rename(path, path_new).await?;
+            }
+            self._feed_dir_cleanup(name).await;
 
             // Ok
             Ok(())
@@ -162,6 +448,191 @@ impl Conn {
         // Raise error if happened
         res?;
 
+        // Save the operation in the history, the partitions are internal
+        if !self._is_partition(name).await {
+            self._history_push(HistoryOp::FeedRename, name, "", name_new)
+                .await?;
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    /// Create the feed and the columns declared in `schema` if they do not
+    /// exist. The existing columns are checked to have the declared
+    /// datatypes and options, if some of them do not match, an error that
+    /// lists the differing attributes is returned before any change.
+    /// It returns the made changes.
+    pub async fn feed_ensure(&self, feed_name: &str, schema: &FeedSchema) -> 
+                             TokioResult<SchemaDiff> {
+        // Check the column declarations
+        let col_items = schema.col_items()?;
+
+        let mut diff = SchemaDiff::default();
+
+        if self.feed_exists(feed_name).await {
+            let col_map = self.col_map_mapping.read().await[feed_name].clone();
+
+            // Check whether the existing columns match the schema
+            let conflicts = col_items.iter()
+                .filter_map(|col_item| col_map.get(&col_item.name)
+                    .map(|existing| Self::_col_conflicts(col_item, existing)))
+                .flatten()
+                .collect::<Vec<String>>();
+            validate!(conflicts.is_empty(), InvalidData, 
+                      conflicts.join(", "))?;
+
+            // Collect the columns that are not declared
+            diff.cols_extra = col_map.keys()
+                .filter(|col_name| col_items.iter()
+                            .all(|col_item| &col_item.name != *col_name))
+                .cloned().collect();
+            diff.cols_extra.sort();
+        } else {
+            // Create the feed with all the columns in one step
+            self.feed_add_with_schema(feed_name, schema).await?;
+            diff.feed_added = true;
+            diff.cols_added = schema.cols.iter()
+                .map(|col| col.name.clone())
+                .collect();
+            return Ok(diff);
+        }
+
+        // Add the missing columns
+        for col in schema.cols.iter() {
+            if !self.col_exists(feed_name, &col.name).await? {
+                self.col_add_with(feed_name, &col.name, &col.datatype, 
+                                  &col.options).await?;
+                diff.cols_added.push(col.name.clone());
+            }
+        }
+
+        // Ok
+        Ok(diff)
+    }
+
+    /// Get the schema of the feed with the columns ordered as they are 
+    /// stored in the column list.
+    pub async fn feed_schema(&self, feed_name: &str) -> 
+                             TokioResult<FeedSchema> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Get the columns from the list
+        let col_items = self.col_list_mapping.write().await
+            .get_mut(feed_name).unwrap().list().await?;
+
+        // Ok
+        Ok(FeedSchema::from_items(&col_items))
+    }
+
+    /// List the feeds in the namespace `ns`, that are the feed named `ns`
+    /// and the feeds with the names starting with `<ns>.`. The partitions
+    /// of the partitioned feeds are not listed.
+    pub async fn ns_list(&self, ns: &str) -> Vec<FeedItem> {
+        let feed_map = self.feed_map.read().await;
+        feed_map.iter()
+            .filter(|(feed_name, _)| {
+                Self::_is_in_ns(feed_name, ns) &&
+                    !Self::_is_partition_in(&feed_map, feed_name)
+            })
+            .map(|(_, feed_item)| feed_item.clone())
+            .collect()
+    }
+
+    /// Rename the namespace `ns` into `ns_new` renaming all its feeds, so
+    /// it can be moved into another namespace. The namespaces must not
+    /// contain each other. The feeds are renamed one by one, so in case of
+    /// failure some of them can be already renamed.
+    pub async fn ns_rename(&self, ns: &str, ns_new: &str) -> 
+                           TokioResult<()> {
+        // Check the names
+        validate_allowed_name(ns)?;
+        validate_allowed_name(ns_new)?;
+        validate!(!Self::_is_in_ns(ns, ns_new) && 
+                  !Self::_is_in_ns(ns_new, ns), InvalidInput, ns_new)?;
+
+        // Get the feeds and their new names, the partitions are renamed
+        // with their partitioned feeds
+        let names = self.ns_list(ns).await.iter()
+            .map(|feed_item| {
+                let name = feed_item.get_name();
+                let name_new = format!("{}{}", ns_new, &name[ns.len()..]);
+                (name, name_new)
+            })
+            .collect::<Vec<(String, String)>>();
+
+        // Check whether the namespace exists
+        validate!(!names.is_empty(), NotFound, ns)?;
+
+        // Check whether the new names are free
+        for (_, name_new) in names.iter() {
+            validate!(!self.feed_exists(name_new).await, 
+                      AlreadyExists, &**name_new)?;
+        }
+
+        // Rename the feeds
+        for (name, name_new) in names.iter() {
+            self.feed_rename(name, name_new).await?;
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    /// Remove the namespace `ns` with all its feeds recursively.
+    pub async fn ns_remove(&self, ns: &str) -> TokioResult<()> {
+        // Get the feeds, the partitions are removed with their partitioned
+        // feeds
+        let names = self.ns_list(ns).await.iter()
+            .map(|feed_item| feed_item.get_name())
+            .collect::<Vec<String>>();
+
+        // Check whether the namespace exists
+        validate!(!names.is_empty(), NotFound, ns)?;
+
+        // Remove the feeds
+        for name in names.iter() {
+            self.feed_remove(name).await?;
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    /// Get the metadata of the feed.
+    pub async fn feed_meta_get(&self, feed_name: &str) -> TokioResult<Meta> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Get the metadata
+        Ok(self.feed_map.read().await[feed_name].meta.clone())
+    }
+
+    /// Set the description and the tags of the feed. The modification time
+    /// is updated.
+    pub async fn feed_meta_set(&self, feed_name: &str, description: &str, 
+                               tags: &HashMap<String, String>) -> 
+                               TokioResult<()> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Update the metadata
+        let mut feed_map = self.feed_map.write().await;
+        let feed_item = feed_map.get_mut(feed_name).unwrap();
+        feed_item.meta.description = description.to_string();
+        feed_item.meta.tags = tags.clone();
+        feed_item.meta.touch();
+
+        // Update feed list
+        self.feed_list.write().await
+            .modify(&feed_name.to_string(), feed_item).await?;
+
         // Ok
         Ok(())
     }
@@ -192,6 +663,14 @@ impl Conn {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the feed is not partitioned
+        self._validate_unpartitioned(feed_name).await?;
+
         // Check whether the column exists
         validate!(self.col_exists(feed_name, name).await?, NotFound, name)?;
         validate!(!self.col_exists(feed_name, name_new).await?, 
@@ -199,19 +678,27 @@ impl Conn {
 
         // Close the col
         let mut col_item = self._col_close(feed_name, name).await;
+        let file_name = col_item.get_file_name();
 
         // Run update
         let res: TokioResult<()> = {
             // Update col list
             col_item.rename(name_new)?;
+            col_item.meta.touch();
             self.col_list_mapping.write().await.get_mut(feed_name).unwrap()
                 .modify(&name.to_string(), &col_item).await?;
 
-            // Rename the seq file
-            let seq_path = Self::_get_seq_path(&self.path, feed_name, name);
-            let seq_path_new = Self::_get_seq_path(&self.path, feed_name, 
+            // Rename the series files
+            let col_path = Self::_get_col_path(&self.path, feed_name, name);
+            let col_path_new = Self::_get_col_path(&self.path, feed_name, 
                                                    name_new);
-            rename(seq_path, seq_path_new.clone()).await?;
+            let series_path = Self::_get_col_path(&self.path, feed_name,
+                                                  &file_name);
+            for path in Series::files(&series_path, &col_item).await? {
+                let path_new = format!("{}{}", col_path_new, 
+                                       &path[col_path.len()..]);
+                rename(path, path_new).await?;
+            }
 
             // Ok
             Ok(())
@@ -223,6 +710,13 @@ impl Conn {
         // Raise error if happened
         res?;
 
+        // Update the modification time of the feed
+        self._feed_touch(feed_name).await?;
+
+        // Save the operation in the history
+        self._history_push(HistoryOp::ColRename, feed_name, name, 
+                           name_new).await?;
+
         // Ok
         Ok(())
     }
@@ -230,15 +724,46 @@ impl Conn {
     /// Add a new column by its name and datatype.
     pub async fn col_add(&self, feed_name: &str, col_name: &str, 
                          datatype: &str) -> TokioResult<()> {
+        self.col_add_with(feed_name, col_name, datatype, 
+                          &ColOptions::default()).await
+    }
+
+    /// Add a new column by its name, datatype and options. The existing 
+    /// records get the default value in the column if it is set, otherwise
+    /// zeros or nulls if it is nullable. They are not stored in the column
+    /// files until some of them are updated.
+    pub async fn col_add_with(&self, feed_name: &str, col_name: &str, 
+                              datatype: &str, options: &ColOptions) -> 
+                              TokioResult<()> {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the feed is not partitioned
+        self._validate_unpartitioned(feed_name).await?;
+
         // Check whether the column exists
         validate!(!self.col_exists(feed_name, col_name).await?, 
                   AlreadyExists, col_name)?;
 
-        // Create col item
-        let col_item = ColItem::new(col_name, datatype)?;
+        // Create col item that stores the records from the current size,
+        // the segmented cols store all the records to keep the segments 
+        // aligned with the rows
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        let size = feed_item.size;
+        let mut col_item = ColItem::with_options(col_name, datatype, 
+                                                 options)?;
+        if !feed_item.is_segmented() {
+            col_item.start = size;
+        }
+
+        // Check whether the datatype is supported by the feed
+        Self::_validate_datatype(&feed_item, &col_item)?;
+        let details = col_item.get_datatype();
 
         // Add col item in the list
         self.col_list_mapping.write().await.get_mut(feed_name).unwrap()
@@ -247,10 +772,21 @@ impl Conn {
         // Open the col
         self._col_open(feed_name, col_name, col_item).await?;
 
-        // Resize the seq
-        let size = self.feed_map.read().await[feed_name].size;
-        let seq = &self.seq_mapping.read().await[feed_name][col_name];
-        seq.lock().await.resize(size).await?;
+        // Resize the series and seal its full segments
+        {
+            let series = &self.series_mapping.read().await
+                [feed_name][col_name];
+            let mut series = series.lock().await;
+            series.resize(size).await?;
+            series.seal().await?;
+        }
+
+        // Update the modification time of the feed
+        self._feed_touch(feed_name).await?;
+
+        // Save the operation in the history
+        self._history_push(HistoryOp::ColAdd, feed_name, col_name, 
+                           &details).await?;
 
         // Ok
         Ok(())
@@ -262,49 +798,336 @@ impl Conn {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the feed is not partitioned
+        self._validate_unpartitioned(feed_name).await?;
+
         // Check whether the column exists
         validate!(self.col_exists(feed_name, col_name).await?, 
                   NotFound, col_name)?;
 
         // Close the col
-        self._col_close(feed_name, col_name).await;
+        let col_item = self._col_close(feed_name, col_name).await;
 
         // Remove col item from the list
         self.col_list_mapping.write().await.get_mut(feed_name).unwrap()
             .remove(&col_name.to_string()).await?;
 
-        // Remove seq file
-        let seq_path = Self::_get_seq_path(&self.path, feed_name, col_name);
-        tokio::fs::remove_file(seq_path).await?;
+        // Remove series files
+        let col_path = Self::_get_col_path(&self.path, feed_name, 
+                                           &col_item.get_file_name());
+        for path in Series::files(&col_path, &col_item).await? {
+            tokio::fs::remove_file(path).await?;
+        }
+
+        // Update the modification time of the feed
+        self._feed_touch(feed_name).await?;
+
+        // Save the operation in the history
+        self._history_push(HistoryOp::ColRemove, feed_name, col_name, 
+                           "").await?;
+
+        // Ok
+        Ok(())
+    }
+
+    /// Change the datatype of the column rewriting its data in chunks. 
+    /// The values that cannot be cast exactly are processed according to 
+    /// `policy`. The column is locked until the end, so the rows written
+    /// meanwhile wait for the cast and go to the new files. The data are
+    /// written into the files of the next generation, and the column 
+    /// switches to them by one update of its record in the column list,
+    /// so the column stays unchanged if the cast fails or is interrupted
+    /// before that. The files of the previous generation are removed after
+    /// the switch.
+    pub async fn col_cast(&self, feed_name: &str, col_name: &str, 
+                          datatype: &str, policy: CastPolicy) -> 
+                          TokioResult<()> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the feed is not partitioned
+        self._validate_unpartitioned(feed_name).await?;
+
+        // Check whether the column exists
+        validate!(self.col_exists(feed_name, col_name).await?, 
+                  NotFound, col_name)?;
+
+        // Check whether the feed is not segmented
+        self._validate_unsegmented(feed_name).await?;
+
+        // Create the new col item keeping the options
+        let col_item = self.col_map_mapping.read().await
+            [feed_name][col_name].clone();
+        let mut options = col_item.get_options();
+        let default = options.default.take();
+        let mut col_item_new = ColItem::with_options(col_name, datatype, 
+                                                     &options)?;
+        col_item_new.start = col_item.start;
+        col_item_new.meta = col_item.meta.clone();
+        col_item_new.meta.touch();
+
+        // Check whether the cast is possible
+        validate!(is_castable(&col_item.parse_datatype(), 
+                              &col_item_new.parse_datatype()), 
+                  InvalidInput, datatype)?;
+        validate!(!self.feed_map.read().await[feed_name].is_capped() || 
+                  !col_item_new.parse_datatype().is_var(), 
+                  Unsupported, datatype)?;
+        validate!(policy != CastPolicy::Null || col_item.nullable, 
+                  InvalidInput, col_name)?;
+
+        // Cast the default value, it is dropped if it becomes null
+        if let Some(unit) = default {
+            let unit = cast(&unit, &col_item.parse_datatype(), 
+                            &col_item_new.parse_datatype(), policy)?;
+            if unit != Dataunit::Null {
+                options.default = Some(unit);
+                col_item_new = ColItem::with_options(col_name, datatype, 
+                                                     &options)?;
+                col_item_new.start = col_item.start;
+                col_item_new.meta = col_item.meta.clone();
+                col_item_new.meta.touch();
+            }
+        }
+
+        // Lock the series until the col is replaced, so the data cannot
+        // change during the cast
+        let series_arc = Arc::clone(
+            &self.series_mapping.read().await[feed_name][col_name]
+        );
+        let mut series = series_arc.lock().await;
+
+        // Write the cast data into the files of the next generation
+        col_item_new.generation = col_item.generation + 1;
+        let col_path = Self::_get_col_path(&self.path, feed_name, 
+                                           &col_item_new.get_file_name());
+        Self::_series_remove(&col_path, &col_item_new).await;
+        if let Err(err) = Self::_col_cast_data(&mut series, &col_path,
+                                               &col_item_new, policy).await {
+            Self::_series_remove(&col_path, &col_item_new).await;
+            return Err(err);
+        }
+
+        // Switch the col to the new files
+        let details = format!("{} -> {}", col_item.get_datatype(),
+                              col_item_new.get_datatype());
+        self._col_switch(feed_name, &col_item, col_item_new, 
+                         &mut series).await?;
+        drop(series);
+
+        // Update the modification time of the feed
+        self._feed_touch(feed_name).await?;
+
+        // Save the operation in the history
+        self._history_push(HistoryOp::ColCast, feed_name, col_name, 
+                           &details).await?;
+
+        // Ok
+        Ok(())
+    }
+
+    /// Get the metadata of the column.
+    pub async fn col_meta_get(&self, feed_name: &str, col_name: &str) -> 
+                              TokioResult<Meta> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Check whether the column exists
+        validate!(self.col_exists(feed_name, col_name).await?, 
+                  NotFound, col_name)?;
+
+        // Get the metadata
+        Ok(self.col_map_mapping.read().await[feed_name][col_name].meta.clone())
+    }
+
+    /// Set the description and the tags of the column. The modification 
+    /// time is updated.
+    pub async fn col_meta_set(&self, feed_name: &str, col_name: &str, 
+                              description: &str, 
+                              tags: &HashMap<String, String>) -> 
+                              TokioResult<()> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the column exists
+        validate!(self.col_exists(feed_name, col_name).await?, 
+                  NotFound, col_name)?;
+
+        // Update the metadata
+        let mut col_map_mapping = self.col_map_mapping.write().await;
+        let col_item = col_map_mapping.get_mut(feed_name).unwrap()
+            .get_mut(col_name).unwrap();
+        col_item.meta.description = description.to_string();
+        col_item.meta.tags = tags.clone();
+        col_item.meta.touch();
+
+        // Update col list
+        self.col_list_mapping.write().await.get_mut(feed_name).unwrap()
+            .modify(&col_name.to_string(), col_item).await?;
 
         // Ok
         Ok(())
     }
 
+    /// Get the dictionary of the categorical column ordered by the codes.
+    pub async fn col_dict(&self, feed_name: &str, col_name: &str) -> 
+                          TokioResult<Vec<Dataunit>> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Check whether the column exists
+        validate!(self.col_exists(feed_name, col_name).await?, 
+                  NotFound, col_name)?;
+
+        // Get the dictionary from the series
+        let series = &self.series_mapping.read().await[feed_name][col_name];
+        series.lock().await.dict()
+    }
+
     /// Get the size of the feed.
     pub async fn size_get(&self, feed_name: &str) -> TokioResult<usize> {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
+        // The size of a partitioned feed is the total size of its 
+        // partitions
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            let partition_items = self._partition_items(feed_name).await?;
+            return Ok(partition_items.last().map_or(0, |item| item.end()));
+        }
+
         // Get size
-        Ok(self.feed_map.read().await[feed_name].size)
+        Ok(self.feed_map.read().await[feed_name].end())
+    }
+
+    /// Get the index of the first row of the feed, it is not zero if
+    /// the oldest rows are removed by `feed_trim_front` keeping 
+    /// the indices.
+    pub async fn base_get(&self, feed_name: &str) -> TokioResult<usize> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Get the first index
+        Ok(self.feed_map.read().await[feed_name].first())
     }
 
     /// Change the size of the feed including the sizes of all column files.
+    /// The feed cannot be truncated before its first row.
     pub async fn size_set(&self, feed_name: &str, size: usize) -> 
                           TokioResult<usize> {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
-        // Resize all seq
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the feed is not partitioned
+        self._validate_unpartitioned(feed_name).await?;
+
+        // Check whether the feed is not capped
+        self._validate_uncapped(feed_name).await?;
+
+        // Check whether the size covers the first row
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        validate!(size >= feed_item.first(), InvalidInput, size.to_string())?;
+
+        // Change the size
+        let old_size = self._size_set(feed_name, size + feed_item.shift)
+            .await? - feed_item.shift;
+        self._feed_seal(feed_name).await?;
+
+        // Save the truncation in the history
+        if size < old_size {
+            self._history_push(HistoryOp::SizeTruncate, feed_name, "", 
+                               &format!("{} -> {}", old_size, size)).await?;
+        }
+
+        // Return
+        Ok(old_size)
+    }
+
+    /// Get the number of records in the history of the structure changes.
+    pub async fn history_size(&self) -> TokioResult<usize> {
+        self.history.lock().await.size().await
+    }
+
+    /// Get `size` records of the history of the structure changes with 
+    /// the offset `ix`.
+    pub async fn history_get(&self, ix: usize, size: usize) -> 
+                             TokioResult<Vec<HistoryItem>> {
+        let mut history = self.history.lock().await;
+
+        // Validate range
+        validate!(ix + size <= history.size().await?, 
+                  UnexpectedEof, (ix + size).to_string())?;
+
+        // Get the records
+        history.get(ix, size).await
+    }
+
+    /// Get all records of the history of the structure changes related to
+    /// the feed `feed_name` including its columns. The feed may not exist.
+    pub async fn history_feed(&self, feed_name: &str) -> 
+                              TokioResult<Vec<HistoryItem>> {
+        let mut history = self.history.lock().await;
+        let size = history.size().await?;
+
+        // Scan the history in chunks
+        let mut items = vec![];
+        for ix in (0..size).step_by(CHUNK_SIZE) {
+            items.extend(
+                history.get(ix, CHUNK_SIZE.min(size - ix)).await?
+                    .into_iter()
+                    .filter(|item| item.feed == feed_name)
+            );
+        }
+
+        // Ok
+        Ok(items)
+    }
+
+    async fn _size_set(&self, feed_name: &str, size: usize) -> 
+                       TokioResult<usize> {
+        // The start of the cols may change if the feed is truncated 
+        // before it
+        let col_names = self.col_map_mapping.read().await[feed_name]
+            .keys().cloned().collect::<Vec<String>>();
+        let moved = self._cols_started_after(feed_name, &col_names, 
+                                             size).await;
+
+        // Resize all series
         let mut js = JoinSet::new();
-        for seq in self.seq_mapping.read().await[feed_name].values() {
-            let seq_clone = Arc::clone(seq);
+        for series in self.series_mapping.read().await[feed_name].values() {
+            let series_clone = Arc::clone(series);
             js.spawn(async move {
-                seq_clone.lock().await.resize(size).await
+                series_clone.lock().await.resize(size).await
             });
         }
-        js.join_all().await;
+        let results = js.join_all().await;
+
+        self._col_start_sync(feed_name, &moved).await?;
+        for res in results {
+            res?;
+        }
+
+        // Resize the tombstones, the new rows are not deleted
+        self.tombstone_mapping.read().await[feed_name].lock().await
+            .resize(size).await?;
 
         // Change the size
         let mut feed_map = self.feed_map.write().await;
@@ -319,96 +1142,892 @@ impl Conn {
     }
 
     /// Get dataset stored in the feed `feed_name`, having the size `size`
-    /// and the columns `cols` with the offset `ix`.
+    /// and the columns `cols` with the offset `ix`. The deleted rows are
+    /// skipped.
     pub async fn data_get(&self, feed_name: &str, ix: usize, size: usize, 
                           cols: &[String]) -> TokioResult<Dataset> {
-        // Check whether the feed exists
-        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+        self.data_get_with(feed_name, ix, size, cols, 
+                           &ReadOptions::default()).await
+    }
 
-        // Validate range
-        validate!(ix + size <= self.feed_map.read().await[feed_name].size, 
-                  UnexpectedEof, (ix + size).to_string())?;
+    /// Get dataset stored in the feed `feed_name` like `data_get` does
+    /// according to `options`.
+    pub async fn data_get_with(&self, feed_name: &str, ix: usize, 
+                               size: usize, cols: &[String], 
+                               options: &ReadOptions) -> 
+                               TokioResult<Dataset> {
+        // Check whether the feed exists and hold it off the compaction
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Read the rows
+        self._data_get(feed_name, ix, size, cols, options).await
+    }
 
-        // Create a JoinSet object
-        let mut js = JoinSet::new();
+    async fn _data_get(&self, feed_name: &str, ix: usize, size: usize, 
+                       cols: &[String], options: &ReadOptions) -> 
+                       TokioResult<Dataset> {
+        // The rows of a partitioned feed are read from its partitions
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            return self._data_get_partitioned(feed_name, ix, size, cols,
+                                              options).await;
+        }
 
+        // Validate range and get the stored ranges
+        let ranges = self._feed_ranges(feed_name, ix, size).await?;
+
+        // Check whether the columns exist
         for col_name in cols.iter() {
-            // Check whether the column exists
             validate!(self.col_exists(feed_name, col_name).await?, 
                       NotFound, &**col_name)?;
+        }
 
-            // Get datatype from col item
-            let datatype = self.col_map_mapping.read().await
-                [feed_name][col_name].datatype.clone();
-
-            // Get seq object
-            let seq = &self.seq_mapping.read().await[feed_name][col_name];
-
-            // Clone the seq
-            let seq_clone = Arc::clone(seq);
-
-            // Clone col_name
-            let col_name_clone = col_name.clone();
+        // Read the columns
+        let mut ds = self._data_read(feed_name, &ranges, cols).await?;
 
-            // Spawn a concurrent task
-            js.spawn(async move {
-                let mut block = vec![0u8; size * datatype.size()];
-                seq_clone.lock().await.get(ix, &mut block).await.unwrap();
-                (block, datatype, col_name_clone)
-            });
+        // Skip the deleted rows
+        if !options.include_deleted {
+            let deleted = self._tombstone_get(feed_name, &ranges).await?;
+            if deleted.contains(&true) {
+                for values in ds.values_mut() {
+                    *values = Self::_skip_deleted(values, &deleted);
+                }
+            }
         }
 
-        // Create an empty dataset
-        let mut ds = HashMap::new();
+        Ok(ds)
+    }
+
+    /// Get the datasets of several row ranges of the feed `feed_name` given
+    /// as pairs of the first index and the size. The ranges are validated 
+    /// at once and the columns are read concurrently with each other. 
+    /// Every column is locked once, its overlapping and adjacent ranges are
+    /// merged and read one after another, so every row is read once. 
+    /// It returns a dataset per range, the deleted rows are skipped as in 
+    /// `data_get`.
+    pub async fn data_get_ranges(&self, feed_name: &str, 
+                                 ranges: &[(usize, usize)], 
+                                 cols: &[String]) -> 
+                                 TokioResult<Vec<Dataset>> {
+        // Check whether the feed exists and hold it off the compaction
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the columns exist
+        for col_name in cols.iter() {
+            validate!(self.col_exists(feed_name, col_name).await?, 
+                      NotFound, &**col_name)?;
+        }
+
+        // The ranges of a partitioned feed are validated at once and read
+        // one by one from its partitions
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            let size = self.size_get(feed_name).await?;
+            for &(ix, count) in ranges.iter() {
+                validate!(ix + count <= size,
+                          UnexpectedEof, (ix + count).to_string())?;
+            }
+            let mut datasets = vec![];
+            for &(ix, count) in ranges.iter() {
+                datasets.push(self._data_get_partitioned(
+                    feed_name, ix, count, cols, &ReadOptions::default()
+                ).await?);
+            }
+            return Ok(datasets);
+        }
+
+        // Validate the ranges and get the stored ones
+        let mut stored = vec![];
+        for &(ix, size) in ranges.iter() {
+            stored.push(self._feed_ranges(feed_name, ix, size).await?);
+        }
+        let stored = Arc::new(stored);
+
+        // Read the columns in parallel
+        let mut js = JoinSet::new();
+        for col_name in cols.iter() {
+            let series_clone = Arc::clone(
+                &self.series_mapping.read().await[feed_name][col_name]
+            );
+            let col_name_clone = col_name.clone();
+            let stored_clone = Arc::clone(&stored);
+            js.spawn(async move {
+                let values = Self::_series_get_many(&series_clone, 
+                                                    &stored_clone).await;
+                (values, col_name_clone)
+            });
+        }
 
+        // Split the values into the datasets
+        let mut datasets = vec![Dataset::new(); ranges.len()];
         while let Some(res) = js.join_next().await {
-            // Get block
-            let (block, datatype, col_name) = res?;
+            let (values, col_name) = res?;
+            for (ds, range_values) in datasets.iter_mut().zip(values?) {
+                ds.insert(col_name.clone(), range_values);
+            }
+        }
 
-            // Convert bytes to a dataset series
-            let series = block.chunks(datatype.size())
-                .map(|chunk| datatype.from_bytes(chunk))
-                .collect::<Vec<Dataunit>>();
+        // Skip the deleted rows, the tombstones are fetched at once
+        let mut deleted = self._tombstone_get(
+            feed_name, &stored.concat()
+        ).await?.into_iter();
+        for (ds, &(_, size)) in datasets.iter_mut().zip(ranges.iter()) {
+            let deleted = deleted.by_ref().take(size).collect::<Vec<bool>>();
+            if deleted.contains(&true) {
+                for values in ds.values_mut() {
+                    *values = Self::_skip_deleted(values, &deleted);
+                }
+            }
+        }
 
-            // Insert series into the dataset
-            ds.insert(col_name, series);
+        // Ok
+        Ok(datasets)
+    }
+
+    /// Get the rows of the feed `feed_name` with the indices `ixs` in 
+    /// the given order, the indices may repeat. The indices are sorted and 
+    /// the close ones are coalesced into ranges, so every column is locked 
+    /// once and read by a few range reads in parallel with the others. 
+    /// The rows cannot be skipped without breaking the order, so it returns
+    /// `NotFound` if some index points to a row marked as deleted.
+    pub async fn data_take(&self, feed_name: &str, ixs: &[usize], 
+                           cols: &[String]) -> TokioResult<Dataset> {
+        self.data_take_with(feed_name, ixs, cols, 
+                            &ReadOptions::default()).await
+    }
+
+    /// Get the rows of the feed `feed_name` like `data_take` does 
+    /// according to `options`, the deleted rows are returned if 
+    /// `options.include_deleted` is set.
+    pub async fn data_take_with(&self, feed_name: &str, ixs: &[usize], 
+                                cols: &[String], options: &ReadOptions) -> 
+                                TokioResult<Dataset> {
+        // Check whether the feed exists and hold it off the compaction
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the columns exist
+        for col_name in cols.iter() {
+            validate!(self.col_exists(feed_name, col_name).await?, 
+                      NotFound, &**col_name)?;
+        }
+
+        // The rows of a partitioned feed are taken from its partitions
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            return self._data_take_partitioned(feed_name, ixs, cols,
+                                               options).await;
+        }
+
+        // Sort the indices and coalesce the close ones into ranges, 
+        // the offsets are the positions of the sorted indices in the read
+        // values
+        let mut sorted = ixs.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let mut ranges = vec![];
+        let mut offsets = vec![];
+        for run in sorted.chunk_by(|a, b| b - a <= TAKE_GAP) {
+            let start = run[0];
+            let size = run[run.len() - 1] - start + 1;
+            let offset = offsets.last().map_or(0, |last| last + 1);
+            offsets.extend(run.iter().map(|ix| offset + ix - start));
+            ranges.extend(self._feed_ranges(feed_name, start, size).await?);
+        }
+        let positions = Arc::new(ixs.iter()
+            .map(|ix| offsets[sorted.binary_search(ix).unwrap()])
+            .collect::<Vec<usize>>());
+
+        // Check whether the rows are not deleted
+        if !options.include_deleted {
+            let deleted = self._tombstone_get(feed_name, &ranges).await?;
+            for (ix, offset) in sorted.iter().zip(offsets.iter()) {
+                validate!(!deleted[*offset], NotFound, 
+                          format!("Row {} is deleted", ix))?;
+            }
+        }
+
+        // Read the columns in parallel
+        let mut js = JoinSet::new();
+        for col_name in cols.iter() {
+            let series_clone = Arc::clone(
+                &self.series_mapping.read().await[feed_name][col_name]
+            );
+            let col_name_clone = col_name.clone();
+            let ranges_clone = ranges.clone();
+            let positions_clone = Arc::clone(&positions);
+            js.spawn(async move {
+                let values = Self::_series_get(&series_clone, &ranges_clone)
+                    .await
+                    .map(|values| positions_clone.iter()
+                        .map(|pos| values[*pos].clone())
+                        .collect::<Vec<Dataunit>>());
+                (values, col_name_clone)
+            });
+        }
+
+        // Collect the dataset
+        let mut ds = HashMap::new();
+        while let Some(res) = js.join_next().await {
+            let (values, col_name) = res?;
+            ds.insert(col_name, values?);
+        }
+
+        // Ok
+        Ok(ds)
+    }
+
+    /// Find the rows of the feed `feed_name` in the row range `range` that
+    /// match `predicate`. The data are read in chunks, the columns of every
+    /// chunk are read in parallel and the deleted rows are skipped. It
+    /// returns the indices of the matching rows and the values of 
+    /// the columns `cols` for them.
+    pub async fn data_filter(&self, feed_name: &str, predicate: &Predicate, 
+                             cols: &[String], range: Range<usize>) -> 
+                             TokioResult<(Vec<usize>, Dataset)> {
+        // Check whether the feed exists and hold it off the compaction
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the columns exist and get the datatypes
+        let pred_cols = predicate.cols();
+        let mut datatypes = HashMap::new();
+        for col_name in pred_cols.iter().chain(cols.iter()) {
+            validate!(self.col_exists(feed_name, col_name).await?, 
+                      NotFound, &**col_name)?;
+            let datatype = self.col_map_mapping.read().await
+                [feed_name][col_name].parse_datatype();
+            datatypes.insert(col_name.clone(), datatype);
+        }
+
+        // Validate range
+        let base = self.base_get(feed_name).await?;
+        let size = self.size_get(feed_name).await?;
+        validate!(range.start >= base, InvalidInput, range.start.to_string())?;
+        validate!(range.end <= size, UnexpectedEof, range.end.to_string())?;
+
+        // The partitions of a partitioned feed are filtered one by one
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            return self._data_filter_partitioned(feed_name, predicate, cols,
+                                                 range).await;
+        }
+
+        // Read the columns of the predicate and the requested ones
+        let mut cols_read = pred_cols;
+        for col_name in cols.iter() {
+            if !cols_read.contains(col_name) {
+                cols_read.push(col_name.clone());
+            }
+        }
+
+        // Evaluate the predicate chunk by chunk, the tombstones of a chunk
+        // are fetched once and the chunk is read once with the deleted rows,
+        // then the predicate is evaluated over the rows that are kept only
+        let mut ixs = vec![];
+        let mut ds: Dataset = cols.iter()
+            .map(|col_name| (col_name.clone(), vec![]))
+            .collect();
+        for ix in range.clone().step_by(CHUNK_SIZE) {
+            let count = CHUNK_SIZE.min(range.end - ix);
+            let ranges = self._feed_ranges(feed_name, ix, count).await?;
+            let deleted = self._tombstone_get(feed_name, &ranges).await?;
+            if !deleted.contains(&false) {
+                continue;
+            }
+            let chunk: Dataset = self._data_read(feed_name, &ranges,
+                                                 &cols_read).await?
+                .into_iter()
+                .map(|(col_name, values)| (col_name, values.into_iter()
+                    .zip(deleted.iter())
+                    .filter(|(_, is_deleted)| !**is_deleted)
+                    .map(|(unit, _)| unit)
+                    .collect()))
+                .collect();
+            let kept = (ix..ix + count).zip(deleted.iter())
+                .filter(|(_, is_deleted)| !**is_deleted)
+                .map(|(i, _)| i)
+                .collect::<Vec<usize>>();
+            let matched = predicate.eval(&chunk, kept.len(), &datatypes);
+            for (k, (i, is_matched)) in
+                    kept.into_iter().zip(matched).enumerate() {
+                if is_matched {
+                    ixs.push(i);
+                    for col_name in cols.iter() {
+                        ds.get_mut(col_name).unwrap()
+                            .push(chunk[col_name][k].clone());
+                    }
+                }
+            }
+        }
+
+        // Ok
+        Ok((ixs, ds))
+    }
+
+    /// Compute the aggregates `aggs` given as pairs of a column and 
+    /// a function over the rows of the feed `feed_name` in the row range 
+    /// `range`. The deleted rows are skipped, if `filter` is given only 
+    /// the matching rows are aggregated. The columns are streamed in 
+    /// chunks and every chunk is aggregated in parallel per column. 
+    /// Only `Count` is allowed for the columns that are not numbers and
+    /// for `UInt128` columns.
+    pub async fn aggregate(&self, feed_name: &str, range: Range<usize>, 
+                           aggs: &[(&str, AggFn)], 
+                           filter: Option<&Predicate>) -> 
+                           TokioResult<Vec<Dataunit>> {
+        // Check whether the feed exists and hold it off the compaction
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the columns exist and get the datatypes
+        let mut datatypes = HashMap::new();
+        let filter_cols = filter.map_or(vec![], |filter| filter.cols());
+        for col_name in filter_cols.iter().map(|col_name| col_name.as_str())
+                .chain(aggs.iter().map(|(col_name, _)| *col_name)) {
+            validate!(self.col_exists(feed_name, col_name).await?, 
+                      NotFound, col_name)?;
+            let datatype = self.col_map_mapping.read().await
+                [feed_name][col_name].parse_datatype();
+            datatypes.insert(col_name.to_string(), datatype);
+        }
+
+        // Create the accumulators
+        let mut accs = HashMap::new();
+        for (col_name, agg_fn) in aggs.iter() {
+            let datatype = &datatypes[*col_name];
+            Self::_agg_validate(datatype, *agg_fn)?;
+            accs.insert(col_name.to_string(), Accumulator::new(datatype));
+        }
+
+        // Validate range
+        let base = self.base_get(feed_name).await?;
+        let size = self.size_get(feed_name).await?;
+        validate!(range.start >= base, InvalidInput, range.start.to_string())?;
+        validate!(range.end <= size, UnexpectedEof, range.end.to_string())?;
+
+        // Aggregate the rows
+        let accs = self._aggregate_accs(feed_name, range, accs, filter, 
+                                        &datatypes).await?;
+
+        // Ok
+        aggs.iter()
+            .map(|(col_name, agg_fn)| accs[*col_name].get(*agg_fn))
+            .collect()
+    }
+
+    /// Group the rows of the feed `feed_name` in the row range `range` by
+    /// the values of the key columns `keys` and compute the aggregates 
+    /// `aggs` for every group. The key columns must be integer, bytes, 
+    /// UUID, string or categorical ones, the deleted rows are skipped. 
+    /// The result contains a row per group ordered by the keys, the columns
+    /// are named as the keys and `<fn>(<col>)` for the aggregates (like 
+    /// `sum(volume)`).
+    pub async fn group_by(&self, feed_name: &str, keys: &[&str], 
+                          aggs: &[(&str, AggFn)], 
+                          range: Range<usize>) -> TokioResult<Dataset> {
+        self.group_by_with(feed_name, keys, aggs, range, 
+                           &GroupOptions::default()).await
+    }
+
+    /// Group the rows the same as `group_by` with the options `options`.
+    /// If there are more groups than `options.max_groups`, they are 
+    /// spilled to the disk and merged in the end.
+    pub async fn group_by_with(&self, feed_name: &str, keys: &[&str], 
+                               aggs: &[(&str, AggFn)], range: Range<usize>, 
+                               options: &GroupOptions) -> 
+                               TokioResult<Dataset> {
+        // Check whether the feed exists and hold it off the compaction
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the key columns exist and have supported datatypes
+        validate!(!keys.is_empty(), InvalidInput, "No keys to group by")?;
+        let mut key_datatypes = vec![];
+        for col_name in keys.iter() {
+            validate!(self.col_exists(feed_name, col_name).await?, 
+                      NotFound, *col_name)?;
+            let datatype = self.col_map_mapping.read().await
+                [feed_name][*col_name].parse_datatype();
+            validate!(matches!(datatype, Datatype::Int64 | Datatype::Int32 | 
+                                         Datatype::Int128 | 
+                                         Datatype::UInt128 | 
+                                         Datatype::Uuid | 
+                                         Datatype::Bytes(_) | 
+                                         Datatype::VarBytes | 
+                                         Datatype::VarStr | 
+                                         Datatype::Category), 
+                      Unsupported, datatype.to_string())?;
+            key_datatypes.push(datatype);
         }
 
+        // Check whether the aggregated columns exist and get the datatypes
+        // of the accumulators
+        let mut datatypes = vec![];
+        for (col_name, agg_fn) in aggs.iter() {
+            validate!(self.col_exists(feed_name, col_name).await?, 
+                      NotFound, *col_name)?;
+            let datatype = self.col_map_mapping.read().await
+                [feed_name][*col_name].parse_datatype();
+            Self::_agg_validate(&datatype, *agg_fn)?;
+            datatypes.push(datatype);
+        }
+
+        // Validate range
+        let base = self.base_get(feed_name).await?;
+        let size = self.size_get(feed_name).await?;
+        validate!(range.start >= base, InvalidInput, range.start.to_string())?;
+        validate!(range.end <= size, UnexpectedEof, range.end.to_string())?;
+
+        // Read the keys and the aggregated columns
+        let mut cols_read = keys.iter()
+            .map(|col_name| col_name.to_string())
+            .collect::<Vec<String>>();
+        for (col_name, _) in aggs.iter() {
+            if !cols_read.iter().any(|name| name == col_name) {
+                cols_read.push(col_name.to_string());
+            }
+        }
+
+        // Collect the groups and build the dataset, the spilled files are
+        // removed whatever the result
+        let group_path = Self::_get_group_path(&self.path);
+        let mut table = GroupTable::new(&group_path, key_datatypes, 
+                                        datatypes, options.max_groups);
+        let res = self._group_by_dataset(feed_name, keys, aggs, range, 
+                                         &cols_read, &mut table).await;
+        table.clear().await?;
+        res
+    }
+
+    /// Get the rows of the partitioned feed `feed_name` that have the time
+    /// in the range `time`. Only the partitions that can contain such rows
+    /// are read, the rows are given in the order of the partitions.
+    pub async fn data_get_time(&self, feed_name: &str, time: Range<i64>, 
+                               cols: &[String]) -> TokioResult<Dataset> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Check whether the feed is partitioned
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        validate!(feed_item.is_partitioned(), Unsupported, feed_name)?;
+
+        // Check whether the columns exist
+        for col_name in cols.iter() {
+            validate!(self.col_exists(feed_name, col_name).await?, 
+                      NotFound, &**col_name)?;
+        }
+
+        // The time column is read to filter the rows
+        let time_col = &feed_item.time_col;
+        let mut cols_read = cols.to_vec();
+        if !cols_read.contains(time_col) {
+            cols_read.push(time_col.clone());
+        }
+
+        // Read the matching partitions in chunks
+        let mut ds: Dataset = cols.iter()
+            .map(|col_name| (col_name.clone(), vec![]))
+            .collect();
+        for partition_item in self._partition_items(feed_name).await?.iter()
+                .filter(|partition_item| partition_item.overlaps(&time)) {
+            let partition_name = Self::_get_partition_feed_name(
+                feed_name, partition_item
+            );
+            for ix in (0..partition_item.size).step_by(CHUNK_SIZE) {
+                let count = CHUNK_SIZE.min(partition_item.size - ix);
+                let chunk = self.data_get(&partition_name, ix, count, 
+                                          &cols_read).await?;
+                let skipped = chunk[time_col].iter()
+                    .map(|unit| !matches!(unit, 
+                                          Dataunit::I(t) if time.contains(t)))
+                    .collect::<Vec<bool>>();
+                for col_name in cols.iter() {
+                    ds.get_mut(col_name).unwrap().extend(
+                        Self::_skip_deleted(&chunk[col_name], &skipped)
+                    );
+                }
+            }
+        }
+
+        // Ok
         Ok(ds)
     }
 
-    /// Push the dataset to the feed. The missed columns will be zeros.
+    /// Get the partitions of the partitioned feed ordered by their periods.
+    pub async fn partition_list(&self, feed_name: &str) -> 
+                                TokioResult<Vec<PartitionItem>> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Check whether the feed is partitioned
+        validate!(self.feed_map.read().await[feed_name].is_partitioned(), 
+                  Unsupported, feed_name)?;
+
+        // Get the partitions
+        self._partition_items(feed_name).await
+    }
+
+    /// Mark `size` rows of the feed `feed_name` from the offset `ix` as
+    /// deleted. The rows are skipped while reading, they are removed from
+    /// the files by `feed_compact`.
+    pub async fn data_delete(&self, feed_name: &str, ix: usize, 
+                             size: usize) -> TokioResult<()> {
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        self._data_delete(feed_name, ix, size).await
+    }
+
+    async fn _data_delete(&self, feed_name: &str, ix: usize,
+                              size: usize) -> TokioResult<()> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Check whether the feed is not capped
+        self._validate_uncapped(feed_name).await?;
+
+        // The rows of a partitioned feed are marked in its partitions
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            for (partition_name, _, part_ix, count) in
+                    self._partition_pieces(feed_name, ix, size).await? {
+                Box::pin(self._data_delete(&partition_name, part_ix, count))
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        // Validate range and get the stored index
+        let ix = self._feed_ranges(feed_name, ix, size).await?[0].0;
+
+        // Mark the rows
+        self.tombstone_mapping.read().await[feed_name].lock().await
+            .fill(ix, size, true).await
+    }
+
+    /// Mark the rows of the feed `feed_name` having the indices `ixs` as
+    /// deleted like `data_delete` does. The indices may repeat and go in 
+    /// any order.
+    pub async fn data_delete_rows(&self, feed_name: &str, ixs: &[usize]) -> 
+                                  TokioResult<()> {
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        self._data_delete_rows(feed_name, ixs).await
+    }
+
+    async fn _data_delete_rows(&self, feed_name: &str, ixs: &[usize]) ->
+                                   TokioResult<()> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Check whether the feed is not capped
+        self._validate_uncapped(feed_name).await?;
+
+        // The rows of a partitioned feed are marked in its partitions,
+        // the indices are validated before that
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            for (partition_name, part_ixs) in
+                    self._partition_rows(feed_name, ixs).await? {
+                let part_ixs = part_ixs.into_iter()
+                    .map(|(_, ix)| ix)
+                    .collect::<Vec<usize>>();
+                Box::pin(self._data_delete_rows(&partition_name, &part_ixs))
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        // Validate the indices and get the stored ones
+        let shift = match (ixs.iter().min(), ixs.iter().max()) {
+            (Some(&first), Some(&last)) => self._feed_ranges(
+                feed_name, first, last - first + 1
+            ).await?[0].0 - first,
+            _ => return Ok(()),
+        };
+
+        // Group the indices into ranges of the consecutive rows
+        let mut ixs = ixs.iter().map(|ix| ix + shift).collect::<Vec<_>>();
+        ixs.sort_unstable();
+        ixs.dedup();
+        let mut ranges: Vec<Range<usize>> = vec![];
+        for ix in ixs {
+            match ranges.last_mut() {
+                Some(range) if range.end == ix => range.end += 1,
+                _ => ranges.push(ix..ix + 1),
+            }
+        }
+
+        // Mark the rows
+        let tombstone_mapping = self.tombstone_mapping.read().await;
+        let mut tombstone = tombstone_mapping[feed_name].lock().await;
+        for range in ranges {
+            tombstone.fill(range.start, range.len(), true).await?;
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    /// Check which of `size` rows of the feed `feed_name` from the offset
+    /// `ix` are marked as deleted.
+    pub async fn data_deleted(&self, feed_name: &str, ix: usize, 
+                              size: usize) -> TokioResult<Vec<bool>> {
+        // Check whether the feed exists and hold it off the compaction
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // The marks of a partitioned feed are read from its partitions
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            let mut deleted = vec![];
+            for (partition_name, _, part_ix, count) in
+                    self._partition_pieces(feed_name, ix, size).await? {
+                deleted.extend(Box::pin(self.data_deleted(
+                    &partition_name, part_ix, count
+                )).await?);
+            }
+            return Ok(deleted);
+        }
+
+        // Validate range and get the stored ranges
+        let ranges = self._feed_ranges(feed_name, ix, size).await?;
+
+        // Get the marks
+        self._tombstone_get(feed_name, &ranges).await
+    }
+
+    /// Remove the deleted rows from the feed `feed_name` rewriting all its 
+    /// columns. The compacted columns, their list and the empty tombstones
+    /// are written as the next generation of the feed, and the feed switches
+    /// to it by one update of its record in the feed list, so it stays 
+    /// unchanged if the compaction fails or is interrupted before that.
+    /// The readers and the writers of the feed wait until the compaction 
+    /// is finished.
+    /// The rows removed by `feed_trim_front` are dropped too, so the row 
+    /// indices start from zero after that. It returns the mapping of the old
+    /// row indices to the new ones.
+    pub async fn feed_compact(&self, feed_name: &str) -> 
+                              TokioResult<IndexRemap> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the feed is not partitioned
+        self._validate_unpartitioned(feed_name).await?;
+
+        // Check whether the feed is not capped or segmented
+        self._validate_uncapped(feed_name).await?;
+        self._validate_unsegmented(feed_name).await?;
+
+        // Keep the readers and the writers waiting until the feed is 
+        // switched
+        let _guard = self._feed_guard(feed_name).await?.write_owned().await;
+
+        // Collect the kept stored rows from the tombstones in chunks
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        let size = feed_item.size;
+        let mut remap = IndexRemap::new();
+        for ix in (feed_item.base..size).step_by(CHUNK_SIZE) {
+            let deleted = self._tombstone_get(
+                feed_name, &[(ix, CHUNK_SIZE.min(size - ix))]
+            ).await?;
+            for (i, is_deleted) in (ix..).zip(deleted) {
+                if !is_deleted {
+                    remap.push(i..i + 1);
+                }
+            }
+        }
+
+        // Nothing to do if no rows are deleted or trimmed
+        if remap.size() == size {
+            return Ok(remap);
+        }
+
+        // Create the items of the next generation
+        let col_items = self.col_map_mapping.read().await[feed_name]
+            .values().cloned().collect::<Vec<ColItem>>();
+        let col_items_new = col_items.iter()
+            .map(|col_item| {
+                let mut col_item_new = col_item.clone();
+                col_item_new.start = remap.count_before(col_item.start);
+                col_item_new.generation += 1;
+                col_item_new
+            })
+            .collect::<Vec<ColItem>>();
+        let mut feed_item_new = feed_item.clone();
+        feed_item_new.size = remap.size();
+        feed_item_new.base = 0;
+        feed_item_new.shift = 0;
+        feed_item_new.generation += 1;
+        feed_item_new.meta.touch();
+
+        // Write the next generation and switch the feed to it by updating
+        // the feed list
+        let res = match self._feed_compact_write(
+            &feed_item_new, &col_items_new, &remap
+        ).await {
+            Ok(()) => self.feed_list.write().await
+                .modify(&feed_name.to_string(), &feed_item_new).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            self._feed_generation_remove(&feed_item_new, &col_items_new)
+                .await;
+            return Err(err);
+        }
+
+        // Reopen the feed in place and remove the previous generation
+        self._feed_switch(feed_item_new, col_items_new).await?;
+        self._feed_generation_remove(&feed_item, &col_items).await;
+
+        // Save the operation in the history
+        let details = format!("{} -> {}", feed_item.end(), remap.size());
+        self._history_push(HistoryOp::FeedCompact, feed_name, "", 
+                           &details).await?;
+
+        // Return the mapping of the row indices instead of the stored ones
+        let mut remap_ix = IndexRemap::new();
+        for range in remap.ranges() {
+            remap_ix.push(range.start - feed_item.shift..
+                          range.end - feed_item.shift);
+        }
+        Ok(remap_ix)
+    }
+
+    /// Remove `count` oldest rows of the feed `feed_name`. The data are not
+    /// moved, the first stored row is saved instead and the disk space of 
+    /// the removed rows is released by punching holes in the column files
+    /// if the file system supports it. For the segmented feeds, the segments
+    /// that contain the removed rows only are dropped. The indices of 
+    /// the remaining rows change according to `mode`.
+    pub async fn feed_trim_front(&self, feed_name: &str, count: usize, 
+                                 mode: TrimMode) -> TokioResult<()> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the feed is not partitioned
+        self._validate_unpartitioned(feed_name).await?;
+
+        // Check whether the feed is not capped
+        self._validate_uncapped(feed_name).await?;
+
+        // Validate the number of the rows
+        let base = {
+            let mut feed_map = self.feed_map.write().await;
+            let feed_item = feed_map.get_mut(feed_name).unwrap();
+            let base = feed_item.base;
+            validate!(base + count <= feed_item.size, 
+                      UnexpectedEof, count.to_string())?;
+
+            // Save the new base before the data are released
+            feed_item.base += count;
+            if mode == TrimMode::Renumber {
+                feed_item.shift = feed_item.base;
+            }
+            feed_item.meta.touch();
+            self.feed_list.write().await
+                .modify(&feed_name.to_string(), feed_item).await?;
+            base
+        };
+
+        // Release the disk space of the removed rows in all series
+        let mut js = JoinSet::new();
+        for series in self.series_mapping.read().await[feed_name].values() {
+            let series_clone = Arc::clone(series);
+            js.spawn(async move {
+                series_clone.lock().await.punch(base, count).await
+            });
+        }
+        let results = js.join_all().await;
+        self.tombstone_mapping.read().await[feed_name].lock().await
+            .punch(base, count).await?;
+        for res in results {
+            res?;
+        }
+
+        // Save the operation in the history
+        self._history_push(HistoryOp::FeedTrim, feed_name, "", 
+                           &count.to_string()).await?;
+
+        // Ok
+        Ok(())
+    }
+
+    /// Push the dataset to the feed. The missed columns will be the default
+    /// values, zeros or nulls for nullable columns. If the feed is capped,
+    /// the oldest rows are overwritten when it is full, so they cannot be
+    /// restored in case of failure.
     pub async fn data_push(&self, feed_name: &str, ds: &Dataset) -> 
                            TokioResult<()> {
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        self._data_push(feed_name, ds).await
+    }
+
+    async fn _data_push(&self, feed_name: &str, ds: &Dataset) ->
+                            TokioResult<()> {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
         // Get the dataset size
         let size = get_dataset_size(ds)?;
 
+        // Check whether the values match the columns, so the feed is not
+        // changed if the dataset is invalid
+        self._data_validate(feed_name, ds).await?;
+
+        // The capped feeds overwrite the oldest rows, the partitioned feeds
+        // route the rows to the partitions
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        if feed_item.is_capped() {
+            return self._data_push_capped(feed_name, ds, size).await;
+        }
+        if feed_item.is_partitioned() {
+            return self._data_push_partitioned(feed_name, ds, size).await;
+        }
+
         // If the dataset is not empty
         if size > 0 {
             // Get the current feed size into ix
-            let ix = self.feed_map.read().await[feed_name].size;
+            let ix = feed_item.size;
 
             // Update the size of all cols
-            self.size_set(feed_name, ix + size).await?;
+            self._size_set(feed_name, ix + size).await?;
 
             // Insert the data from the dataset
-            self.data_patch(feed_name, ix, ds).await?;
+            let cols = ds.keys().cloned().collect::<Vec<String>>();
+            self._data_update(feed_name, ix - feed_item.shift, ds,
+                              &cols).await?;
+
+            // Seal the segments that became full
+            self._feed_seal(feed_name).await?;
         }
 
         Ok(())
     }
 
     /// Update the records in the feed with the given dataset. The missing
-    /// columns will be filled with zeros. For preventing it use `data_patch`
-    /// instead.
+    /// columns will be filled with the default values or zeros (nulls for 
+    /// nullable columns). 
+    /// For preventing it use `data_patch` instead.
     pub async fn data_save(&self, feed_name: &str, ix: usize, 
                            ds: &Dataset) -> TokioResult<()> {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the values match the columns
+        self._data_validate(feed_name, ds).await?;
+
         // Get all columns
         let cols = self.col_map_mapping.read().await[feed_name]
             .keys().cloned().collect::<Vec<String>>();
@@ -428,6 +2047,14 @@ impl Conn {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        // Check whether the values match the columns
+        self._data_validate(feed_name, ds).await?;
+
         // Get dataset columns
         let cols = ds.keys().cloned().collect::<Vec<String>>();
 
@@ -439,31 +2066,64 @@ impl Conn {
     }
 
     /// Get raw bytes having the size `size` (in data units) of the column 
-    /// `col_name` in the feed `feed_name` with the offset `ix`.
+    /// `col_name` in the feed `feed_name` with the offset `ix`. The deleted
+    /// rows are skipped.
     pub async fn raw_get(&self, feed_name: &str, col_name: &str, ix: usize, 
                          size: usize) -> TokioResult<Vec<u8>> {
-        // Check whether the feed exists
-        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+        self.raw_get_with(feed_name, col_name, ix, size, 
+                          &ReadOptions::default()).await
+    }
+
+    /// Get raw bytes of the column `col_name` in the feed `feed_name` like
+    /// `raw_get` does according to `options`.
+    pub async fn raw_get_with(&self, feed_name: &str, col_name: &str, 
+                              ix: usize, size: usize, 
+                              options: &ReadOptions) -> TokioResult<Vec<u8>> {
+        // Check whether the feed exists and hold it off the compaction
+        let _guard = self._feed_guard_read(feed_name).await?;
 
         // Check whether the column exists
         validate!(self.col_exists(feed_name, col_name).await?, 
                   NotFound, col_name)?;
 
-        // Validate range
-        validate!(ix + size <= self.feed_map.read().await[feed_name].size, 
-                  UnexpectedEof, (ix + size).to_string())?;
+        // The bytes of a partitioned feed are read from its partitions
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            let mut block = vec![];
+            for (partition_name, _, part_ix, count) in
+                    self._partition_pieces(feed_name, ix, size).await? {
+                block.extend(Box::pin(self.raw_get_with(
+                    &partition_name, col_name, part_ix, count, options
+                )).await?);
+            }
+            return Ok(block);
+        }
 
-        // Get seq object
-        let seq = &self.seq_mapping.read().await[feed_name][col_name];
+        // Validate range and get the stored ranges
+        let ranges = self._feed_ranges(feed_name, ix, size).await?;
 
-        // Get col item because we need the datatype
-        let col_item = &self.col_map_mapping
-            .read().await[feed_name][col_name];
+        // Get series object
+        let series_arc = &self.series_mapping.read().await
+            [feed_name][col_name];
+        let mut series = series_arc.lock().await;
 
-        // Get bytes from the seq file into a buffer
-        let mut block = vec![0u8; size * col_item.datatype.size()];
-        seq.lock().await.get(ix, &mut block).await?;
+        // Get bytes from the series into a buffer
+        let mut block = vec![];
+        for &(start, count) in ranges.iter() {
+            block.extend(series.get_raw(start, count).await?);
+        }
+        drop(series);
+
+        // Skip the deleted rows
+        if !options.include_deleted {
+            let deleted = self._tombstone_get(feed_name, &ranges).await?;
+            if deleted.contains(&true) {
+                let chunks = block.chunks(block.len() / size)
+                    .collect::<Vec<&[u8]>>();
+                return Ok(Self::_skip_deleted(&chunks, &deleted).concat());
+            }
+        }
 
+        // Ok
         Ok(block)
     }
 
@@ -471,6 +2131,16 @@ impl Conn {
     /// of the feed `feed_name` with the offset `ix`.
     pub async fn raw_set(&self, feed_name: &str, col_name: &str, ix: usize, 
                          block: &[u8]) -> TokioResult<()> {
+        let _guard = self._feed_guard_read(feed_name).await?;
+
+        // Check whether the feed is not a partition
+        self._validate_not_partition(feed_name).await?;
+
+        self._raw_set(feed_name, col_name, ix, block).await
+    }
+
+    async fn _raw_set(&self, feed_name: &str, col_name: &str, ix: usize,
+                          block: &[u8]) -> TokioResult<()> {
         // Check whether the feed exists
         validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
 
@@ -478,101 +2148,1052 @@ impl Conn {
         validate!(self.col_exists(feed_name, col_name).await?, 
                   NotFound, col_name)?;
 
-        // Get seq object
-        let seq_arc = &self.seq_mapping.read().await[feed_name][col_name];
-        let mut seq = seq_arc.lock().await;
+        // The bytes of a partitioned feed are written to its partitions,
+        // the time column cannot be changed
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        if feed_item.is_partitioned() {
+            validate!(col_name != feed_item.time_col, InvalidInput, col_name)?;
+            let series = Arc::clone(
+                &self.series_mapping.read().await[feed_name][col_name]
+            );
+            let series = series.lock().await;
+            let (size, unit_size) = (series.raw_size(block),
+                                     series.datatype().size());
+            drop(series);
+            for (partition_name, start, part_ix, count) in
+                    self._partition_pieces(feed_name, ix, size).await? {
+                let offset = (start + part_ix - ix) * unit_size;
+                Box::pin(self._raw_set(
+                    &partition_name, col_name, part_ix,
+                    &block[offset..offset + count * unit_size]
+                )).await?;
+            }
+            return Ok(());
+        }
+
+        // Get series object
+        let series_arc = Arc::clone(
+            &self.series_mapping.read().await[feed_name][col_name]
+        );
+        let mut series = series_arc.lock().await;
+
+        // Validate range and get the stored ranges
+        let size = series.raw_size(block);
+        let ranges = self._feed_ranges(feed_name, ix, size).await?;
+
+        // The start of the col may change if earlier records are updated
+        let first = ranges.iter().map(|&(start, _)| start).min();
+        let moved = match first {
+            Some(first) if size > 0 => self._cols_started_after(
+                feed_name, &[col_name.to_string()], first
+            ).await,
+            _ => vec![],
+        };
 
-        // Validate range
-        let end = ix + block.len() / seq.block_size();
-        validate!(end <= self.feed_map.read().await[feed_name].size, 
-                  UnexpectedEof, end.to_string())?;
+        // Update the series with the parts of the block
+        let unit_size = series.datatype().size();
+        let mut res = Ok(());
+        let mut offset = 0;
+        for (start, count) in ranges {
+            let end = offset + count * unit_size;
+            res = series.update_raw(start, &block[offset..end]).await;
+            if res.is_err() {
+                break;
+            }
+            offset = end;
+        }
+        drop(series);
 
-        // Update the seq file with the block
-        seq.update(ix, block).await?;
+        self._col_start_sync(feed_name, &moved).await?;
+        res?;
 
         // Ok
         Ok(())
     }
 
-    async fn _data_update(&self, feed_name: &str, ix: usize, ds: &Dataset, 
-                          cols: &[String]) -> TokioResult<()> {
-        // Get dataset size, it also check where the dataset is valid: 
-        // all series have the same size
-        let size = get_dataset_size(ds)?;
+    async fn _data_push_capped(&self, feed_name: &str, ds: &Dataset, 
+                               size: usize) -> TokioResult<()> {
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        let cap = feed_item.cap;
 
-        // Validate range
-        validate!(ix + size <= self.feed_map.read().await[feed_name].size, 
-                  UnexpectedEof, (ix + size).to_string())?;
+        // Only the last rows that fit the capacity are written
+        let skip = size.saturating_sub(cap);
+        let count = size - skip;
+        let size_new = (feed_item.size + count).min(cap);
+        let overwritten = feed_item.size + size - size_new;
+
+        // Grow the files up to the capacity
+        if size_new > feed_item.size {
+            self._size_set(feed_name, size_new).await?;
+        }
+
+        // Move the head over the overwritten and the skipped rows
+        if overwritten > 0 {
+            let mut feed_map = self.feed_map.write().await;
+            let feed_item = feed_map.get_mut(feed_name).unwrap();
+            feed_item.head = (feed_item.head + overwritten) % cap;
+            self.feed_list.write().await
+                .modify(&feed_name.to_string(), feed_item).await?;
+        }
+
+        // Write the rows as the newest ones, the missing columns are
+        // cleared because the overwritten rows keep the old values
+        let ds = ds.iter()
+            .map(|(col_name, values)| (col_name.clone(), 
+                                       values[skip..].to_vec()))
+            .collect::<Dataset>();
+        let cols = self.col_map_mapping.read().await[feed_name]
+            .keys().cloned().collect::<Vec<String>>();
+        self._data_update(feed_name, size_new - count, &ds, &cols).await
+    }
+
+    async fn _data_validate(&self, feed_name: &str, ds: &Dataset) ->
+                            TokioResult<()> {
+        // The values of the missing columns are ignored
+        for (col_name, values) in ds.iter() {
+            let series = self.series_mapping.read().await[feed_name]
+                .get(col_name).cloned();
+            if let Some(series) = series {
+                series.lock().await.validate(values)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn _data_update(&self, feed_name: &str, ix: usize, ds: &Dataset, 
+                          cols: &[String]) -> TokioResult<()> {
+        // Get dataset size, it also check where the dataset is valid: 
+        // all series have the same size
+        let size = get_dataset_size(ds)?;
+
+        // The rows of a partitioned feed are updated in its partitions,
+        // the time column cannot be changed
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        if feed_item.is_partitioned() {
+            validate!(!ds.contains_key(&feed_item.time_col),
+                      InvalidInput, &*feed_item.time_col)?;
+            let cols = cols.iter()
+                .filter(|col_name| **col_name != feed_item.time_col)
+                .cloned()
+                .collect::<Vec<String>>();
+            for (partition_name, start, part_ix, count) in
+                    self._partition_pieces(feed_name, ix, size).await? {
+                let offset = start + part_ix - ix;
+                let partition_ds = ds.iter()
+                    .map(|(col_name, values)| (
+                        col_name.clone(),
+                        values[offset..offset + count].to_vec()
+                    ))
+                    .collect::<Dataset>();
+                Box::pin(self._data_update(&partition_name, part_ix,
+                                           &partition_ds, &cols)).await?;
+            }
+            return Ok(());
+        }
+
+        // Validate range and get the stored ranges
+        let ranges = self._feed_ranges(feed_name, ix, size).await?;
 
         // If the dataset is not empty
         if size > 0 {
+            // The start of the cols may change if earlier records are 
+            // updated
+            let first = ranges.iter().map(|&(start, _)| start).min()
+                .unwrap();
+            let moved = self._cols_started_after(feed_name, cols, 
+                                                 first).await;
+
             // Create a join set
             let mut js = JoinSet::new();
 
             // Iterate the colunms
             for col_name in cols.iter() {
-                // Get col item because we need the datatype
-                if let Some(col_item) = &self.col_map_mapping.read()
-                                             .await[feed_name].get(col_name) {
-                    // Convert the series into a byte sequence
-                    let block = if let Some(series) = ds.get(col_name) {
-                        series.iter().map(
-                            |unit| col_item.datatype.to_bytes(unit).unwrap()
-                        ).collect::<Vec<Vec<u8>>>().concat()
-                    } else {
-                        vec![0u8; size * col_item.datatype.size()]
-                    };
-
-                    // Get seq object
-                    let seq = &self.seq_mapping.read()
-                                   .await[feed_name][col_name];
-
-                    // Clone the seq
-                    let seq_clone = Arc::clone(seq);
-
-                    // Update the seq file with the block in parralel
+                // Get series object if the column exists
+                if let Some(series) = self.series_mapping.read()
+                                          .await[feed_name].get(col_name) {
+                    // Clone the values of the column if they are given
+                    let values = ds.get(col_name).cloned();
+
+                    // Clone the series and the ranges
+                    let series_clone = Arc::clone(series);
+                    let ranges_clone = ranges.clone();
+
+                    // Update the series with the values in parralel
                     js.spawn(async move {
-                        seq_clone.lock().await.update(ix, &block).await
+                        let mut series = series_clone.lock().await;
+                        let mut offset = 0;
+                        for (start, count) in ranges_clone {
+                            let end = offset + count;
+                            if let Some(values) = &values {
+                                series.update(start, 
+                                              &values[offset..end]).await?;
+                            } else {
+                                series.clear(start, count).await?;
+                            }
+                            offset = end;
+                        }
+                        TokioResult::Ok(())
                     });
                 }
             }
 
             // Execute in parralel
-            js.join_all().await;
+            let results = js.join_all().await;
+
+            self._col_start_sync(feed_name, &moved).await?;
+            for res in results {
+                res?;
+            }
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _col_cast_data(series: &mut Series, col_path: &str,
+                            col_item_new: &ColItem,
+                            policy: CastPolicy) -> TokioResult<()> {
+        let datatype = series.datatype().clone();
+
+        // Create the series of the new generation of the same size
+        let size = series.size().await?;
+        let mut series_new = Series::new(col_path, col_item_new).await?;
+        series_new.resize(size).await?;
+        let datatype_new = series_new.datatype().clone();
+
+        // Cast the stored data in chunks
+        for ix in (series.start()..size).step_by(CHUNK_SIZE) {
+            let values = series.get(ix, CHUNK_SIZE.min(size - ix)).await?
+                .iter()
+                .map(|unit| cast(unit, &datatype, &datatype_new, policy))
+                .collect::<TokioResult<Vec<Dataunit>>>()?;
+            series_new.update(ix, &values).await?;
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _col_switch(&self, feed_name: &str, col_item: &ColItem,
+                         col_item_new: ColItem,
+                         series: &mut Series) -> TokioResult<()> {
+        // Update the col list, it is the only write that switches the col
+        // to the new files
+        let col_path_new = Self::_get_col_path(&self.path, feed_name, 
+                                               &col_item_new.get_file_name());
+        if let Err(err) = self.col_list_mapping.write().await
+                .get_mut(feed_name).unwrap()
+                .modify(&col_item.name, &col_item_new).await {
+            Self::_series_remove(&col_path_new, &col_item_new).await;
+            return Err(err);
+        }
+
+        // Reopen the locked series in place, so the writers waiting for it
+        // use the new files
+        *series = self._series_open(feed_name, &col_item_new).await?;
+        self.col_map_mapping.write().await.get_mut(feed_name).unwrap()
+            .insert(col_item.name.clone(), col_item_new);
+
+        // Remove the files of the previous generation
+        let col_path = Self::_get_col_path(&self.path, feed_name, 
+                                           &col_item.get_file_name());
+        Self::_series_remove(&col_path, col_item).await;
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _series_remove(col_path: &str, col_item: &ColItem) {
+        // The files are removed if they exist, they are not used anyway
+        if let Ok(paths) = Series::files(col_path, col_item).await {
+            for path in paths {
+                let _ = remove_file(path).await;
+            }
+        }
+    }
+
+    async fn _feed_ranges(&self, feed_name: &str, ix: usize, 
+                          size: usize) -> 
+                          TokioResult<Vec<(usize, usize)>> {
+        let feed_map = self.feed_map.read().await;
+        let feed_item = &feed_map[feed_name];
+
+        // The trimmed rows are unavailable
+        validate!(ix >= feed_item.first(), InvalidInput, ix.to_string())?;
+        validate!(ix + size <= feed_item.end(), 
+                  UnexpectedEof, (ix + size).to_string())?;
+
+        // The rows of a capped feed start from the head and wrap around
+        // the end of the files
+        if feed_item.is_capped() {
+            let start = (feed_item.head + ix) % feed_item.cap;
+            if start + size > feed_item.cap {
+                let count = feed_item.cap - start;
+                return Ok(vec![(start, count), (0, size - count)]);
+            }
+            return Ok(vec![(start, size)]);
+        }
+
+        // Return the range of the stored rows
+        Ok(vec![(ix + feed_item.shift, size)])
+    }
+
+    async fn _data_push_partitioned(&self, feed_name: &str, ds: &Dataset, 
+                                    size: usize) -> TokioResult<()> {
+        if size == 0 {
+            return Ok(());
+        }
+
+        // Get the time of the rows, it must be given
+        let feed_item = self.feed_map.read().await[feed_name].clone();
+        let times = ds.get(&feed_item.time_col)
+            .and_then(|values| values.iter()
+                .map(|unit| match unit {
+                    Dataunit::I(t) => Some(*t),
+                    _ => None,
+                })
+                .collect::<Option<Vec<i64>>>());
+        validate!(times.is_some(), InvalidData, &*feed_item.time_col)?;
+        let times = times.unwrap();
+
+        // Group the rows by the partitions
+        let mut groups = BTreeMap::<i64, Vec<usize>>::new();
+        for (ix, time) in times.iter().enumerate() {
+            groups.entry(time.div_euclid(feed_item.period as i64))
+                .or_default().push(ix);
+        }
+
+        // Push the rows partition by partition
+        let schema = self.feed_schema(feed_name).await?;
+        for (key, ixs) in groups {
+            // Get the partition creating it if it is new
+            let exists = self.partition_mapping.read().await
+                [feed_name].exists(&key);
+            let mut partition_item = if exists {
+                self.partition_mapping.write().await.get_mut(feed_name)
+                    .unwrap().detail(&key).await?
+            } else {
+                PartitionItem::new(key)
+            };
+            let partition_name = Self::_get_partition_feed_name(
+                feed_name, &partition_item
+            );
+            if !exists {
+                self._feed_create(FeedItem::new(&partition_name)?, &schema)
+                    .await?;
+                self.partition_mapping.write().await.get_mut(feed_name)
+                    .unwrap().add(&partition_item).await?;
+            }
+
+            // Push the rows of the partition
+            let partition_ds = ds.iter()
+                .map(|(col_name, values)| (
+                    col_name.clone(), 
+                    ixs.iter().map(|&ix| values[ix].clone()).collect()
+                ))
+                .collect::<Dataset>();
+            Box::pin(self._data_push(&partition_name, &partition_ds)).await?;
+
+            // Save the size and the time bounds of the partition
+            partition_item.size += ixs.len();
+            for &ix in ixs.iter() {
+                partition_item.time_min = partition_item.time_min
+                    .min(times[ix]);
+                partition_item.time_max = partition_item.time_max
+                    .max(times[ix]);
+            }
+            self.partition_mapping.write().await.get_mut(feed_name).unwrap()
+                .modify(&key, &partition_item).await?;
+        }
+
+        // Update the first rows of the partitions
+        let mut start = 0;
+        for mut partition_item in self._partition_items(feed_name).await? {
+            if partition_item.start != start {
+                partition_item.start = start;
+                self.partition_mapping.write().await.get_mut(feed_name)
+                    .unwrap().modify(&partition_item.key, &partition_item)
+                    .await?;
+            }
+            start += partition_item.size;
         }
 
         // Ok
         Ok(())
     }
 
+    async fn _data_get_partitioned(&self, feed_name: &str, ix: usize,
+                                   size: usize, cols: &[String],
+                                   options: &ReadOptions) ->
+                                   TokioResult<Dataset> {
+        // Validate range and split it into the partitions
+        let pieces = self._partition_pieces(feed_name, ix, size).await?;
+
+        // Check whether the columns exist
+        for col_name in cols.iter() {
+            validate!(self.col_exists(feed_name, col_name).await?,
+                      NotFound, &**col_name)?;
+        }
+
+        // Read the rows from the partitions that cover the range
+        let mut ds: Dataset = cols.iter()
+            .map(|col_name| (col_name.clone(), vec![]))
+            .collect();
+        for (partition_name, _, part_ix, count) in pieces {
+            let partition_ds = Box::pin(self.data_get_with(
+                &partition_name, part_ix, count, cols, options
+            )).await?;
+            for (col_name, values) in partition_ds.into_iter() {
+                ds.get_mut(&col_name).unwrap().extend(values);
+            }
+        }
+
+        // Ok
+        Ok(ds)
+    }
+
+    async fn _data_take_partitioned(&self, feed_name: &str, ixs: &[usize],
+                                    cols: &[String],
+                                    options: &ReadOptions) ->
+                                    TokioResult<Dataset> {
+        // Take the rows from the partitions and put them in place
+        let mut ds: Dataset = cols.iter()
+            .map(|col_name| (col_name.clone(),
+                             vec![Dataunit::Null; ixs.len()]))
+            .collect();
+        for (partition_name, rows) in
+                self._partition_rows(feed_name, ixs).await? {
+            let part_ixs = rows.iter()
+                .map(|&(_, ix)| ix)
+                .collect::<Vec<usize>>();
+            let partition_ds = Box::pin(self.data_take_with(
+                &partition_name, &part_ixs, cols, options
+            )).await?;
+            for (col_name, values) in partition_ds.into_iter() {
+                let col_values = ds.get_mut(&col_name).unwrap();
+                for (&(pos, _), unit) in rows.iter().zip(values) {
+                    col_values[pos] = unit;
+                }
+            }
+        }
+
+        // Ok
+        Ok(ds)
+    }
+
+    async fn _data_filter_partitioned(&self, feed_name: &str,
+                                      predicate: &Predicate, cols: &[String],
+                                      range: Range<usize>) ->
+                                      TokioResult<(Vec<usize>, Dataset)> {
+        // Filter the partitions that cover the range
+        let mut ixs = vec![];
+        let mut ds: Dataset = cols.iter()
+            .map(|col_name| (col_name.clone(), vec![]))
+            .collect();
+        for (partition_name, start, part_ix, count) in self._partition_pieces(
+            feed_name, range.start, range.len()
+        ).await? {
+            let (partition_ixs, partition_ds) = Box::pin(self.data_filter(
+                &partition_name, predicate, cols, part_ix..part_ix + count
+            )).await?;
+            ixs.extend(partition_ixs.into_iter().map(|ix| start + ix));
+            for (col_name, values) in partition_ds.into_iter() {
+                ds.get_mut(&col_name).unwrap().extend(values);
+            }
+        }
+
+        // Ok
+        Ok((ixs, ds))
+    }
+
+    async fn _partition_pieces(&self, feed_name: &str, ix: usize,
+                               size: usize) ->
+                               TokioResult<Vec<PartitionPiece>> {
+        // Validate range
+        let partition_items = self._partition_items(feed_name).await?;
+        let end = partition_items.last().map_or(0, |item| item.end());
+        validate!(ix + size <= end, UnexpectedEof, (ix + size).to_string())?;
+
+        // Split the range into the pieces of the partitions that cover it
+        Ok(partition_items.iter()
+            .filter(|item| item.start < ix + size && item.end() > ix)
+            .map(|item| {
+                let from = ix.max(item.start);
+                let to = (ix + size).min(item.end());
+                (Self::_get_partition_feed_name(feed_name, item),
+                 item.start, from - item.start, to - from)
+            })
+            .collect())
+    }
+
+    async fn _partition_rows(&self, feed_name: &str, ixs: &[usize]) ->
+                             TokioResult<Vec<(String,
+                                              Vec<(usize, usize)>)>> {
+        // Find the partitions of the indices, the rows are kept as
+        // the positions in `ixs` and the indices in the partitions
+        let partition_items = self._partition_items(feed_name).await?;
+        let mut parts = BTreeMap::<usize, Vec<(usize, usize)>>::new();
+        for (pos, &ix) in ixs.iter().enumerate() {
+            let part = partition_items.partition_point(|item| {
+                item.end() <= ix
+            });
+            validate!(part < partition_items.len(),
+                      UnexpectedEof, ix.to_string())?;
+            parts.entry(part).or_default()
+                .push((pos, ix - partition_items[part].start));
+        }
+
+        // Ok
+        Ok(parts.into_iter()
+            .map(|(part, rows)| (
+                Self::_get_partition_feed_name(feed_name,
+                                               &partition_items[part]),
+                rows
+            ))
+            .collect())
+    }
+
+    async fn _aggregate_accs(&self, feed_name: &str, range: Range<usize>, 
+                             mut accs: HashMap<String, Accumulator>, 
+                             filter: Option<&Predicate>, 
+                             datatypes: &HashMap<String, Datatype>) -> 
+                             TokioResult<HashMap<String, Accumulator>> {
+        // The partitions of a partitioned feed are aggregated one by one
+        // and their accumulators are merged
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            for (partition_name, _, part_ix, count) in self._partition_pieces(
+                feed_name, range.start, range.len()
+            ).await? {
+                let empty = accs.iter()
+                    .map(|(col_name, acc)| {
+                        (col_name.clone(), Accumulator::new(acc.datatype()))
+                    })
+                    .collect();
+                let partition_accs = Box::pin(self._aggregate_accs(
+                    &partition_name, part_ix..part_ix + count, empty, filter,
+                    datatypes
+                )).await?;
+                for (col_name, acc) in partition_accs.iter() {
+                    accs.get_mut(col_name).unwrap().merge(acc);
+                }
+            }
+            return Ok(accs);
+        }
+
+        let options = ReadOptions { include_deleted: true };
+        let filter_cols = filter.map_or(vec![], |filter| filter.cols());
+
+        for ix in range.clone().step_by(CHUNK_SIZE) {
+            let count = CHUNK_SIZE.min(range.end - ix);
+            let ranges = self._feed_ranges(feed_name, ix, count).await?;
+
+            // Get the mask of the aggregated rows
+            let mut mask = self._tombstone_get(feed_name, &ranges).await?
+                .into_iter()
+                .map(|is_deleted| !is_deleted)
+                .collect::<Vec<bool>>();
+            if let Some(filter) = filter {
+                let chunk = self._data_get(feed_name, ix, count, 
+                                           &filter_cols, &options).await?;
+                for (m, x) in mask.iter_mut()
+                        .zip(filter.eval(&chunk, count, datatypes)) {
+                    *m &= x;
+                }
+            }
+            if !mask.contains(&true) {
+                continue;
+            }
+            let mask = Arc::new(mask);
+
+            // Aggregate the columns in parallel
+            let mut js = JoinSet::new();
+            for (col_name, mut acc) in accs.drain() {
+                let series_clone = Arc::clone(
+                    &self.series_mapping.read().await[feed_name][&col_name]
+                );
+                let ranges_clone = ranges.clone();
+                let mask_clone = Arc::clone(&mask);
+                js.spawn(async move {
+                    let values = Self::_series_get(&series_clone, 
+                                                   &ranges_clone).await;
+                    if let Ok(values) = &values {
+                        for (unit, _) in values.iter().zip(mask_clone.iter())
+                                .filter(|(_, is_masked)| **is_masked) {
+                            acc.push(unit);
+                        }
+                    }
+                    (values.map(|_| acc), col_name)
+                });
+            }
+            while let Some(res) = js.join_next().await {
+                let (acc, col_name) = res?;
+                accs.insert(col_name, acc?);
+            }
+        }
+
+        // Ok
+        Ok(accs)
+    }
+
+    async fn _group_by_dataset(&self, feed_name: &str, keys: &[&str], 
+                               aggs: &[(&str, AggFn)], range: Range<usize>, 
+                               cols_read: &[String], 
+                               table: &mut GroupTable) -> 
+                               TokioResult<Dataset> {
+        // Collect the groups chunk by chunk
+        self._group_by_table(feed_name, keys, aggs, range, cols_read, 
+                             table).await?;
+        let groups = table.finish().await?;
+
+        // Build the dataset
+        let mut ds = Dataset::new();
+        for (i, col_name) in keys.iter().enumerate() {
+            ds.insert(col_name.to_string(), groups.iter()
+                .map(|(group_keys, _)| group_keys[i].clone())
+                .collect());
+        }
+        for (i, (col_name, agg_fn)) in aggs.iter().enumerate() {
+            ds.insert(format!("{}({})", agg_fn, col_name), groups.iter()
+                .map(|(_, accs)| accs[i].get(*agg_fn))
+                .collect::<TokioResult<Vec<Dataunit>>>()?);
+        }
+
+        // Ok
+        Ok(ds)
+    }
+
+    async fn _group_by_table(&self, feed_name: &str, keys: &[&str], 
+                             aggs: &[(&str, AggFn)], range: Range<usize>, 
+                             cols_read: &[String], 
+                             table: &mut GroupTable) -> TokioResult<()> {
+        for ix in range.clone().step_by(CHUNK_SIZE) {
+            let count = CHUNK_SIZE.min(range.end - ix);
+            let chunk = self._data_get(feed_name, ix, count, cols_read, 
+                                       &ReadOptions::default()).await?;
+            let key_cols = keys.iter()
+                .map(|col_name| &chunk[*col_name])
+                .collect::<Vec<&Vec<Dataunit>>>();
+            let agg_cols = aggs.iter()
+                .map(|(col_name, _)| &chunk[*col_name])
+                .collect::<Vec<&Vec<Dataunit>>>();
+            for i in 0..get_dataset_size(&chunk)? {
+                let group_keys = key_cols.iter()
+                    .map(|values| values[i].clone())
+                    .collect::<Vec<Dataunit>>();
+                let values = agg_cols.iter()
+                    .map(|values| &values[i])
+                    .collect::<Vec<&Dataunit>>();
+                table.push(&group_keys, &values).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn _agg_validate(datatype: &Datatype, agg_fn: AggFn) -> 
+                     TokioResult<()> {
+        // The functions except count need numbers, the values of `UInt128`
+        // may not fit the 128-bit sum, so they are only counted
+        let is_number = matches!(datatype, 
+                                 Datatype::Int64 | Datatype::Int32 | 
+                                 Datatype::Float64 | Datatype::Float32 | 
+                                 Datatype::Decimal(..) | Datatype::Int128);
+        validate!(agg_fn == AggFn::Count || is_number, 
+                  Unsupported, datatype.to_string())
+    }
+
+    async fn _partition_items(&self, feed_name: &str) -> 
+                              TokioResult<Vec<PartitionItem>> {
+        let mut partition_items = self.partition_mapping.write().await
+            .get_mut(feed_name).unwrap().list().await?;
+        partition_items.sort_by_key(|partition_item| partition_item.key);
+        Ok(partition_items)
+    }
+
+    async fn _partition_names(&self, feed_name: &str) -> 
+                              TokioResult<Vec<String>> {
+        if !self.feed_map.read().await[feed_name].is_partitioned() {
+            return Ok(vec![]);
+        }
+        Ok(self._partition_items(feed_name).await?.iter()
+            .map(|item| Self::_get_partition_feed_name(feed_name, item))
+            .collect())
+    }
+
+    async fn _is_partition(&self, feed_name: &str) -> bool {
+        let feed_map = self.feed_map.read().await;
+        Self::_is_partition_in(&feed_map, feed_name)
+    }
+
+    fn _is_partition_in(feed_map: &HashMap<String, FeedItem>,
+                        feed_name: &str) -> bool {
+        // The partitions are the feeds named `p<key>` or `m<key>` in
+        // the namespace of a partitioned feed
+        match feed_name.rsplit_once('.') {
+            Some((parent, name)) => feed_map.get(parent)
+                .is_some_and(|feed_item| feed_item.is_partitioned()) &&
+                Self::_is_partition_name(name),
+            None => false,
+        }
+    }
+
+    fn _is_partition_name(name: &str) -> bool {
+        name.strip_prefix(['p', 'm'])
+            .is_some_and(|key| !key.is_empty() &&
+                               key.bytes().all(|b| b.is_ascii_digit()))
+    }
+
+    async fn _validate_not_partition(&self, feed_name: &str) ->
+                                     TokioResult<()> {
+        validate!(!self._is_partition(feed_name).await,
+                  PermissionDenied, feed_name)
+    }
+
+    async fn _validate_unpartitioned(&self, feed_name: &str) -> 
+                                     TokioResult<()> {
+        validate!(!self.feed_map.read().await[feed_name].is_partitioned(), 
+                  Unsupported, feed_name)
+    }
+
+    async fn _validate_uncapped(&self, feed_name: &str) -> TokioResult<()> {
+        validate!(!self.feed_map.read().await[feed_name].is_capped(), 
+                  Unsupported, feed_name)
+    }
+
+    async fn _validate_unsegmented(&self, feed_name: &str) -> 
+                                   TokioResult<()> {
+        validate!(!self.feed_map.read().await[feed_name].is_segmented(), 
+                  Unsupported, feed_name)
+    }
+
+    fn _validate_datatype(feed_item: &FeedItem, col_item: &ColItem) -> 
+                          TokioResult<()> {
+        // The cols of a capped feed must have fixed size, the cols of 
+        // a segmented feed must be stored as they are
+        let datatype = col_item.parse_datatype();
+        validate!(!feed_item.is_capped() || !datatype.is_var(), 
+                  Unsupported, col_item.get_datatype())?;
+        validate!(!feed_item.is_segmented() || datatype.is_fixed(), 
+                  Unsupported, col_item.get_datatype())
+    }
+
+    async fn _feed_seal(&self, feed_name: &str) -> TokioResult<()> {
+        // Seal the full segments of all series if the feed is segmented
+        if self.feed_map.read().await[feed_name].is_segmented() {
+            for series in self.series_mapping.read().await
+                              [feed_name].values() {
+                series.lock().await.seal().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn _feed_compact_write(&self, feed_item_new: &FeedItem, 
+                                 col_items_new: &[ColItem], 
+                                 remap: &IndexRemap) -> TokioResult<()> {
+        // Remove the files left by an interrupted compaction
+        self._feed_generation_remove(feed_item_new, col_items_new).await;
+
+        // Write the compacted data of all cols
+        let feed_name = &feed_item_new.name;
+        for col_item_new in col_items_new.iter() {
+            let col_path = Self::_get_col_path(&self.path, feed_name, 
+                                               &col_item_new.get_file_name());
+            self._col_compact_data(feed_name, &col_item_new.name, &col_path, 
+                                   col_item_new, remap).await?;
+        }
+
+        // Write the col list, the tombstones are created empty on open
+        let col_list_path = Self::_get_col_list_path(&self.path, 
+                                                     feed_item_new);
+        List::<ColItem, String>::new(col_list_path).await?
+            .add_many(col_items_new).await
+    }
+
+    async fn _feed_switch(&self, feed_item_new: FeedItem, 
+                          col_items_new: Vec<ColItem>) -> TokioResult<()> {
+        // Open the next generation
+        let feed_name = feed_item_new.get_name();
+        let feed_name = feed_name.as_str();
+        let col_list_path = Self::_get_col_list_path(&self.path, 
+                                                     &feed_item_new);
+        let col_list = List::<ColItem, String>::new(col_list_path).await?;
+        let mut series_new = vec![];
+        for col_item_new in col_items_new.iter() {
+            series_new.push(self._series_open(feed_name, col_item_new)
+                                .await?);
+        }
+        let tombstone_path = Self::_get_tombstone_path(&self.path, 
+                                                       &feed_item_new);
+        let tombstone_new = Bitmap::new(tombstone_path, feed_item_new.size)
+            .await?;
+
+        // Replace the series and the tombstones, the readers and the writers
+        // of the feed are held off by the guard taken by `feed_compact`
+        let series_mapping = self.series_mapping.read().await;
+        for (col_item_new, series) in col_items_new.iter().zip(series_new) {
+            *series_mapping[feed_name][&col_item_new.name].lock().await = 
+                series;
+        }
+        drop(series_mapping);
+        *self.tombstone_mapping.read().await[feed_name].lock().await = 
+            tombstone_new;
+
+        // Update the mappings
+        self.col_list_mapping.write().await
+            .insert(feed_name.to_string(), col_list);
+        self.col_map_mapping.write().await.insert(
+            feed_name.to_string(), 
+            col_items_new.into_iter()
+                .map(|col_item| (col_item.get_name(), col_item))
+                .collect()
+        );
+        self.feed_map.write().await
+            .insert(feed_name.to_string(), feed_item_new);
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _feed_generation_remove(&self, feed_item: &FeedItem,
+                                     col_items: &[ColItem]) {
+        // The files are removed if they exist, they are not used anyway
+        for col_item in col_items.iter() {
+            let col_path = Self::_get_col_path(&self.path, &feed_item.name, 
+                                               &col_item.get_file_name());
+            Self::_series_remove(&col_path, col_item).await;
+        }
+        let col_list_path = Self::_get_col_list_path(&self.path, feed_item);
+        for path in [format!("{}.heap", col_list_path), col_list_path,
+                     Self::_get_tombstone_path(&self.path, feed_item)] {
+            let _ = remove_file(path).await;
+        }
+    }
+
+    async fn _col_compact_data(&self, feed_name: &str, col_name: &str, 
+                               col_path: &str, col_item_new: &ColItem, 
+                               remap: &IndexRemap) -> TokioResult<()> {
+        // Lock the series, so the data cannot change during the compaction
+        let series_arc = Arc::clone(
+            &self.series_mapping.read().await[feed_name][col_name]
+        );
+        let mut series = series_arc.lock().await;
+        let start = series.start();
+
+        // Create the series of the new generation, the kept rows before
+        // the start are not stored as before
+        let mut series_new = Series::new(col_path, col_item_new).await?;
+
+        // Append the kept stored rows in chunks
+        for range in remap.ranges() {
+            for ix in (range.start.max(start)..range.end).step_by(CHUNK_SIZE) {
+                let count = CHUNK_SIZE.min(range.end - ix);
+                let values = series.get(ix, count).await?;
+                let ix_new = remap.get(ix).unwrap();
+                series_new.resize(ix_new + count).await?;
+                series_new.update(ix_new, &values).await?;
+            }
+        }
+        series_new.resize(remap.size()).await?;
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _data_read(&self, feed_name: &str, ranges: &[(usize, usize)], 
+                        cols: &[String]) -> TokioResult<Dataset> {
+        // Create a JoinSet object
+        let mut js = JoinSet::new();
+
+        for col_name in cols.iter() {
+            // Get series object
+            let series = &self.series_mapping.read().await
+                [feed_name][col_name];
+
+            // Clone the series
+            let series_clone = Arc::clone(series);
+
+            // Clone col_name and the ranges
+            let col_name_clone = col_name.clone();
+            let ranges_clone = ranges.to_vec();
+
+            // Spawn a concurrent task
+            js.spawn(async move {
+                let values = Self::_series_get(&series_clone, 
+                                               &ranges_clone).await;
+                (values, col_name_clone)
+            });
+        }
+
+        // Create an empty dataset
+        let mut ds = HashMap::new();
+
+        while let Some(res) = js.join_next().await {
+            // Get values of the column
+            let (values, col_name) = res?;
+
+            // Insert series into the dataset
+            ds.insert(col_name, values?);
+        }
+
+        Ok(ds)
+    }
+
+    async fn _tombstone_get(&self, feed_name: &str, 
+                            ranges: &[(usize, usize)]) -> 
+                            TokioResult<Vec<bool>> {
+        let tombstone_mapping = self.tombstone_mapping.read().await;
+        let mut tombstone = tombstone_mapping[feed_name].lock().await;
+        let mut deleted = vec![];
+        for &(start, count) in ranges {
+            deleted.extend(tombstone.get(start, count).await?);
+        }
+        Ok(deleted)
+    }
+
+    async fn _series_get(series: &Mutex<Series>, 
+                         ranges: &[(usize, usize)]) -> 
+                         TokioResult<Vec<Dataunit>> {
+        let mut series = series.lock().await;
+        let mut values = vec![];
+        for &(start, count) in ranges {
+            values.extend(series.get(start, count).await?);
+        }
+        Ok(values)
+    }
+
+    async fn _series_get_many(series: &Mutex<Series>, 
+                              ranges_many: &[Vec<(usize, usize)>]) -> 
+                              TokioResult<Vec<Vec<Dataunit>>> {
+        // Merge the overlapping and adjacent ranges
+        let mut sorted = ranges_many.concat();
+        sorted.retain(|&(_, count)| count > 0);
+        sorted.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = vec![];
+        for (start, count) in sorted {
+            match merged.last_mut() {
+                Some((last, last_count)) if start <= *last + *last_count => {
+                    *last_count = (*last_count).max(start + count - *last);
+                },
+                _ => merged.push((start, count)),
+            }
+        }
+
+        // Read the merged ranges
+        let mut series = series.lock().await;
+        let mut values_merged = vec![];
+        for &(start, count) in merged.iter() {
+            values_merged.push(series.get(start, count).await?);
+        }
+
+        // Cut the values of the ranges from the merged ones
+        Ok(ranges_many.iter()
+            .map(|ranges| {
+                let mut values = vec![];
+                for &(start, count) in ranges.iter().filter(|r| r.1 > 0) {
+                    let k = merged.partition_point(|r| r.0 <= start) - 1;
+                    let offset = start - merged[k].0;
+                    values.extend_from_slice(
+                        &values_merged[k][offset..offset + count]
+                    );
+                }
+                values
+            })
+            .collect())
+    }
+
+    async fn _feed_guard(&self, feed_name: &str) -> 
+                         TokioResult<Arc<RwLock<()>>> {
+        // Check whether the feed exists
+        validate!(self.feed_exists(feed_name).await, NotFound, feed_name)?;
+
+        // Get the guard of the feed
+        Ok(Arc::clone(&self.guard_mapping.read().await[feed_name]))
+    }
+
+    async fn _feed_guard_read(&self, feed_name: &str) -> 
+                              TokioResult<OwnedRwLockReadGuard<()>> {
+        // The readers and the writers share the guard of the feed, and 
+        // `feed_compact` holds it exclusively, because it moves the stored
+        // rows and switches the files under them. The guard is not 
+        // reentrant, a method holding it must not call another one taking 
+        // it for the same feed, or it may deadlock with a waiting compaction
+        Ok(self._feed_guard(feed_name).await?.read_owned().await)
+    }
+
+    fn _skip_deleted<T: Clone>(items: &[T], deleted: &[bool]) -> Vec<T> {
+        items.iter().zip(deleted.iter())
+            .filter(|(_, is_deleted)| !**is_deleted)
+            .map(|(item, _)| item.clone())
+            .collect()
+    }
+
     async fn _feed_open(&self, feed_name: &str, feed_item: FeedItem) -> 
                         TokioResult<()> {
+        // Move the feed from the flat directory of older versions, where
+        // the dotted names were not nested
+        let feed_path = Self::_get_feed_path(&self.path, feed_name);
+        if !try_exists(&feed_path).await? {
+            let flat_path = path_concat!(self.path.clone(), feed_name);
+            validate!(feed_name.contains('.') && 
+                      try_exists(&flat_path).await?, NotFound, feed_name)?;
+            if let Some(parent) = PathBuf::from(&feed_path).parent() {
+                create_dir_all(parent).await?;
+            }
+            rename(flat_path, &feed_path).await?;
+        }
+
         // Open col list file
-        let col_list_path = Self::_get_col_list_path(&self.path, feed_name);
+        let col_list_path = Self::_get_col_list_path(&self.path, &feed_item);
         let mut col_list = List::<ColItem, String>::new(col_list_path).await?;
         let col_map = col_list.map().await?;
 
-        // Open all seq files
+        // Open all series, they need the feed item to be segmented
+        let size = feed_item.size;
+        let tombstone_path = Self::_get_tombstone_path(&self.path, &feed_item);
+        self.feed_map.write().await.insert(feed_name.to_string(), feed_item);
         self.col_map_mapping.write().await
             .insert(feed_name.to_string(), HashMap::new());
-        self.seq_mapping.write().await
+        self.series_mapping.write().await
             .insert(feed_name.to_string(), HashMap::new());
         for (col_name, col_item) in col_map.into_iter() {
             self._col_open(feed_name, &col_name, col_item).await?;
         }
 
+        // Open the tombstones
+        let tombstone = Bitmap::new(tombstone_path, size).await?;
+        self.tombstone_mapping.write().await
+            .insert(feed_name.to_string(), Mutex::new(tombstone));
+
+        // Open the partition list of a partitioned feed
+        if self.feed_map.read().await[feed_name].is_partitioned() {
+            let partition_list_path = Self::_get_partition_list_path(
+                &self.path, feed_name
+            );
+            let partition_list = List::<PartitionItem, i64>::new(
+                partition_list_path
+            ).await?;
+            self.partition_mapping.write().await
+                .insert(feed_name.to_string(), partition_list);
+        }
+
         // Update mappings
-        self.feed_map.write().await.insert(feed_name.to_string(), feed_item);
         self.col_list_mapping.write().await
             .insert(feed_name.to_string(), col_list);
+        self.guard_mapping.write().await
+            .insert(feed_name.to_string(), Arc::new(RwLock::new(())));
         
         // Ok
         Ok(())
     }
 
     async fn _feed_close(&self, feed_name: &str) -> FeedItem {
-        // Close all seq files by removing them from seq_mapping
-        self.seq_mapping.write().await.remove(feed_name);
+        // Close all series by removing them from series_mapping
+        self.series_mapping.write().await.remove(feed_name);
+        self.guard_mapping.write().await.remove(feed_name);
+
+        // Close the tombstones and the partition list
+        self.tombstone_mapping.write().await.remove(feed_name);
+        self.partition_mapping.write().await.remove(feed_name);
 
         // Close col list file by removing it from col_list_mapping
         self.col_list_mapping.write().await.remove(feed_name);
@@ -584,23 +3205,34 @@ impl Conn {
 
     async fn _col_open(&self, feed_name: &str, col_name: &str, 
                        col_item: ColItem) -> TokioResult<()> {
-        // Create a seq for the col and set the necessary size
-        let seq_path = Self::_get_seq_path(&self.path, feed_name, col_name);
-        let seq = Seq::new(seq_path, col_item.datatype.size()).await?;
+        // Open the series of the col
+        let series = self._series_open(feed_name, &col_item).await?;
 
         // Update the mappings
         self.col_map_mapping.write().await.get_mut(feed_name).unwrap()
             .insert(col_name.to_string(), col_item);
-        self.seq_mapping.write().await.get_mut(feed_name).unwrap()
-            .insert(col_name.to_string(), Arc::new(Mutex::new(seq)));
+        self.series_mapping.write().await.get_mut(feed_name).unwrap()
+            .insert(col_name.to_string(), Arc::new(Mutex::new(series)));
 
         // Ok
         Ok(())
     }
 
+    async fn _series_open(&self, feed_name: &str, col_item: &ColItem) -> 
+                          TokioResult<Series> {
+        let col_path = Self::_get_col_path(&self.path, feed_name, 
+                                           &col_item.get_file_name());
+        let segment = self.feed_map.read().await[feed_name].segment;
+        if segment > 0 {
+            Series::new_segmented(&col_path, col_item, segment).await
+        } else {
+            Series::new(&col_path, col_item).await
+        }
+    }
+
     async fn _col_close(&self, feed_name: &str, col_name: &str) -> ColItem {
-        // Close seq file by removing it from seq_mapping
-        self.seq_mapping.write().await.get_mut(feed_name).unwrap()
+        // Close series by removing it from series_mapping
+        self.series_mapping.write().await.get_mut(feed_name).unwrap()
             .remove(col_name);
 
         // Remove col item from col_map_mapping and return it
@@ -608,15 +3240,1819 @@ impl Conn {
             .remove(col_name).unwrap()
     }
 
+    async fn _history_push(&self, op: HistoryOp, feed_name: &str, 
+                           col_name: &str, details: &str) -> TokioResult<()> {
+        self.history.lock().await.push(&[
+            Self::_history_item(op, feed_name, col_name, details)
+        ]).await
+    }
+
+    fn _history_item(op: HistoryOp, feed_name: &str, col_name: &str, 
+                     details: &str) -> HistoryItem {
+        HistoryItem {
+            time: timestamp(),
+            op,
+            feed: feed_name.to_string(),
+            col: col_name.to_string(),
+            details: details.to_string(),
+        }
+    }
+
+    async fn _feed_touch(&self, feed_name: &str) -> TokioResult<()> {
+        // Update the modification time of the feed and save it
+        let mut feed_map = self.feed_map.write().await;
+        let feed_item = feed_map.get_mut(feed_name).unwrap();
+        feed_item.meta.touch();
+        self.feed_list.write().await
+            .modify(&feed_name.to_string(), feed_item).await
+    }
+
+    async fn _cols_started_after(&self, feed_name: &str, cols: &[String], 
+                                 ix: usize) -> Vec<String> {
+        // The cols whose stored values start after `ix`, only their start
+        // can change if they are written or truncated from `ix`
+        self.col_map_mapping.read().await[feed_name].iter()
+            .filter(|(col_name, col_item)| {
+                col_item.start > ix && cols.contains(col_name)
+            })
+            .map(|(col_name, _)| col_name.clone())
+            .collect()
+    }
+
+    async fn _col_start_sync(&self, feed_name: &str, 
+                             cols: &[String]) -> TokioResult<()> {
+        // Save the start of the cols whose series have changed it
+        for col_name in cols.iter() {
+            let series = Arc::clone(
+                &self.series_mapping.read().await[feed_name][col_name]
+            );
+            let start = series.lock().await.start();
+            let mut col_map_mapping = self.col_map_mapping.write().await;
+            let col_item = col_map_mapping.get_mut(feed_name).unwrap()
+                .get_mut(col_name).unwrap();
+            if col_item.start != start {
+                col_item.start = start;
+                self.col_list_mapping.write().await.get_mut(feed_name)
+                    .unwrap().modify(col_name, col_item).await?;
+            }
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _feed_dir_cleanup(&self, feed_name: &str) {
+        // Remove the directory of the feed and its parents while they are
+        // empty
+        let mut parts = feed_name.split('.').collect::<Vec<&str>>();
+        while !parts.is_empty() {
+            let dir_path = path_concat!(&self.path, parts.join("/"));
+            if remove_dir(dir_path).await.is_err() {
+                break;
+            }
+            parts.pop();
+        }
+    }
+
+    fn _col_conflicts(expected: &ColItem, found: &ColItem) -> Vec<String> {
+        // Every differing attribute is reported with both values
+        let mut conflicts = vec![];
+        if expected.datatype != found.datatype {
+            conflicts.push(format!("{} datatype ({} expected, {} found)",
+                                   expected.name, expected.datatype,
+                                   found.datatype));
+        }
+        if expected.nullable != found.nullable {
+            conflicts.push(format!("{} nullable ({} expected, {} found)",
+                                   expected.name, expected.nullable,
+                                   found.nullable));
+        }
+        if expected.get_default_bytes() != found.get_default_bytes() {
+            conflicts.push(format!("{} default ({:?} expected, {:?} found)",
+                                   expected.name, expected.default,
+                                   found.default));
+        }
+        conflicts
+    }
+
+    async fn _get_feed_files(feed_path: &str) -> TokioResult<Vec<PathBuf>> {
+        // The files of the feed are all the files in its directory, 
+        // the subdirectories belong to the feeds in its namespace
+        let mut paths = vec![];
+        let mut entries = read_dir(feed_path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                paths.push(entry.path());
+            }
+        }
+        Ok(paths)
+    }
+
+    fn _is_in_ns(feed_name: &str, ns: &str) -> bool {
+        feed_name == ns || feed_name.strip_prefix(ns)
+            .is_some_and(|rest| rest.starts_with('.'))
+    }
+
     fn _get_feed_list_path(path: &str) -> String {
         path_concat!(path, "feed.list")
     }
 
-    fn _get_col_list_path(path: &str, feed_name: &str) -> String {
-        path_concat!(path, feed_name, "col.list")
+    fn _get_template_list_path(path: &str) -> String {
+        path_concat!(path, "template.list")
+    }
+
+    fn _get_history_path(path: &str) -> String {
+        path_concat!(path, "history.log")
+    }
+
+    fn _get_group_path(path: &str) -> String {
+        // The dot keeps it apart from the directories of the feeds
+        path_concat!(path, "group.tmp")
+    }
+
+    fn _get_feed_path(path: &str, feed_name: &str) -> String {
+        path_concat!(path, feed_name.replace('.', "/"))
+    }
+
+    fn _get_tombstone_path(path: &str, feed_item: &FeedItem) -> String {
+        let file_name = if feed_item.generation == 0 {
+            "deleted.bitmap".to_string()
+        } else {
+            format!("deleted~{}.bitmap", feed_item.generation)
+        };
+        path_concat!(Self::_get_feed_path(path, &feed_item.name), file_name)
+    }
+
+    fn _get_partition_list_path(path: &str, feed_name: &str) -> String {
+        path_concat!(Self::_get_feed_path(path, feed_name), "partitions.list")
+    }
+
+    fn _get_partition_feed_name(feed_name: &str, 
+                                partition_item: &PartitionItem) -> String {
+        format!("{}.{}", feed_name, partition_item.get_name())
+    }
+
+    fn _get_col_list_path(path: &str, feed_item: &FeedItem) -> String {
+        let file_name = if feed_item.generation == 0 {
+            "col.list".to_string()
+        } else {
+            format!("col~{}.list", feed_item.generation)
+        };
+        path_concat!(Self::_get_feed_path(path, &feed_item.name), file_name)
+    }
+
+    fn _get_col_path(path: &str, feed_name: &str, file_name: &str) -> 
+                     String {
+        path_concat!(Self::_get_feed_path(path, feed_name), file_name)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::from_bytes_many;
+    use crate::record::{encode_record, decode_record};
+    use crate::predicate::CmpOp;
+
+    async fn open(name: &str) -> TokioResult<Conn> {
+        let path = path_concat!("./tmp", name);
+        let _ = tokio::fs::remove_dir_all(&path).await;
+        Conn::new(&path).await
+    }
+
+    #[tokio::test]
+    async fn test_var_cols() -> TokioResult<()> {
+        let conn = open("test_var_cols").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.col_add("xyz", "s", "VarStr").await?;
+
+        let ds: Dataset = HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(1), Dataunit::I(2)]),
+            ("s".to_string(), vec![Dataunit::S("qwe".to_string()), 
+                                   Dataunit::S("".to_string())]),
+        ]);
+        conn.data_push("xyz", &ds).await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("s".to_string(), vec![Dataunit::S("asdf".to_string())]),
+        ])).await?;
+
+        let cols = ["x".to_string(), "s".to_string()];
+        let ds = conn.data_get("xyz", 1, 2, &cols).await?;
+        assert_eq!(ds["x"], vec![Dataunit::I(2), Dataunit::I(0)]);
+        assert_eq!(ds["s"], vec![Dataunit::S("".to_string()), 
+                                 Dataunit::S("asdf".to_string())]);
+
+        conn.size_set("xyz", 1).await?;
+        let ds = conn.data_get("xyz", 0, 1, &cols).await?;
+        assert_eq!(ds["s"], vec![Dataunit::S("qwe".to_string())]);
+        assert!(conn.raw_get("xyz", "s", 0, 1).await.is_err());
+
+        conn.col_rename("xyz", "s", "t").await?;
+        conn.col_remove("xyz", "t").await?;
+
+        Ok(())
     }
 
-    fn _get_seq_path(path: &str, feed_name: &str, col_name: &str) -> String {
-        path_concat!(path, feed_name, format!("{}.col", col_name))
+    #[tokio::test]
+    async fn test_category_cols() -> TokioResult<()> {
+        let conn = open("test_category_cols").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "c", "Category").await?;
+        conn.size_set("xyz", 1).await?;
+
+        let values = ["NYSE", "LSE", "NYSE"].iter()
+            .map(|s| Dataunit::S(s.to_string()))
+            .collect::<Vec<Dataunit>>();
+        conn.data_push("xyz", &HashMap::from([
+            ("c".to_string(), values.clone()),
+        ])).await?;
+
+        let ds = conn.data_get("xyz", 1, 3, &["c".to_string()]).await?;
+        assert_eq!(ds["c"], values);
+        assert_eq!(conn.raw_get("xyz", "c", 0, 4).await?, 
+                   vec![0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(conn.col_dict("xyz", "c").await?, vec![
+            Dataunit::S("".to_string()), 
+            Dataunit::S("NYSE".to_string()), 
+            Dataunit::S("LSE".to_string()), 
+        ]);
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_category_cols").await?;
+        assert_eq!(conn.col_dict("xyz", "c").await?.len(), 3);
+        assert!(conn.col_dict("xyz", "d").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_array_cols() -> TokioResult<()> {
+        let conn = open("test_array_cols").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "v", "Float32[3]").await?;
+        assert!(conn.col_add("xyz", "w", "VarStr[3]").await.is_err());
+        assert_eq!(conn.col_add("xyz", "w", "Int32[0]").await.unwrap_err()
+                       .kind(), tokio::io::ErrorKind::InvalidInput);
+
+        let values = vec![
+            Dataunit::List(vec![Dataunit::F(1.0), Dataunit::F(0.5), 
+                                Dataunit::F(-2.0)]),
+            Dataunit::List(vec![Dataunit::F(0.0), Dataunit::F(0.25), 
+                                Dataunit::F(4.0)]),
+        ];
+        conn.data_push("xyz", &HashMap::from([
+            ("v".to_string(), values.clone()),
+        ])).await?;
+        assert!(conn.data_patch("xyz", 1, &HashMap::from([
+            ("v".to_string(), vec![Dataunit::List(vec![Dataunit::F(1.0)])]),
+        ])).await.is_err());
+        assert_eq!(conn.size_get("xyz").await?, 2);
+
+        let ds = conn.data_get("xyz", 0, 2, &["v".to_string()]).await?;
+        assert_eq!(ds["v"], values);
+
+        let block = conn.raw_get("xyz", "v", 1, 1).await?;
+        assert_eq!(from_bytes_many::<f32>(&block), &[0.0, 0.25, 4.0]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nullable_cols() -> TokioResult<()> {
+        let conn = open("test_nullable_cols").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.size_set("xyz", 2).await?;
+
+        let options = ColOptions { nullable: true, ..Default::default() };
+        conn.col_add_with("xyz", "y", "Float64", &options).await?;
+        conn.col_add_with("xyz", "s", "VarStr", &options).await?;
+
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(0), Dataunit::I(5)]),
+            ("y".to_string(), vec![Dataunit::F(0.0), Dataunit::Null]),
+        ])).await?;
+        assert!(conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::Null]),
+        ])).await.is_err());
+
+        let cols = ["x".to_string(), "y".to_string(), "s".to_string()];
+        let ds = conn.data_get("xyz", 1, 3, &cols).await?;
+        assert_eq!(ds["x"], vec![Dataunit::I(0), Dataunit::I(0), 
+                                 Dataunit::I(5)]);
+        assert_eq!(ds["y"], vec![Dataunit::Null, Dataunit::F(0.0), 
+                                 Dataunit::Null]);
+        assert_eq!(ds["s"], vec![Dataunit::Null; 3]);
+
+        conn.data_patch("xyz", 0, &HashMap::from([
+            ("s".to_string(), vec![Dataunit::S("qwe".to_string())]),
+        ])).await?;
+        conn.data_save("xyz", 2, &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(1)]),
+        ])).await?;
+        conn.size_set("xyz", 5).await?;
+
+        let ds = conn.data_get("xyz", 0, 5, &cols).await?;
+        assert_eq!(ds["y"], vec![Dataunit::Null; 5]);
+        assert_eq!(ds["s"], vec![Dataunit::S("qwe".to_string()), 
+                                 Dataunit::Null, Dataunit::Null, 
+                                 Dataunit::Null, Dataunit::Null]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_decimal_cols() -> TokioResult<()> {
+        let conn = open("test_decimal_cols").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.col_add("xyz", "d", "Decimal(6,2)").await?;
+
+        let s = |x: &str| Dataunit::S(x.to_string());
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(1)]),
+            ("d".to_string(), vec![s("-12.5")]),
+        ])).await?;
+
+        // The values over the precision change nothing
+        assert!(conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(2), Dataunit::I(3)]),
+            ("d".to_string(), vec![s("1.5"), s("10000")]),
+        ])).await.is_err());
+        assert!(conn.data_save("xyz", 0, &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(4)]),
+            ("d".to_string(), vec![s("1.234")]),
+        ])).await.is_err());
+        assert_eq!(conn.size_get("xyz").await?, 1);
+
+        let cols = ["x".to_string(), "d".to_string()];
+        let ds = conn.data_get("xyz", 0, 1, &cols).await?;
+        assert_eq!(ds["x"], vec![Dataunit::I(1)]);
+        assert_eq!(ds["d"], vec![s("-12.50")]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_col_cast() -> TokioResult<()> {
+        let conn = open("test_col_cast").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int32").await?;
+        conn.col_add("xyz", "y", "Float64").await?;
+        conn.col_add_with("xyz", "z", "Float64", 
+                          &ColOptions { nullable: true, 
+                                        ..Default::default() }).await?;
+        conn.col_add("xyz", "s", "VarStr").await?;
+
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(1), Dataunit::I(-7)]),
+            ("y".to_string(), vec![Dataunit::F(0.5), Dataunit::F(0.1)]),
+            ("z".to_string(), vec![Dataunit::F(2.0), Dataunit::F(2.5)]),
+            ("s".to_string(), vec![Dataunit::S("a".to_string()), 
+                                   Dataunit::S("b".to_string())]),
+        ])).await?;
+
+        conn.col_cast("xyz", "x", "Int64", CastPolicy::Strict).await?;
+        assert!(conn.col_cast("xyz", "y", "Float32", CastPolicy::Strict)
+                    .await.is_err());
+        assert!(conn.col_cast("xyz", "y", "Float32", CastPolicy::Null)
+                    .await.is_err());
+        conn.col_cast("xyz", "z", "Int32", CastPolicy::Null).await?;
+        conn.col_cast("xyz", "s", "Category", CastPolicy::Strict).await?;
+        assert!(conn.col_cast("xyz", "s", "Int32", CastPolicy::Lossy)
+                    .await.is_err());
+
+        let cols = ["x".to_string(), "y".to_string(), "z".to_string(), 
+                    "s".to_string()];
+        let ds = conn.data_get("xyz", 0, 2, &cols).await?;
+        assert_eq!(ds["x"], vec![Dataunit::I(1), Dataunit::I(-7)]);
+        assert_eq!(ds["y"], vec![Dataunit::F(0.5), Dataunit::F(0.1)]);
+        assert_eq!(ds["z"], vec![Dataunit::I(2), Dataunit::Null]);
+        assert_eq!(ds["s"], vec![Dataunit::S("a".to_string()), 
+                                 Dataunit::S("b".to_string())]);
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_col_cast").await?;
+        let col_map = conn.col_list("xyz").await?.into_iter()
+            .map(|col_item| (col_item.get_name(), col_item.get_datatype()))
+            .collect::<HashMap<String, String>>();
+        assert_eq!(col_map["x"], "Int64");
+        assert_eq!(col_map["y"], "Float64");
+        assert_eq!(col_map["s"], "Category");
+        assert_eq!(conn.raw_get("xyz", "x", 1, 1).await?, 
+                   (-7i64).to_le_bytes().to_vec());
+
+        let ds = HashMap::from([("x".to_string(), vec![Dataunit::I(9)])]);
+        let (res_cast, res_push) = tokio::join!(
+            conn.col_cast("xyz", "x", "Int32", CastPolicy::Strict),
+            conn.data_push("xyz", &ds),
+        );
+        res_cast?;
+        res_push?;
+        assert_eq!(conn.data_get("xyz", 0, 3, &["x".to_string()]).await?
+                       ["x"],
+                   [Dataunit::I(1), Dataunit::I(-7), Dataunit::I(9)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_col_cast_interrupted() -> TokioResult<()> {
+        let conn = open("test_col_cast_interrupted").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(1), Dataunit::I(-7)]),
+        ])).await?;
+
+        // The data are cast but the col list is not updated
+        let mut col_item_new = ColItem::new("x", "Int32")?;
+        col_item_new.generation = 1;
+        let col_path = Conn::_get_col_path(&conn.path, "xyz", 
+                                           &col_item_new.get_file_name());
+        {
+            let series_arc = Arc::clone(
+                &conn.series_mapping.read().await["xyz"]["x"]
+            );
+            let mut series = series_arc.lock().await;
+            Conn::_col_cast_data(&mut series, &col_path, &col_item_new,
+                                 CastPolicy::Strict).await?;
+        }
+        drop(conn);
+
+        // The col keeps the old files and the old datatype
+        let conn = Conn::new("./tmp/test_col_cast_interrupted").await?;
+        assert_eq!(conn.col_list("xyz").await?[0].get_datatype(), "Int64");
+        assert_eq!(conn.raw_get("xyz", "x", 1, 1).await?, 
+                   (-7i64).to_le_bytes().to_vec());
+
+        // The next cast overwrites the unused files and removes the old ones
+        conn.col_cast("xyz", "x", "Int32", CastPolicy::Strict).await?;
+        assert_eq!(conn.raw_get("xyz", "x", 1, 1).await?, 
+                   (-7i32).to_le_bytes().to_vec());
+        let col_path_old = Conn::_get_col_path(&conn.path, "xyz", "x");
+        assert!(!try_exists(format!("{}.col", col_path_old)).await?);
+
+        // A failed cast leaves the col unchanged
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I((1 << 24) + 1)]),
+        ])).await?;
+        assert!(conn.col_cast("xyz", "x", "Float32", CastPolicy::Strict)
+                    .await.is_err());
+        drop(conn);
+        let conn = Conn::new("./tmp/test_col_cast_interrupted").await?;
+        assert_eq!(conn.col_list("xyz").await?[0].get_datatype(), "Int32");
+        assert_eq!(conn.data_get("xyz", 0, 3, &["x".to_string()]).await?
+                       ["x"],
+                   [Dataunit::I(1), Dataunit::I(-7), 
+                    Dataunit::I((1 << 24) + 1)]);
+        assert!(!try_exists(format!("{}~2.col", col_path_old)).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_cols() -> TokioResult<()> {
+        let conn = open("test_default_cols").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.size_set("xyz", 2).await?;
+
+        let default = |unit| ColOptions { 
+            default: Some(unit), ..Default::default() 
+        };
+        conn.col_add_with("xyz", "y", "Float64", 
+                          &default(Dataunit::F(f64::NAN))).await?;
+        conn.col_add_with("xyz", "z", "Int32", 
+                          &default(Dataunit::I(-1))).await?;
+        conn.col_add_with("xyz", "c", "Category", 
+                          &default(Dataunit::S("n/a".to_string()))).await?;
+        conn.col_add_with("xyz", "n", "Int32", &ColOptions { 
+            nullable: true, default: Some(Dataunit::I(7)) 
+        }).await?;
+        assert!(conn.col_add_with("xyz", "w", "Int32", 
+                                  &default(Dataunit::F(0.5))).await.is_err());
+
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(5)]),
+            ("z".to_string(), vec![Dataunit::I(3)]),
+        ])).await?;
+        conn.data_save("xyz", 0, &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(4)]),
+        ])).await?;
+        conn.size_set("xyz", 4).await?;
+
+        let cols = ["y".to_string(), "z".to_string(), "c".to_string(), 
+                    "n".to_string()];
+        let ds = conn.data_get("xyz", 0, 4, &cols).await?;
+        assert!(ds["y"].iter()
+                    .all(|unit| matches!(unit, Dataunit::F(x) if x.is_nan())));
+        assert_eq!(ds["z"], vec![Dataunit::I(-1), Dataunit::I(-1), 
+                                 Dataunit::I(3), Dataunit::I(-1)]);
+        assert_eq!(ds["c"], vec![Dataunit::S("n/a".to_string()); 4]);
+        assert_eq!(ds["n"], vec![Dataunit::I(7); 4]);
+
+        conn.col_cast("xyz", "z", "Int64", CastPolicy::Strict).await?;
+        conn.size_set("xyz", 5).await?;
+        assert_eq!(conn.data_get("xyz", 4, 1, &cols).await?["z"], 
+                   vec![Dataunit::I(-1)]);
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_default_cols").await?;
+        let col_item = conn.col_list("xyz").await?.into_iter()
+            .find(|col_item| col_item.get_name() == "z").unwrap();
+        assert_eq!(col_item.get_default(), Some(Dataunit::I(-1)));
+
+        // An unknown datatype in the stored record is rejected
+        let mut col_item = col_item.clone();
+        col_item.datatype = Datatype::Category;
+        let block = encode_record(&col_item);
+        let pos = block.windows(8).position(|w| w == b"Category").unwrap();
+        let block = [&block[..pos], b"Catalogs", &block[pos + 8..]].concat();
+        let err = decode_record::<ColItem>(&block).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sparse_cols() -> TokioResult<()> {
+        let conn = open("test_sparse_cols").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.size_set("xyz", 1000).await?;
+
+        conn.col_add_with("xyz", "y", "Int64", &ColOptions { 
+            default: Some(Dataunit::I(-1)), ..Default::default() 
+        }).await?;
+        conn.col_add_with("xyz", "s", "VarStr", &ColOptions { 
+            nullable: true, ..Default::default() 
+        }).await?;
+        let col_size = |col: &str| std::fs::metadata(
+            format!("./tmp/test_sparse_cols/xyz/{}.col", col)
+        ).unwrap().len();
+        assert_eq!(col_size("y"), 0);
+
+        conn.data_push("xyz", &HashMap::from([
+            ("y".to_string(), vec![Dataunit::I(5)]),
+            ("s".to_string(), vec![Dataunit::S("qwe".to_string())]),
+        ])).await?;
+        assert_eq!(col_size("y"), 8);
+
+        let cols = ["y".to_string(), "s".to_string()];
+        let ds = conn.data_get("xyz", 998, 3, &cols).await?;
+        assert_eq!(ds["y"], vec![Dataunit::I(-1), Dataunit::I(-1), 
+                                 Dataunit::I(5)]);
+        assert_eq!(ds["s"], vec![Dataunit::Null, Dataunit::Null, 
+                                 Dataunit::S("qwe".to_string())]);
+        assert_eq!(conn.raw_get("xyz", "y", 999, 2).await?, 
+                   [(-1i64).to_le_bytes(), 5i64.to_le_bytes()].concat());
+
+        conn.data_patch("xyz", 990, &HashMap::from([
+            ("y".to_string(), vec![Dataunit::I(7)]),
+            ("s".to_string(), vec![Dataunit::S("asd".to_string())]),
+        ])).await?;
+        assert_eq!(col_size("y"), 88);
+        let ds = conn.data_get("xyz", 990, 11, &cols).await?;
+        assert_eq!(ds["y"][..2], [Dataunit::I(7), Dataunit::I(-1)]);
+        assert_eq!(ds["y"][10], Dataunit::I(5));
+        assert_eq!(ds["s"][..2], [Dataunit::S("asd".to_string()), 
+                                  Dataunit::Null]);
+        assert_eq!(ds["s"][10], Dataunit::S("qwe".to_string()));
+        assert!(!std::fs::read_dir("./tmp/test_sparse_cols/xyz")?
+            .any(|entry| entry.unwrap().file_name().to_string_lossy()
+                .ends_with('~')));
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_sparse_cols").await?;
+        let starts = conn.col_list("xyz").await?.into_iter()
+            .map(|col_item| (col_item.get_name(), col_item.start))
+            .collect::<HashMap<String, usize>>();
+        assert_eq!(starts["x"], 0);
+        assert_eq!(starts["y"], 990);
+        assert_eq!(conn.data_get("xyz", 1000, 1, &cols).await?["y"], 
+                   vec![Dataunit::I(5)]);
+
+        conn.size_set("xyz", 500).await?;
+        conn.size_set("xyz", 600).await?;
+        let col_item = conn.col_list("xyz").await?.into_iter()
+            .find(|col_item| col_item.get_name() == "y").unwrap();
+        assert_eq!(col_item.start, 500);
+        assert_eq!(conn.data_get("xyz", 599, 1, &cols).await?["y"], 
+                   vec![Dataunit::I(-1)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete() -> TokioResult<()> {
+        let conn = open("test_soft_delete").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), (0..10).map(Dataunit::I).collect()),
+        ])).await?;
+        conn.col_add_with("xyz", "s", "VarStr", &ColOptions { 
+            nullable: true, ..Default::default() 
+        }).await?;
+        conn.data_patch("xyz", 6, &HashMap::from([
+            ("s".to_string(), ["a", "b", "c", "d"].iter()
+                .map(|x| Dataunit::S(x.to_string())).collect()),
+        ])).await?;
+
+        conn.data_delete("xyz", 1, 2).await?;
+        conn.data_delete_rows("xyz", &[7, 4, 7]).await?;
+        assert!(conn.data_delete("xyz", 9, 2).await.is_err());
+        assert!(conn.data_delete_rows("xyz", &[10]).await.is_err());
+        assert_eq!(conn.data_deleted("xyz", 0, 5).await?, 
+                   vec![false, true, true, false, true]);
+
+        let cols = ["x".to_string(), "s".to_string()];
+        let ds = conn.data_get("xyz", 0, 10, &cols).await?;
+        assert_eq!(ds["x"], [0, 3, 5, 6, 8, 9].into_iter()
+            .map(Dataunit::I).collect::<Vec<Dataunit>>());
+        assert_eq!(ds["s"][3..], [Dataunit::S("a".to_string()), 
+                                  Dataunit::S("c".to_string()),
+                                  Dataunit::S("d".to_string())]);
+        let options = ReadOptions { include_deleted: true };
+        assert_eq!(conn.data_get_with("xyz", 0, 10, &cols, &options).await?
+                       ["x"].len(), 10);
+        assert_eq!(conn.raw_get("xyz", "x", 2, 3).await?, 
+                   3i64.to_le_bytes());
+        assert_eq!(conn.raw_get_with("xyz", "x", 2, 3, &options).await?
+                       .len(), 24);
+
+        let remap = conn.feed_compact("xyz").await?;
+        assert_eq!(remap.size(), 6);
+        assert_eq!(remap.get(3), Some(1));
+        assert_eq!(remap.get(4), None);
+        assert_eq!(remap.get(8), Some(4));
+        assert_eq!(conn.size_get("xyz").await?, 6);
+        assert_eq!(conn.data_deleted("xyz", 0, 6).await?, vec![false; 6]);
+        assert_eq!(conn.data_get("xyz", 0, 6, &cols).await?, ds);
+
+        let col_item = conn.col_list("xyz").await?.into_iter()
+            .find(|col_item| col_item.get_name() == "s").unwrap();
+        assert_eq!(col_item.start, 3);
+        assert_eq!(conn.history_feed("xyz").await?.last().unwrap().details, 
+                   "10 -> 6");
+
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(10)]),
+        ])).await?;
+        conn.data_delete("xyz", 0, 1).await?;
+        drop(conn);
+        let conn = Conn::new("./tmp/test_soft_delete").await?;
+        assert_eq!(conn.data_deleted("xyz", 0, 7).await?, 
+                   [vec![true], vec![false; 6]].concat());
+        let remap = conn.feed_compact("xyz").await?;
+        assert_eq!((remap.get(0), remap.get(6)), (None, Some(5)));
+        assert_eq!(conn.data_get("xyz", 5, 1, &cols).await?["x"], 
+                   vec![Dataunit::I(10)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compact_concurrent() -> TokioResult<()> {
+        let conn = open("test_compact_concurrent").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), (0..10).map(Dataunit::I).collect()),
+        ])).await?;
+        conn.data_delete("xyz", 2, 2).await?;
+
+        // The writers wait for the compaction or go before it
+        let ds = HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(100), Dataunit::I(101)]),
+        ]);
+        let (res_compact, res_push, res_delete) = tokio::join!(
+            conn.feed_compact("xyz"),
+            conn.data_push("xyz", &ds),
+            conn.data_delete("xyz", 0, 1),
+        );
+        res_compact?;
+        res_push?;
+        res_delete?;
+
+        let size = conn.size_get("xyz").await?;
+        assert_eq!(conn.data_get("xyz", 0, size, &["x".to_string()]).await?
+                       ["x"],
+                   [1, 4, 5, 6, 7, 8, 9, 100, 101].into_iter()
+                       .map(Dataunit::I).collect::<Vec<Dataunit>>());
+
+        // The readers get the rows before the compaction or after it
+        conn.data_delete("xyz", 0, 1).await?;
+        let cols = ["x".to_string()];
+        let (res_compact, res_get) = tokio::join!(
+            conn.feed_compact("xyz"),
+            conn.data_get("xyz", 1, 4, &cols),
+        );
+        res_compact?;
+        let values = res_get?.remove("x").unwrap();
+        assert!(values == [4, 5, 6, 7].map(Dataunit::I) || 
+                values == [5, 6, 7, 8].map(Dataunit::I), "{:?}", values);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compact_interrupted() -> TokioResult<()> {
+        let conn = open("test_compact_interrupted").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), (0..10).map(Dataunit::I).collect()),
+        ])).await?;
+        conn.data_delete("xyz", 2, 2).await?;
+        let expected = [0, 1, 4, 5, 6, 7, 8, 9].into_iter()
+            .map(Dataunit::I).collect::<Vec<Dataunit>>();
+
+        // The next generation is written but the feed list is not updated
+        let mut feed_item_new = conn.feed_map.read().await["xyz"].clone();
+        feed_item_new.generation = 1;
+        let mut col_item_new = conn.col_list("xyz").await?[0].clone();
+        col_item_new.generation = 1;
+        let mut remap = IndexRemap::new();
+        remap.push(0..2);
+        remap.push(4..10);
+        conn._feed_compact_write(&feed_item_new, &[col_item_new], 
+                                 &remap).await?;
+        drop(conn);
+
+        // The feed keeps the previous generation
+        let conn = Conn::new("./tmp/test_compact_interrupted").await?;
+        assert_eq!(conn.size_get("xyz").await?, 10);
+        assert_eq!(conn.data_deleted("xyz", 2, 2).await?, vec![true; 2]);
+        assert_eq!(conn.data_get("xyz", 0, 10, &["x".to_string()]).await?
+                       ["x"], expected);
+
+        // The compaction writes the next generation again and removes 
+        // the previous one
+        conn.feed_compact("xyz").await?;
+        assert_eq!(conn.size_get("xyz").await?, 8);
+        let feed_path = Conn::_get_feed_path(&conn.path, "xyz");
+        for file_name in ["x.col", "col.list", "deleted.bitmap"] {
+            assert!(!try_exists(path_concat!(&feed_path, file_name)).await?);
+        }
+        drop(conn);
+        let conn = Conn::new("./tmp/test_compact_interrupted").await?;
+        assert_eq!(conn.data_get("xyz", 0, 8, &["x".to_string()]).await?
+                       ["x"], expected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trim_front() -> TokioResult<()> {
+        let conn = open("test_trim_front").await?;
+        conn.feed_ensure("xyz", &FeedSchema::new()
+            .col("x", "Int64")
+            .col_with("s", "VarStr", &ColOptions { 
+                nullable: true, ..Default::default() 
+            })
+            .col("c", "Category")
+        ).await?;
+        let size = 100000;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), (0..size).map(Dataunit::I).collect()),
+            ("s".to_string(), (0..size)
+                .map(|i| Dataunit::S(format!("v{}", i))).collect()),
+            ("c".to_string(), (0..size)
+                .map(|i| Dataunit::S(["a", "b"][i as usize % 2].to_string()))
+                .collect()),
+        ])).await?;
+
+        let blocks = || {
+            use std::os::unix::fs::MetadataExt;
+            std::fs::metadata("./tmp/test_trim_front/xyz/x.col")
+                .unwrap().blocks()
+        };
+        let blocks_before = blocks();
+        conn.feed_trim_front("xyz", 60000, TrimMode::Stable).await?;
+        assert!(blocks() < blocks_before);
+        assert_eq!(conn.base_get("xyz").await?, 60000);
+        assert_eq!(conn.size_get("xyz").await?, 100000);
+
+        let cols = ["x".to_string(), "s".to_string(), "c".to_string()];
+        assert!(conn.data_get("xyz", 59999, 2, &cols).await.is_err());
+        assert!(conn.data_delete_rows("xyz", &[10, 60000]).await.is_err());
+        assert!(conn.size_set("xyz", 59999).await.is_err());
+        let ds = conn.data_get("xyz", 60000, 2, &cols).await?;
+        assert_eq!(ds["x"], vec![Dataunit::I(60000), Dataunit::I(60001)]);
+        assert_eq!(ds["s"][1], Dataunit::S("v60001".to_string()));
+        assert_eq!(ds["c"][1], Dataunit::S("b".to_string()));
+
+        conn.feed_trim_front("xyz", 10000, TrimMode::Renumber).await?;
+        assert_eq!(conn.base_get("xyz").await?, 0);
+        assert_eq!(conn.size_get("xyz").await?, 30000);
+        assert_eq!(conn.data_get("xyz", 0, 1, &cols).await?["s"], 
+                   vec![Dataunit::S("v70000".to_string())]);
+        assert!(conn.feed_trim_front("xyz", 30001, TrimMode::Stable).await
+                    .is_err());
+
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(-1)]),
+        ])).await?;
+        conn.data_delete_rows("xyz", &[0]).await?;
+        drop(conn);
+
+        let conn = Conn::new("./tmp/test_trim_front").await?;
+        assert_eq!(conn.size_get("xyz").await?, 30001);
+        assert_eq!(conn.raw_get("xyz", "x", 30000, 1).await?, 
+                   (-1i64).to_le_bytes());
+        assert_eq!(conn.data_get("xyz", 0, 2, &cols).await?["x"], 
+                   vec![Dataunit::I(70001)]);
+
+        let remap = conn.feed_compact("xyz").await?;
+        assert_eq!((remap.get(0), remap.get(1)), (None, Some(0)));
+        assert_eq!(conn.size_get("xyz").await?, 30000);
+        assert_eq!(conn.feed_list().await[0].size, 30000);
+        assert_eq!(conn.data_get("xyz", 0, 1, &cols).await?["s"], 
+                   vec![Dataunit::S("v70001".to_string())]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_capped_feeds() -> TokioResult<()> {
+        let conn = open("test_capped_feeds").await?;
+        let schema = FeedSchema::new()
+            .col("x", "Int64")
+            .col_with("n", "Int64", &ColOptions { 
+                nullable: true, ..Default::default() 
+            });
+        assert!(conn.feed_add_capped("ring", 0, &schema).await.is_err());
+        assert!(conn.feed_add_capped("ring", 5, &schema.clone()
+            .col("s", "VarStr")).await.is_err());
+        conn.feed_add_capped("ring", 5, &schema).await?;
+
+        let push = |from: i64, to: i64| {
+            let conn = &conn;
+            async move {
+                conn.data_push("ring", &HashMap::from([
+                    ("x".to_string(), (from..to).map(Dataunit::I).collect()),
+                    ("n".to_string(), (from..to).map(Dataunit::I).collect()),
+                ])).await
+            }
+        };
+        let ints = |from: i64, to: i64| {
+            (from..to).map(Dataunit::I).collect::<Vec<Dataunit>>()
+        };
+        let cols = ["x".to_string(), "n".to_string()];
+
+        push(0, 3).await?;
+        assert_eq!(conn.size_get("ring").await?, 3);
+        push(3, 7).await?;
+        assert_eq!(conn.size_get("ring").await?, 5);
+        assert_eq!(conn.data_get("ring", 0, 5, &cols).await?["x"], 
+                   ints(2, 7));
+
+        assert!(conn.col_add("ring", "s", "VarStr").await.is_err());
+        conn.col_add("ring", "y", "Int64").await?;
+        conn.data_push("ring", &HashMap::from([
+            ("x".to_string(), ints(7, 9)),
+        ])).await?;
+        let ds = conn.data_get("ring", 0, 5, &cols).await?;
+        assert_eq!(ds["x"], ints(4, 9));
+        assert_eq!(ds["n"][2..], [Dataunit::I(6), Dataunit::Null, 
+                                  Dataunit::Null]);
+
+        push(9, 21).await?;
+        assert_eq!(conn.data_get("ring", 0, 5, &cols).await?["x"], 
+                   ints(16, 21));
+        assert_eq!(std::fs::metadata("./tmp/test_capped_feeds/ring/x.col")?
+                       .len(), 40);
+        assert_eq!(conn.raw_get("ring", "x", 3, 2).await?, 
+                   [19i64.to_le_bytes(), 20i64.to_le_bytes()].concat());
+
+        conn.raw_set("ring", "x", 3, 
+                     &[100i64.to_le_bytes(), 101i64.to_le_bytes()].concat())
+            .await?;
+        conn.data_patch("ring", 2, &HashMap::from([
+            ("y".to_string(), ints(-3, 0)),
+        ])).await?;
+
+        assert!(conn.data_delete("ring", 0, 1).await.is_err());
+        assert!(conn.data_delete_rows("ring", &[0]).await.is_err());
+        assert!(conn.size_set("ring", 2).await.is_err());
+        assert!(conn.feed_trim_front("ring", 1, TrimMode::Stable).await
+                    .is_err());
+        assert!(conn.feed_compact("ring").await.is_err());
+        drop(conn);
+
+        let conn = Conn::new("./tmp/test_capped_feeds").await?;
+        let cols = ["x".to_string(), "y".to_string()];
+        let ds = conn.data_get("ring", 0, 5, &cols).await?;
+        assert_eq!(ds["x"], [ints(16, 19), ints(100, 102)].concat());
+        assert_eq!(ds["y"], [vec![Dataunit::I(0); 2], ints(-3, 0)].concat());
+        assert_eq!(conn.feed_list().await[0].head, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_segmented_feeds() -> TokioResult<()> {
+        let conn = open("test_segmented_feeds").await?;
+        let schema = FeedSchema::new()
+            .col("x", "Int64")
+            .col_with("n", "Int64", &ColOptions { 
+                nullable: true, ..Default::default() 
+            });
+        assert!(conn.feed_add_segmented("seg", 6, &schema).await.is_err());
+        assert!(conn.feed_add_segmented("seg", 8, &schema.clone()
+            .col("c", "Category")).await.is_err());
+        conn.feed_add_segmented("seg", 8, &schema).await?;
+
+        let ints = |from: i64, to: i64| {
+            (from..to).map(Dataunit::I).collect::<Vec<Dataunit>>()
+        };
+        let exists = |name: &str| {
+            std::path::Path::new("./tmp/test_segmented_feeds/seg")
+                .join(name).exists()
+        };
+        let cols = ["x".to_string(), "n".to_string(), "y".to_string()];
+
+        conn.data_push("seg", &HashMap::from([
+            ("x".to_string(), ints(0, 20)),
+            ("n".to_string(), ints(0, 20)),
+        ])).await?;
+        assert!(exists("x.col.0") && exists("x.col.2") && exists("n.valid.1"));
+        assert!(!exists("x.col"));
+
+        let err = conn.data_patch("seg", 3, &HashMap::from([
+            ("x".to_string(), ints(-1, 0)),
+        ])).await.unwrap_err();
+        assert_eq!(err.kind(), tokio::io::ErrorKind::PermissionDenied);
+        conn.data_patch("seg", 18, &HashMap::from([
+            ("n".to_string(), vec![Dataunit::Null]),
+        ])).await?;
+
+        assert!(conn.col_add("seg", "s", "VarStr").await.is_err());
+        conn.col_add("seg", "y", "Int64").await?;
+        let ds = conn.data_get("seg", 6, 4, &cols).await?;
+        assert_eq!(ds["x"], ints(6, 10));
+        assert_eq!(ds["y"], vec![Dataunit::I(0); 4]);
+
+        assert!(conn.col_cast("seg", "x", "Float64", CastPolicy::Lossy).await
+                    .is_err());
+        assert!(conn.feed_compact("seg").await.is_err());
+
+        conn.feed_trim_front("seg", 17, TrimMode::Stable).await?;
+        assert!(!exists("x.col.0") && !exists("y.col.1"));
+        assert!(!exists("n.valid.0") && !exists("n.valid.1"));
+        assert!(exists("x.col.2") && exists("n.valid.2"));
+        let ds = conn.data_get("seg", 17, 3, &cols).await?;
+        assert_eq!(ds["x"], ints(17, 20));
+        assert_eq!(ds["n"], [ints(17, 18), vec![Dataunit::Null], 
+                             ints(19, 20)].concat());
+        drop(conn);
+
+        let conn = Conn::new("./tmp/test_segmented_feeds").await?;
+        assert_eq!(conn.feed_list().await[0].segment, 8);
+        conn.size_set("seg", 19).await?;
+        conn.col_rename("seg", "x", "z").await?;
+        assert!(exists("z.col.2") && !exists("x.col.2"));
+        assert_eq!(conn.data_get("seg", 17, 2, &["z".to_string()]).await?
+                       ["z"], ints(17, 19));
+        conn.col_remove("seg", "n").await?;
+        assert!(!exists("n.valid.2") && !exists("n.col.2"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_feeds() -> TokioResult<()> {
+        let conn = open("test_partitioned_feeds").await?;
+        let schema = FeedSchema::new()
+            .col("t", "Int64")
+            .col("v", "Float64");
+        assert!(conn.feed_add_partitioned("ticks", "t", 0, &schema).await
+                    .is_err());
+        assert!(conn.feed_add_partitioned("ticks", "v", 10, &schema).await
+                    .is_err());
+        assert!(conn.feed_add_partitioned("ticks", "x", 10, &schema).await
+                    .is_err());
+        conn.feed_add_partitioned("ticks", "t", 10, &schema).await?;
+
+        let push = |times: &[i64]| {
+            let conn = &conn;
+            let ds = HashMap::from([
+                ("t".to_string(), 
+                 times.iter().map(|&t| Dataunit::I(t)).collect()),
+                ("v".to_string(), 
+                 times.iter().map(|&t| Dataunit::F(t as f64 / 2.0)).collect()),
+            ]);
+            async move { conn.data_push("ticks", &ds).await }
+        };
+        let ints = |times: &[i64]| {
+            times.iter().map(|&t| Dataunit::I(t)).collect::<Vec<Dataunit>>()
+        };
+        let cols = ["t".to_string()];
+
+        push(&[5, 25, 12, -3, 27]).await?;
+        push(&[15]).await?;
+        assert_eq!(conn.size_get("ticks").await?, 6);
+        assert!(conn.feed_exists("ticks.m1").await);
+        assert!(conn.feed_exists("ticks.p2").await);
+        assert_eq!(conn.data_get("ticks", 0, 6, &cols).await?["t"],
+                   ints(&[-3, 5, 12, 15, 25, 27]));
+        assert_eq!(conn.data_get("ticks", 2, 3, &cols).await?["t"],
+                   ints(&[12, 15, 25]));
+        assert!(conn.data_get("ticks", 2, 5, &cols).await.is_err());
+        assert_eq!(conn.history_feed("ticks.p1").await?, []);
+
+        let ds = conn.data_get_time("ticks", 14..26, 
+                                    &["v".to_string()]).await?;
+        assert_eq!(ds["v"], [Dataunit::F(7.5), Dataunit::F(12.5)]);
+        assert!(conn.data_get_time("ticks.p1", 0..10, &cols).await.is_err());
+
+        let pred = Predicate::cmp("t", CmpOp::Ge, Dataunit::I(12));
+        let (ixs, ds) = conn.data_filter("ticks", &pred, &cols, 1..5).await?;
+        assert_eq!(ixs, [2, 3, 4]);
+        assert_eq!(ds["t"], ints(&[12, 15, 25]));
+        let res = conn.aggregate("ticks", 1..5, &[
+            ("t", AggFn::Count), ("t", AggFn::Max),
+        ], Some(&pred)).await?;
+        assert_eq!(res, ints(&[3, 25]));
+        assert_eq!(conn.data_take("ticks", &[4, 0, 2], &cols).await?["t"],
+                   ints(&[25, -3, 12]));
+        assert!(conn.data_take("ticks", &[6], &cols).await.is_err());
+        let datasets = conn.data_get_ranges("ticks", &[(4, 2), (0, 1)],
+                                            &cols).await?;
+        assert_eq!(datasets[0]["t"], ints(&[25, 27]));
+        assert_eq!(datasets[1]["t"], ints(&[-3]));
+        assert_eq!(conn.raw_get("ticks", "t", 1, 2).await?,
+                   [5i64.to_le_bytes(), 12i64.to_le_bytes()].concat());
+
+        conn.data_patch("ticks", 1, &HashMap::from([
+            ("v".to_string(), vec![Dataunit::F(1.0), Dataunit::F(2.0)]),
+        ])).await?;
+        assert_eq!(conn.data_get("ticks", 0, 3, &["v".to_string()]).await?
+                       ["v"],
+                   [Dataunit::F(-1.5), Dataunit::F(1.0), Dataunit::F(2.0)]);
+        assert!(conn.data_patch("ticks", 0, &HashMap::from([
+            ("t".to_string(), ints(&[0])),
+        ])).await.is_err());
+        assert!(conn.raw_set("ticks", "t", 0, &0i64.to_le_bytes()).await
+                    .is_err());
+
+        conn.data_delete("ticks", 1, 2).await?;
+        conn.data_delete_rows("ticks", &[5]).await?;
+        assert_eq!(conn.data_deleted("ticks", 0, 6).await?,
+                   [false, true, true, false, false, true]);
+        assert_eq!(conn.data_get("ticks", 0, 6, &cols).await?["t"],
+                   ints(&[-3, 15, 25]));
+
+        assert!(conn.data_push("ticks", &HashMap::from([
+            ("t".to_string(), vec![Dataunit::Null]),
+        ])).await.is_err());
+        assert!(conn.data_push("ticks", &HashMap::from([
+            ("t".to_string(), ints(&[35, 45])),
+            ("v".to_string(), vec![Dataunit::F(1.0), Dataunit::I(2)]),
+        ])).await.is_err());
+        assert!(!conn.feed_exists("ticks.p3").await);
+        assert_eq!(conn.size_get("ticks").await?, 6);
+        assert!(conn.col_add("ticks", "w", "Int64").await.is_err());
+        assert!(conn.size_set("ticks", 2).await.is_err());
+
+        // The partitions are hidden and cannot be changed directly
+        assert_eq!(conn.feed_list().await.len(), 1);
+        assert_eq!(conn.ns_list("ticks").await.len(), 1);
+        let denied = |res: TokioResult<()>| {
+            res.unwrap_err().kind() == tokio::io::ErrorKind::PermissionDenied
+        };
+        assert!(denied(conn.feed_remove("ticks.p1").await));
+        assert!(denied(conn.feed_add("ticks.p5").await));
+        assert!(denied(conn.col_add("ticks.p1", "w", "Int64").await));
+        assert!(denied(conn.data_push("ticks.p1", &HashMap::from([
+            ("t".to_string(), ints(&[11])),
+        ])).await));
+        assert!(denied(conn.size_set("ticks.p1", 0).await.map(|_| ())));
+        conn.feed_add("ticks.extra").await?;
+        conn.feed_remove("ticks.extra").await?;
+        drop(conn);
+
+        let conn = Conn::new("./tmp/test_partitioned_feeds").await?;
+        let partition_items = conn.partition_list("ticks").await?;
+        assert_eq!(partition_items.iter()
+                       .map(|item| (item.key, item.start, item.size))
+                       .collect::<Vec<_>>(), 
+                   [(-1, 0, 1), (0, 1, 1), (1, 2, 2), (2, 4, 2)]);
+        assert_eq!((partition_items[2].time_min, partition_items[2].time_max), 
+                   (12, 15));
+
+        conn.feed_rename("ticks", "quotes").await?;
+        assert!(conn.feed_exists("quotes.p1").await);
+        assert!(!conn.feed_exists("ticks.p1").await);
+        assert_eq!(conn.data_get("quotes", 3, 3, &cols).await?["t"],
+                   ints(&[15, 25]));
+
+        conn.ns_rename("quotes", "prices").await?;
+        assert_eq!(conn.size_get("prices").await?, 6);
+        conn.ns_remove("prices").await?;
+        assert!(conn.feed_list().await.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_data_filter() -> TokioResult<()> {
+        let conn = open("test_data_filter").await?;
+        conn.feed_add_with_schema("xyz", &FeedSchema::new()
+            .col("x", "Int64")
+            .col("s", "VarStr")
+            .col_with("n", "Float64", &ColOptions { 
+                nullable: true, ..Default::default() 
+            })).await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), (0..10).map(Dataunit::I).collect()),
+            ("s".to_string(), ["a", "b", "c", "d", "e"].repeat(2).iter()
+                .map(|s| Dataunit::S(s.to_string())).collect()),
+            ("n".to_string(), (0..10)
+                .map(|i| if i % 3 == 0 { 
+                    Dataunit::Null 
+                } else { 
+                    Dataunit::F(i as f64) 
+                })
+                .collect()),
+        ])).await?;
+        conn.data_delete("xyz", 4, 1).await?;
+
+        let pred = Predicate::between("x", Dataunit::I(2), Dataunit::I(8))
+            .and(!Predicate::is_null("n"));
+        let (ixs, ds) = conn.data_filter("xyz", &pred, &["s".to_string()], 
+                                         0..10).await?;
+        assert_eq!(ixs, [2, 5, 7, 8]);
+        assert_eq!(ds["s"], ["c", "a", "c", "d"].iter()
+            .map(|s| Dataunit::S(s.to_string())).collect::<Vec<_>>());
+
+        let pred = Predicate::is_in("s", vec![Dataunit::S("b".to_string())])
+            .or(Predicate::cmp("n", CmpOp::Lt, Dataunit::F(2.0)));
+        let (ixs, ds) = conn.data_filter("xyz", &pred, &[], 1..9).await?;
+        assert_eq!(ixs, [1, 6]);
+        assert!(ds.is_empty());
+
+        let pred = Predicate::cmp("y", CmpOp::Eq, Dataunit::I(0));
+        assert!(conn.data_filter("xyz", &pred, &[], 0..10).await.is_err());
+        let pred = Predicate::cmp("x", CmpOp::Eq, Dataunit::I(0));
+        assert!(conn.data_filter("xyz", &pred, &[], 0..11).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_data_filter_numbers() -> TokioResult<()> {
+        let conn = open("test_data_filter_numbers").await?;
+        conn.feed_add_with_schema("xyz", &FeedSchema::new()
+            .col("price", "Decimal(10,2)")
+            .col("n", "Int128")).await?;
+        let s = |x: &str| Dataunit::S(x.to_string());
+        conn.data_push("xyz", &HashMap::from([
+            ("price".to_string(), ["9.00", "10.00", "100.50", "-2.25"].iter()
+                .map(|x| s(x)).collect()),
+            ("n".to_string(), ["9", "10", &i128::MAX.to_string(), "-11"]
+                .iter().map(|x| s(x)).collect()),
+        ])).await?;
+
+        let pred = Predicate::cmp("price", CmpOp::Lt, s("10.00"));
+        let (ixs, ds) = conn.data_filter("xyz", &pred, &["price".to_string()],
+                                         0..4).await?;
+        assert_eq!(ixs, [0, 3]);
+        assert_eq!(ds["price"], [s("9.00"), s("-2.25")]);
+
+        let pred = Predicate::cmp("price", CmpOp::Ge, Dataunit::I(10))
+            .and(Predicate::cmp("price", CmpOp::Lt, Dataunit::F(100.5)));
+        let (ixs, _) = conn.data_filter("xyz", &pred, &[], 0..4).await?;
+        assert_eq!(ixs, [1]);
+
+        let pred = Predicate::cmp("n", CmpOp::Gt, s("9"));
+        let (ixs, _) = conn.data_filter("xyz", &pred, &[], 0..4).await?;
+        assert_eq!(ixs, [1, 2]);
+
+        let pred = Predicate::between("n", Dataunit::I(-11), Dataunit::I(9));
+        let (ixs, _) = conn.data_filter("xyz", &pred, &[], 0..4).await?;
+        assert_eq!(ixs, [0, 3]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_aggregate() -> TokioResult<()> {
+        let conn = open("test_aggregate").await?;
+        conn.feed_add_with_schema("xyz", &FeedSchema::new()
+            .col("x", "Int64")
+            .col("s", "VarStr")
+            .col_with("n", "Float64", &ColOptions { 
+                nullable: true, ..Default::default() 
+            })).await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), (0..10).map(Dataunit::I).collect()),
+            ("s".to_string(), ["a", "b", "c", "d", "e"].repeat(2).iter()
+                .map(|s| Dataunit::S(s.to_string())).collect()),
+            ("n".to_string(), (0..10)
+                .map(|i| if i % 3 == 0 { 
+                    Dataunit::Null 
+                } else { 
+                    Dataunit::F(i as f64) 
+                })
+                .collect()),
+        ])).await?;
+        conn.data_delete("xyz", 4, 1).await?;
+
+        let res = conn.aggregate("xyz", 0..10, &[
+            ("x", AggFn::Sum), ("x", AggFn::Max), ("s", AggFn::Count),
+            ("n", AggFn::Count), ("n", AggFn::Sum), ("n", AggFn::Min),
+        ], None).await?;
+        assert_eq!(res, [
+            Dataunit::I(41), Dataunit::I(9), Dataunit::I(9),
+            Dataunit::I(5), Dataunit::F(23.0), Dataunit::F(1.0),
+        ]);
+
+        let pred = Predicate::cmp("x", CmpOp::Ge, Dataunit::I(5));
+        let res = conn.aggregate("xyz", 2..10, &[
+            ("x", AggFn::Mean), ("n", AggFn::Min), ("n", AggFn::Max),
+        ], Some(&pred)).await?;
+        assert_eq!(res, [
+            Dataunit::F(7.0), Dataunit::F(5.0), Dataunit::F(8.0),
+        ]);
+
+        let res = conn.aggregate("xyz", 0..2, &[("n", AggFn::Stddev)], 
+                                 None).await?;
+        assert_eq!(res, [Dataunit::Null]);
+
+        assert!(conn.aggregate("xyz", 0..10, &[("s", AggFn::Sum)], None)
+            .await.is_err());
+        assert!(conn.aggregate("xyz", 0..11, &[("x", AggFn::Sum)], None)
+            .await.is_err());
+
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(i64::MAX)]),
+            ("s".to_string(), vec![Dataunit::S("f".to_string())]),
+            ("n".to_string(), vec![Dataunit::Null]),
+        ])).await?;
+        assert!(conn.aggregate("xyz", 0..11, &[("x", AggFn::Sum)], None)
+            .await.is_err());
+
+        conn.feed_trim_front("xyz", 3, TrimMode::Stable).await?;
+        let err = conn.aggregate("xyz", 2..11, &[("x", AggFn::Count)], None)
+            .await.unwrap_err();
+        assert_eq!(err.kind(), tokio::io::ErrorKind::InvalidInput);
+        let res = conn.aggregate("xyz", 3..10, &[("x", AggFn::Count)],
+                                 None).await?;
+        assert_eq!(res, [Dataunit::I(6)]);
+
+        let s = |x: &str| Dataunit::S(x.to_string());
+        conn.feed_add_with_schema("money", &FeedSchema::new()
+            .col("price", "Decimal(10,2)")
+            .col("n", "Int128")
+            .col("u", "UInt128")).await?;
+        conn.data_push("money", &HashMap::from([
+            ("price".to_string(), vec![s("0.10"), s("0.20"), s("9.99")]),
+            ("n".to_string(), vec![s("9"), s("10"), s("-1")]),
+            ("u".to_string(), vec![s("1"), s("2"), s("3")]),
+        ])).await?;
+        let res = conn.aggregate("money", 0..3, &[
+            ("price", AggFn::Sum), ("price", AggFn::Max), ("n", AggFn::Sum),
+            ("n", AggFn::Min), ("n", AggFn::Mean), ("u", AggFn::Count),
+        ], None).await?;
+        assert_eq!(res, [
+            s("10.29"), s("9.99"), s("18"), s("-1"), Dataunit::F(6.0),
+            Dataunit::I(3),
+        ]);
+        assert!(conn.aggregate("money", 0..3, &[("u", AggFn::Sum)], None)
+            .await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_group_by() -> TokioResult<()> {
+        let conn = open("test_group_by").await?;
+        let ints = |xs: &[i64]| {
+            xs.iter().copied().map(Dataunit::I).collect::<Vec<Dataunit>>()
+        };
+        conn.feed_add_with_schema("trades", &FeedSchema::new()
+            .col("sym", "Category")
+            .col("status", "Int32")
+            .col("vol", "Float64")).await?;
+        let syms = ["b", "a", "b", "c", "a", "b"];
+        conn.data_push("trades", &HashMap::from([
+            ("sym".to_string(), syms.iter()
+                .map(|s| Dataunit::S(s.to_string())).collect()),
+            ("status".to_string(), [1, 2, 1, 1, 2, 2].into_iter()
+                .map(Dataunit::I).collect()),
+            ("vol".to_string(), [1.5, 2.0, 2.5, 4.0, 3.0, 0.5].into_iter()
+                .map(Dataunit::F).collect()),
+        ])).await?;
+        conn.data_delete("trades", 3, 1).await?;
+
+        let aggs = [("vol", AggFn::Sum), ("vol", AggFn::Count)];
+        let ds = conn.group_by("trades", &["sym"], &aggs, 0..6).await?;
+        assert_eq!(ds.len(), 3);
+        assert_eq!(ds["sym"], ["a", "b"].iter()
+            .map(|s| Dataunit::S(s.to_string())).collect::<Vec<_>>());
+        assert_eq!(ds["sum(vol)"], [Dataunit::F(5.0), Dataunit::F(4.5)]);
+        assert_eq!(ds["count(vol)"], ints(&[2, 3]));
+
+        let options = GroupOptions { max_groups: 1 };
+        let ds = conn.group_by_with("trades", &["status", "sym"], 
+                                    &[("vol", AggFn::Max)], 1..6, 
+                                    &options).await?;
+        assert_eq!(ds["status"], ints(&[1, 2, 2]));
+        assert_eq!(ds["sym"], ["b", "a", "b"].iter()
+            .map(|s| Dataunit::S(s.to_string())).collect::<Vec<_>>());
+        assert_eq!(ds["max(vol)"], 
+                   [Dataunit::F(2.5), Dataunit::F(3.0), Dataunit::F(0.5)]);
+        let mut entries = tokio::fs::read_dir("./tmp/test_group_by/group.tmp")
+            .await?;
+        assert!(entries.next_entry().await?.is_none());
+
+        assert!(conn.group_by("trades", &["vol"], &aggs, 0..6).await
+            .is_err());
+        assert!(conn.group_by("trades", &["sym"], &[("sym", AggFn::Sum)], 
+                              0..6).await.is_err());
+        assert!(conn.group_by("trades", &["sym"], &aggs, 0..7).await
+            .is_err());
+
+        conn.feed_trim_front("trades", 2, TrimMode::Stable).await?;
+        let err = conn.group_by("trades", &["sym"], &aggs, 1..6).await
+            .unwrap_err();
+        assert_eq!(err.kind(), tokio::io::ErrorKind::InvalidInput);
+        let ds = conn.group_by("trades", &["sym"], &aggs, 2..6).await?;
+        assert_eq!(ds["count(vol)"], ints(&[1, 2]));
+
+        // The wide integers are ordered as numbers
+        conn.feed_add_with_schema("wide", &FeedSchema::new()
+            .col("n", "Int128")
+            .col("x", "Int64")).await?;
+        conn.data_push("wide", &HashMap::from([
+            ("n".to_string(), ["10", "9", "-1", "9"].iter()
+                .map(|s| Dataunit::S(s.to_string())).collect()),
+            ("x".to_string(), ints(&[i64::MAX, 1, 2, 3])),
+        ])).await?;
+        let ds = conn.group_by_with("wide", &["n"], &[("x", AggFn::Sum)], 
+                                    0..4, &options).await?;
+        assert_eq!(ds["n"], ["-1", "9", "10"].iter()
+            .map(|s| Dataunit::S(s.to_string())).collect::<Vec<_>>());
+        assert_eq!(ds["sum(x)"], ints(&[2, 4, i64::MAX]));
+
+        // The unsigned ones are ordered as numbers above `i128::MAX` too
+        let ks = [3, 0, 5, 1, 7, 2, 6, 4];
+        conn.feed_add_with_schema("big", &FeedSchema::new()
+            .col("u", "UInt128")
+            .col("k", "Int64")).await?;
+        conn.data_push("big", &HashMap::from([
+            ("u".to_string(), ks.iter()
+                .map(|k| Dataunit::S((u128::MAX - *k as u128).to_string()))
+                .collect()),
+            ("k".to_string(), ints(&ks)),
+        ])).await?;
+        let ds = conn.group_by_with("big", &["u"], &[("k", AggFn::Max)], 
+                                    0..8, &options).await?;
+        assert_eq!(ds["max(k)"], ints(&[7, 6, 5, 4, 3, 2, 1, 0]));
+
+        // The spilled files are removed if an aggregate fails
+        conn.data_push("wide", &HashMap::from([
+            ("n".to_string(), vec![Dataunit::S("10".to_string())]),
+            ("x".to_string(), ints(&[1])),
+        ])).await?;
+        assert!(conn.group_by_with("wide", &["n"], &[("x", AggFn::Sum)], 
+                                   0..5, &options).await.is_err());
+        let mut entries = tokio::fs::read_dir("./tmp/test_group_by/group.tmp")
+            .await?;
+        assert!(entries.next_entry().await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_data_take() -> TokioResult<()> {
+        let conn = open("test_data_take").await?;
+        conn.feed_add_with_schema("xyz", &FeedSchema::new()
+            .col("x", "Int64")
+            .col("s", "VarStr")).await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), (0..1000).map(Dataunit::I).collect()),
+            ("s".to_string(), (0..1000)
+                .map(|i| Dataunit::S(format!("s{}", i))).collect()),
+        ])).await?;
+        conn.data_delete("xyz", 4, 1).await?;
+
+        let cols = ["x".to_string(), "s".to_string()];
+        let ds = conn.data_take("xyz", &[999, 3, 500, 3, 0], &cols).await?;
+        assert_eq!(ds["x"], [999, 3, 500, 3, 0].into_iter()
+            .map(Dataunit::I).collect::<Vec<_>>());
+        assert_eq!(ds["s"][2], Dataunit::S("s500".to_string()));
+
+        assert_eq!(conn.data_take("xyz", &[3, 4], &cols).await.unwrap_err()
+                       .kind(), tokio::io::ErrorKind::NotFound);
+        let options = ReadOptions { include_deleted: true };
+        let ds = conn.data_take_with("xyz", &[3, 4], &cols, &options).await?;
+        assert_eq!(ds["x"], [Dataunit::I(3), Dataunit::I(4)]);
+
+        let ds = conn.data_take("xyz", &[], &cols).await?;
+        assert!(ds["x"].is_empty());
+        assert!(conn.data_take("xyz", &[5, 1000], &cols).await.is_err());
+        assert!(conn.data_take("xyz", &[5], &["y".to_string()]).await
+            .is_err());
+
+        conn.feed_trim_front("xyz", 10, TrimMode::Stable).await?;
+        let ds = conn.data_take("xyz", &[12, 10], &cols).await?;
+        assert_eq!(ds["x"], [Dataunit::I(12), Dataunit::I(10)]);
+        assert!(conn.data_take("xyz", &[9], &cols).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_data_get_ranges() -> TokioResult<()> {
+        let conn = open("test_data_get_ranges").await?;
+        let schema = FeedSchema::new()
+            .col("x", "Int64")
+            .col("y", "Float64");
+        let ints = |from: i64, to: i64| {
+            (from..to).map(Dataunit::I).collect::<Vec<Dataunit>>()
+        };
+        let cols = ["x".to_string(), "y".to_string()];
+
+        conn.feed_add_with_schema("xyz", &schema).await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), ints(0, 100)),
+            ("y".to_string(), (0..100).map(|i| Dataunit::F(i as f64))
+                .collect()),
+        ])).await?;
+        conn.data_delete("xyz", 52, 1).await?;
+
+        let datasets = conn.data_get_ranges("xyz", &[(10, 3), (50, 4), (0, 0)], 
+                                            &cols).await?;
+        assert_eq!(datasets.len(), 3);
+        assert_eq!(datasets[0]["x"], ints(10, 13));
+        assert_eq!(datasets[1]["x"], 
+                   [Dataunit::I(50), Dataunit::I(51), Dataunit::I(53)]);
+        assert_eq!(datasets[1]["y"][2], Dataunit::F(53.0));
+        assert!(datasets[2]["y"].is_empty());
+
+        let datasets = conn.data_get_ranges("xyz", &[(20, 3), (21, 3), (24, 2), 
+                                                     (20, 1)], &cols).await?;
+        assert_eq!(datasets[0]["x"], ints(20, 23));
+        assert_eq!(datasets[1]["x"], ints(21, 24));
+        assert_eq!(datasets[2]["x"], ints(24, 26));
+        assert_eq!(datasets[3]["y"], [Dataunit::F(20.0)]);
+        assert!(conn.data_get_ranges("xyz", &[(0, 1), (99, 2)], &cols).await
+            .is_err());
+
+        conn.feed_add_capped("ring", 5, &schema).await?;
+        conn.data_push("ring", &HashMap::from([
+            ("x".to_string(), ints(0, 7)),
+            ("y".to_string(), vec![Dataunit::F(0.0); 7]),
+        ])).await?;
+        let datasets = conn.data_get_ranges("ring", &[(0, 5), (3, 1)], 
+                                            &cols).await?;
+        assert_eq!(datasets[0]["x"], ints(2, 7));
+        assert_eq!(datasets[1]["x"], ints(5, 6));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_meta() -> TokioResult<()> {
+        let conn = open("test_meta").await?;
+        conn.feed_add("xyz").await?;
+        conn.feed_add("abc").await?;
+        conn.col_add("xyz", "x", "Int64").await?;
+        conn.col_add("xyz", "y", "Float64").await?;
+
+        let meta = conn.feed_meta_get("xyz").await?;
+        assert!(meta.created > 0);
+        assert_eq!(meta.description, "");
+
+        let tags = HashMap::from([
+            ("owner".to_string(), "qwe".to_string()),
+        ]);
+        conn.feed_meta_set("xyz", "Some measurements", &tags).await?;
+        conn.col_meta_set("xyz", "y", "Weight", &HashMap::from([
+            ("unit".to_string(), "kg".to_string()),
+        ])).await?;
+        conn.col_rename("xyz", "y", "w").await?;
+        conn.col_cast("xyz", "w", "Float32", CastPolicy::Lossy).await?;
+        conn.feed_remove("abc").await?;
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_meta").await?;
+        let meta = conn.feed_meta_get("xyz").await?;
+        assert_eq!(meta.description, "Some measurements");
+        assert_eq!(meta.tags, tags);
+        assert!(meta.modified >= meta.created);
+
+        let meta = conn.col_meta_get("xyz", "w").await?;
+        assert_eq!(meta.description, "Weight");
+        assert_eq!(meta.tags["unit"], "kg");
+        assert_eq!(conn.col_meta_get("xyz", "x").await?.description, "");
+        assert!(conn.col_meta_get("xyz", "y").await.is_err());
+        assert!(conn.feed_meta_get("abc").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_namespaces() -> TokioResult<()> {
+        let conn = open("test_namespaces").await?;
+        for feed_name in ["market", "market.eu.ticks", "market.eu.bars", 
+                          "market.us.ticks", "marketing"] {
+            conn.feed_add(feed_name).await?;
+            conn.col_add(feed_name, "x", "Int64").await?;
+        }
+        assert!(std::fs::exists(
+            "./tmp/test_namespaces/market/eu/ticks/x.col"
+        )?);
+
+        let names = |feed_items: Vec<FeedItem>| {
+            let mut names = feed_items.iter()
+                .map(|feed_item| feed_item.get_name())
+                .collect::<Vec<String>>();
+            names.sort();
+            names
+        };
+        assert_eq!(names(conn.ns_list("market.eu").await), 
+                   ["market.eu.bars", "market.eu.ticks"]);
+        assert_eq!(names(conn.ns_list("market").await).len(), 4);
+
+        conn.data_push("market.eu.ticks", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(5)]),
+        ])).await?;
+        conn.ns_rename("market.eu", "stock.europe").await?;
+        assert!(conn.ns_rename("market", "market.old").await.is_err());
+        assert!(conn.ns_rename("market.us", "stock.europe").await.is_err());
+        assert!(!std::fs::exists("./tmp/test_namespaces/market/eu")?);
+        assert_eq!(conn.data_get("stock.europe.ticks", 0, 1, 
+                                 &["x".to_string()]).await?["x"], 
+                   vec![Dataunit::I(5)]);
+
+        conn.feed_remove("market").await?;
+        assert!(conn.feed_exists("market.us.ticks").await);
+        conn.ns_remove("market").await?;
+        assert!(!std::fs::exists("./tmp/test_namespaces/market")?);
+        assert!(conn.ns_remove("market").await.is_err());
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_namespaces").await?;
+        assert_eq!(names(conn.feed_list().await), 
+                   ["marketing", "stock.europe.bars", "stock.europe.ticks"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flat_dirs() -> TokioResult<()> {
+        let path = "./tmp/test_flat_dirs";
+        let conn = open("test_flat_dirs").await?;
+        conn.feed_add("market.ticks").await?;
+        conn.col_add("market.ticks", "x", "Int64").await?;
+        conn.data_push("market.ticks", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(5)]),
+        ])).await?;
+        drop(conn);
+
+        // The feed is moved from the flat directory of older versions
+        std::fs::rename(path_concat!(path, "market", "ticks"), 
+                        path_concat!(path, "market.ticks"))?;
+        std::fs::remove_dir(path_concat!(path, "market"))?;
+        let conn = Conn::new(path).await?;
+        assert!(std::fs::exists(path_concat!(path, "market", "ticks"))?);
+        assert!(!std::fs::exists(path_concat!(path, "market.ticks"))?);
+        assert_eq!(conn.data_get("market.ticks", 0, 1, &["x".to_string()])
+                       .await?["x"], vec![Dataunit::I(5)]);
+        drop(conn);
+
+        // The feed without its directory is not recreated empty
+        std::fs::remove_dir_all(path_concat!(path, "market"))?;
+        assert_eq!(Conn::new(path).await.err().unwrap().kind(), 
+                   tokio::io::ErrorKind::NotFound);
+        assert!(!std::fs::exists(path_concat!(path, "market"))?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_long_names() -> TokioResult<()> {
+        let conn = open("test_long_names").await?;
+        let prefix = ["q".repeat(150), "w".repeat(150)].join(".");
+        let names = [format!("{}.a", prefix), format!("{}.b", prefix)];
+        for name in names.iter() {
+            conn.feed_add(name).await?;
+            conn.col_add(name, &"x".repeat(200), "Int64").await?;
+        }
+        assert!(conn.feed_add(&"q".repeat(201)).await.is_err());
+        assert!(conn.col_add(&names[0], &["x"; 101].join("."), "Int64")
+                    .await.is_err());
+        assert!(conn.col_add(&names[0], "col.list", "VarStr")
+                    .await.is_err());
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_long_names").await?;
+        assert_eq!(conn.feed_list().await.len(), 2);
+        for name in names.iter() {
+            assert!(conn.col_exists(name, &"x".repeat(200)).await?);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_feed_ensure() -> TokioResult<()> {
+        let conn = open("test_feed_ensure").await?;
+        let schema = FeedSchema::new()
+            .col("x", "Int64")
+            .col_with("y", "Float64", &ColOptions { 
+                nullable: true, ..Default::default() 
+            });
+
+        let diff = conn.feed_ensure("xyz", &schema).await?;
+        assert!(diff.feed_added);
+        assert_eq!(diff.cols_added, ["x", "y"]);
+
+        let diff = conn.feed_ensure("xyz", &schema).await?;
+        assert_eq!(diff, SchemaDiff::default());
+
+        conn.col_add("xyz", "z", "Int32").await?;
+        let schema = schema.col("s", "VarStr");
+        let diff = conn.feed_ensure("xyz", &schema).await?;
+        assert!(!diff.feed_added);
+        assert_eq!(diff.cols_added, ["s"]);
+        assert_eq!(diff.cols_extra, ["z"]);
+
+        let err = conn.feed_ensure("xyz", &FeedSchema::new()
+            .col("x", "Int32")
+            .col("w", "Int32")).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "x datatype (Int32 expected, Int64 found)");
+        assert!(!conn.col_exists("xyz", "w").await?);
+        let err = conn.feed_ensure("xyz", &FeedSchema::new()
+            .col_with("y", "Float64", &ColOptions {
+                nullable: false, default: Some(Dataunit::F(0.5))
+            })).await.unwrap_err();
+        assert_eq!(err.to_string(),
+                   "y nullable (false expected, true found), \
+                    y default (Some(F(0.5)) expected, None found)");
+        assert!(conn.feed_ensure("xyz", &FeedSchema::new()
+            .col("w", "Int32")
+            .col("w", "Int64")).await.is_err());
+
+        let names = conn.feed_schema("xyz").await?.cols.into_iter()
+            .map(|col| col.name)
+            .collect::<Vec<String>>();
+        assert_eq!(names, ["x", "y", "z", "s"]);
+        assert!(conn.feed_schema("xyz").await?.cols[1].options.nullable);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_history() -> TokioResult<()> {
+        let conn = open("test_history").await?;
+        conn.feed_add("xyz").await?;
+        conn.col_add("xyz", "x", "Int32").await?;
+        conn.col_add("xyz", "y", "Float64").await?;
+        conn.data_push("xyz", &HashMap::from([
+            ("x".to_string(), vec![Dataunit::I(1), Dataunit::I(2)]),
+        ])).await?;
+        conn.size_set("xyz", 1).await?;
+        conn.size_set("xyz", 3).await?;
+        conn.col_cast("xyz", "x", "Int64", CastPolicy::Strict).await?;
+        conn.col_rename("xyz", "y", "z").await?;
+        conn.col_remove("xyz", "z").await?;
+        conn.feed_rename("xyz", "abc").await?;
+        conn.feed_add("qwe").await?;
+        conn.feed_remove("qwe").await?;
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_history").await?;
+        assert_eq!(conn.history_size().await?, 10);
+        assert!(conn.history_get(5, 6).await.is_err());
+
+        let ops = conn.history_get(0, 10).await?.into_iter()
+            .map(|item| (item.op, item.col, item.details))
+            .collect::<Vec<(HistoryOp, String, String)>>();
+        let op = |op, col: &str, details: &str| {
+            (op, col.to_string(), details.to_string())
+        };
+        assert_eq!(ops[..8], [
+            op(HistoryOp::FeedAdd, "", ""),
+            op(HistoryOp::ColAdd, "x", "Int32"),
+            op(HistoryOp::ColAdd, "y", "Float64"),
+            op(HistoryOp::SizeTruncate, "", "2 -> 1"),
+            op(HistoryOp::ColCast, "x", "Int32 -> Int64"),
+            op(HistoryOp::ColRename, "y", "z"),
+            op(HistoryOp::ColRemove, "z", ""),
+            op(HistoryOp::FeedRename, "", "abc"),
+        ]);
+
+        assert_eq!(conn.history_feed("xyz").await?.len(), 8);
+        assert_eq!(conn.history_feed("qwe").await?.len(), 2);
+        assert!(conn.history_feed("abc").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_templates() -> TokioResult<()> {
+        let conn = open("test_templates").await?;
+        let schema = FeedSchema::new()
+            .col("price", "Float64")
+            .col_with("size", "Int64", &ColOptions { 
+                default: Some(Dataunit::I(1)), ..Default::default() 
+            })
+            .col("side", "Category");
+
+        conn.feed_add_with_schema("ticks.MSFT", &schema).await?;
+        assert_eq!(conn.feed_schema("ticks.MSFT").await?, schema);
+        assert!(conn.feed_add_with_schema("ticks.MSFT", &schema)
+                    .await.is_err());
+        assert!(conn.feed_add_with_schema("ticks.IBM", &schema.clone()
+            .col("price", "Int64")).await.is_err());
+        assert!(!conn.feed_exists("ticks.IBM").await);
+
+        conn.template_add("ticks", &schema).await?;
+        assert!(conn.template_add("ticks", &schema).await.is_err());
+        assert!(conn.feed_add_from_template("bars", "bars.AAPL")
+                    .await.is_err());
+
+        drop(conn);
+        let conn = Conn::new("./tmp/test_templates").await?;
+        assert_eq!(conn.template_list().await?, ["ticks"]);
+        conn.feed_add_from_template("ticks", "ticks.AAPL").await?;
+        assert_eq!(conn.feed_schema("ticks.AAPL").await?, schema);
+        conn.data_push("ticks.AAPL", &HashMap::from([
+            ("price".to_string(), vec![Dataunit::F(1.5)]),
+        ])).await?;
+        assert_eq!(conn.data_get("ticks.AAPL", 0, 1, &["size".to_string()])
+                       .await?["size"], vec![Dataunit::I(1)]);
+        assert_eq!(conn.history_feed("ticks.AAPL").await?.len(), 4);
+
+        conn.template_remove("ticks").await?;
+        assert!(conn.template_get("ticks").await.is_err());
+        assert!(conn.template_list().await?.is_empty());
+
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_legacy_lists() -> TokioResult<()> {
+        use std::io::ErrorKind;
+        use crate::utils::to_bytes_many;
+        use crate::list::ListLegacyTrait;
+
+        // The names are padded with zeros up to 256 bytes
+        let name = |name: &str| {
+            let mut block = name.as_bytes().to_vec();
+            block.resize(256, 0);
+            block
+        };
+
+        // Write the lists and the data in the legacy layout as the baseline
+        // stored them: a feed is its name and size, a column is the tag
+        // of the datatype padded to 8 bytes, the length of bytes and
+        // the name
+        let path = "./tmp/test_legacy_lists";
+        let _ = tokio::fs::remove_dir_all(path).await;
+        tokio::fs::create_dir_all(path_concat!(path, "xyz")).await?;
+        tokio::fs::write(path_concat!(path, "feed.list"), [
+            name("xyz"), 2u64.to_le_bytes().to_vec(),
+        ].concat()).await?;
+        tokio::fs::write(path_concat!(path, "xyz", "col.list"), [
+            vec![0u8; 16], name("x"),
+            vec![4u8, 0, 0, 0, 0, 0, 0, 0], 3u64.to_le_bytes().to_vec(),
+            name("b"),
+        ].concat()).await?;
+        tokio::fs::write(path_concat!(path, "xyz", "x.col"), 
+                         to_bytes_many(&[5i64, -7i64])).await?;
+        tokio::fs::write(path_concat!(path, "xyz", "b.col"), [1u8; 6]).await?;
+
+        let conn = Conn::new(path).await?;
+        assert_eq!(conn.size_get("xyz").await?, 2);
+        let col_items = conn.col_list("xyz").await?;
+        assert!(col_items.iter().any(|col_item| col_item.get_name() == "b"
+                && col_item.get_datatype() == "Bytes[3]"));
+        let ds = conn.data_get("xyz", 0, 2, &["x".to_string()]).await?;
+        assert_eq!(ds["x"], [Dataunit::I(5), Dataunit::I(-7)]);
+
+        // The lists are stored in the current format after reopening
+        conn.col_add("xyz", "y", "Float64").await?;
+        drop(conn);
+        let conn = Conn::new(path).await?;
+        assert_eq!(conn.col_list("xyz").await?.len(), 3);
+        assert!(!tokio::fs::try_exists(path_concat!(path, "feed.list.legacy"))
+                    .await?);
+
+        // An unknown datatype tag is rejected
+        let block = [vec![9u8; 16], name("z")].concat();
+        let err = ColItem::from_legacy(&block).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        Ok(())
     }
 }
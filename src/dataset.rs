@@ -15,6 +15,15 @@ use crate::datatype::Dataunit;
 pub type Dataset = HashMap<String, Vec<Dataunit>>;
 
 
+/// Options of reading the data from a feed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Whether to return the rows marked as deleted. By default they are
+    /// skipped, so the result may be shorter than the requested range.
+    pub include_deleted: bool,
+}
+
+
 /// Get size of the dataset. It works correctly for valid datasets because the 
 /// function returns the length of the first vector. Otherwise it returns error.
 pub fn get_dataset_size(ds: &Dataset) -> TokioResult<usize> {
@@ -1,6 +1,7 @@
 //! Converting between datatypes for different purposes: into bytes and back,
 //! serializations, from and into strings and so on.
 
+use std::fmt;
 use std::mem::size_of;
 use std::str::FromStr;
 
@@ -10,8 +11,9 @@ use serde::{Serialize, Deserialize};
 use crate::utils::{to_bytes, from_bytes};
 
 
-/// A dataunit for convenient integration. It supports integers, floats and
-/// strings that should represent fixed size bytes encrypted with Base64.
+/// A dataunit for convenient integration. It supports integers, floats,
+/// strings that should represent fixed size bytes encrypted with Base64,
+/// lists of them for array datatypes and null for missing values.
 /// It is compatible with `serde` serialization so it may be used in
 /// API interfaces like, for example, `actix_web` provides.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -25,12 +27,27 @@ pub enum Dataunit {
 
     /// String
     S(String),
+
+    /// List of the values for array datatypes
+    List(Vec<Dataunit>),
+
+    /// Missing value for nullable columns
+    Null,
 }
 
 
+/// Maximum precision of the decimal datatype.
+pub const MAX_DECIMAL_PRECISION: u8 = 38;
+
+/// Maximum precision of the decimal datatype stored as 64-bit integer.
+const MAX_DECIMAL64_PRECISION: u8 = 18;
+
+
 /// Allowed datatypes for the stored data. It manages the converting between
 /// basic datatypes and bytes in the file. Integers and floats cast and convert
-/// normally, bytes convert to strings and back according the Base64 algorithm.
+/// normally, bytes convert to strings and back according the Base64 algorithm,
+/// decimals and 128-bit integers convert to strings with the exact decimal
+/// representation, UUIDs convert to their canonical strings.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Datatype {
     /// 64-bit integer.
@@ -47,6 +64,35 @@ pub enum Datatype {
 
     /// Bytes with the fized size.
     Bytes(usize),
+
+    /// Bytes with a variable length.
+    VarBytes,
+
+    /// UTF-8 string with a variable length.
+    VarStr,
+
+    /// UTF-8 string from a small set of distinct values. The values are
+    /// stored as integer codes of the column dictionary.
+    Category,
+
+    /// Array of the fixed number of values of a fixed size datatype stored
+    /// contiguously, for example, `Float32[768]`.
+    Array(Box<Datatype>, usize),
+
+    /// Fixed-point decimal with the precision (total number of digits) and
+    /// the scale (number of digits after the point), for example,
+    /// `Decimal(10,2)`. It is stored as the integer scaled by `10^scale`:
+    /// 64-bit for the precision up to 18 and 128-bit otherwise.
+    Decimal(u8, u8),
+
+    /// UUID represented as the canonical hyphenated string.
+    Uuid,
+
+    /// 128-bit integer represented as the decimal string.
+    Int128,
+
+    /// 128-bit unsigned integer represented as the decimal string.
+    UInt128,
 }
 
 
@@ -91,6 +137,74 @@ impl Datatype {
                     None
                 }
             },
+            Self::VarBytes => {
+                if let Dataunit::S(x) = x {
+                    BASE64_STANDARD.decode(x).ok()
+                } else {
+                    None
+                }
+            },
+            Self::VarStr | Self::Category => {
+                if let Dataunit::S(x) = x {
+                    Some(x.as_bytes().to_vec())
+                } else {
+                    None
+                }
+            },
+            Self::Array(datatype, len) => {
+                if let Dataunit::List(x) = x {
+                    if x.len() == *len {
+                        x.iter()
+                            .map(|unit| datatype.to_bytes(unit))
+                            .collect::<Option<Vec<Vec<u8>>>>()
+                            .map(|blocks| blocks.concat())
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            },
+            Self::Decimal(precision, scale) => {
+                let value = match x {
+                    Dataunit::S(x) => parse_decimal(x, *scale),
+                    Dataunit::I(x) => 10i128.checked_pow(*scale as u32)
+                        .and_then(|factor| factor.checked_mul(*x as i128)),
+                    Dataunit::F(x) if x.is_finite() => parse_decimal(
+                        &format!("{:.*}", *scale as usize, x), *scale
+                    ),
+                    _ => None,
+                }?;
+
+                if value.unsigned_abs() >= 10u128.pow(*precision as u32) {
+                    None
+                } else if *precision <= MAX_DECIMAL64_PRECISION {
+                    Some((value as i64).to_le_bytes().to_vec())
+                } else {
+                    Some(value.to_le_bytes().to_vec())
+                }
+            },
+            Self::Uuid => {
+                if let Dataunit::S(x) = x {
+                    parse_uuid(x).map(|block| block.to_vec())
+                } else {
+                    None
+                }
+            },
+            Self::Int128 => {
+                match x {
+                    Dataunit::S(x) => x.parse::<i128>().ok(),
+                    Dataunit::I(x) => Some(*x as i128),
+                    _ => None,
+                }.map(|x| x.to_le_bytes().to_vec())
+            },
+            Self::UInt128 => {
+                match x {
+                    Dataunit::S(x) => x.parse::<u128>().ok(),
+                    Dataunit::I(x) => u128::try_from(*x).ok(),
+                    _ => None,
+                }.map(|x| x.to_le_bytes().to_vec())
+            },
         }
     }
 
@@ -113,10 +227,102 @@ impl Datatype {
                 let string = BASE64_STANDARD.encode(&block[..*len]);
                 Dataunit::S(string)
             },
+            Self::VarBytes => {
+                Dataunit::S(BASE64_STANDARD.encode(block))
+            },
+            Self::VarStr | Self::Category => {
+                Dataunit::S(String::from_utf8_lossy(block).into_owned())
+            },
+            Self::Array(datatype, len) => {
+                Dataunit::List(
+                    block[..datatype.size() * len].chunks(datatype.size())
+                        .map(|chunk| datatype.from_bytes(chunk))
+                        .collect()
+                )
+            },
+            Self::Decimal(precision, scale) => {
+                let value = if *precision <= MAX_DECIMAL64_PRECISION {
+                    i64::from_le_bytes(block[..8].try_into().unwrap()) as i128
+                } else {
+                    i128::from_le_bytes(block[..16].try_into().unwrap())
+                };
+                Dataunit::S(format_decimal(value, *scale))
+            },
+            Self::Uuid => {
+                Dataunit::S(format_uuid(block[..16].try_into().unwrap()))
+            },
+            Self::Int128 => {
+                let x = i128::from_le_bytes(block[..16].try_into().unwrap());
+                Dataunit::S(x.to_string())
+            },
+            Self::UInt128 => {
+                let x = u128::from_le_bytes(block[..16].try_into().unwrap());
+                Dataunit::S(x.to_string())
+            },
+        }
+    }
+
+    /// Get the value `x` of a `Decimal` (as the integer scaled by 
+    /// `10^scale`), `Int128` or `UInt128` column as an integer. The strings
+    /// are parsed and the integers are scaled. It is `None` for the other 
+    /// datatypes and for the values that cannot be represented exactly.
+    pub fn to_i128(&self, x: &Dataunit) -> Option<i128> {
+        match (self, x) {
+            (Self::Decimal(_, scale), Dataunit::S(x)) => {
+                parse_decimal(x, *scale)
+            },
+            (Self::Decimal(_, scale), Dataunit::I(x)) => {
+                10i128.checked_pow(*scale as u32)
+                    .and_then(|factor| factor.checked_mul(*x as i128))
+            },
+            (Self::Int128 | Self::UInt128, Dataunit::S(x)) => {
+                x.parse::<i128>().ok()
+            },
+            (Self::Int128 | Self::UInt128, Dataunit::I(x)) => {
+                Some(*x as i128)
+            },
+            _ => None,
         }
     }
 
-    /// Size in bytes.
+    /// Get the value `x` of a `UInt128` column as an unsigned integer, so
+    /// the values above `i128::MAX` are represented exactly. It is `None` 
+    /// for the other datatypes and for the negative values.
+    pub fn to_u128(&self, x: &Dataunit) -> Option<u128> {
+        match (self, x) {
+            (Self::UInt128, Dataunit::S(x)) => x.parse::<u128>().ok(),
+            (Self::UInt128, Dataunit::I(x)) => u128::try_from(*x).ok(),
+            _ => None,
+        }
+    }
+
+    /// Get the value of a `Decimal`, `Int128` or `UInt128` column from 
+    /// the integer `x` given like `to_i128` does. It is `None` for the other
+    /// datatypes.
+    pub fn from_i128(&self, x: i128) -> Option<Dataunit> {
+        match self {
+            Self::Decimal(_, scale) => {
+                Some(Dataunit::S(format_decimal(x, *scale)))
+            },
+            Self::Int128 | Self::UInt128 => Some(Dataunit::S(x.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Check whether the values of the datatype have a variable length.
+    pub fn is_var(&self) -> bool {
+        matches!(self, Self::VarBytes | Self::VarStr)
+    }
+
+    /// Check whether the values of the datatype are stored as they are 
+    /// in fixed size blocks, so the datatype can be an array item.
+    pub fn is_fixed(&self) -> bool {
+        !self.is_var() && *self != Self::Category
+    }
+
+    /// Size in bytes. For the variable length datatypes it is the size of
+    /// the offset that is stored for each value, for `Category` it is
+    /// the size of the dictionary code.
     pub fn size(&self) -> usize {
         match self {
             Self::Int64 => size_of::<i64>(),
@@ -124,19 +330,40 @@ impl Datatype {
             Self::Int32 => size_of::<i32>(),
             Self::Float32 => size_of::<f32>(),
             Self::Bytes(len) => *len,
+            Self::VarBytes | Self::VarStr => size_of::<u64>(),
+            Self::Category => size_of::<u32>(),
+            Self::Array(datatype, len) => datatype.size() * len,
+            Self::Decimal(precision, _) => {
+                if *precision <= MAX_DECIMAL64_PRECISION {
+                    size_of::<i64>()
+                } else {
+                    size_of::<i128>()
+                }
+            },
+            Self::Uuid | Self::Int128 | Self::UInt128 => size_of::<u128>(),
         }
     }
 }
 
 
-impl ToString for Datatype {
-    fn to_string(&self) -> String {
+impl fmt::Display for Datatype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Int64 => "Int64".to_string(),
-            Self::Float64 => "Float64".to_string(),
-            Self::Int32 => "Int32".to_string(),
-            Self::Float32 => "Float32".to_string(),
-            Self::Bytes(len) => format!("Bytes[{}]", len),
+            Self::Int64 => write!(f, "Int64"),
+            Self::Float64 => write!(f, "Float64"),
+            Self::Int32 => write!(f, "Int32"),
+            Self::Float32 => write!(f, "Float32"),
+            Self::Bytes(len) => write!(f, "Bytes[{}]", len),
+            Self::VarBytes => write!(f, "VarBytes"),
+            Self::VarStr => write!(f, "VarStr"),
+            Self::Category => write!(f, "Category"),
+            Self::Array(datatype, len) => write!(f, "{}[{}]", datatype, len),
+            Self::Decimal(precision, scale) => {
+                write!(f, "Decimal({},{})", precision, scale)
+            },
+            Self::Uuid => write!(f, "Uuid"),
+            Self::Int128 => write!(f, "Int128"),
+            Self::UInt128 => write!(f, "UInt128"),
         }
     }
 }
@@ -151,22 +378,129 @@ impl FromStr for Datatype {
             "Float64" => Ok(Self::Float64),
             "Int32" => Ok(Self::Int32),
             "Float32" => Ok(Self::Float32),
+            "VarBytes" => Ok(Self::VarBytes),
+            "VarStr" => Ok(Self::VarStr),
+            "Category" => Ok(Self::Category),
+            "Uuid" => Ok(Self::Uuid),
+            "Int128" => Ok(Self::Int128),
+            "UInt128" => Ok(Self::UInt128),
+            _ if s.starts_with("Decimal(") && s.ends_with(')') => {
+                let (precision, scale) = s
+                    .strip_prefix("Decimal(")
+                    .and_then(|s| s.strip_suffix(')'))
+                    .and_then(|s| s.split_once(','))
+                    .and_then(|(precision, scale)| Some((
+                        precision.trim().parse::<u8>().ok()?,
+                        scale.trim().parse::<u8>().ok()?,
+                    )))
+                    .ok_or("Unknown datatype".to_string())?;
+
+                if 0 < precision && precision <= MAX_DECIMAL_PRECISION && 
+                        scale <= precision {
+                    Ok(Self::Decimal(precision, scale))
+                } else {
+                    Err("Unknown datatype".to_string())
+                }
+            },
             _ => {
-                let len_str = s
-                    .strip_prefix("Bytes[")
-                    .and_then(|s| s.strip_suffix(']'))
+                let (item_str, len_str) = s
+                    .strip_suffix(']')
+                    .and_then(|s| s.rsplit_once('['))
                     .ok_or("Unknown datatype".to_string())?;
 
                 let len = len_str.parse::<usize>()
                     .map_err(|_| "Unknown datatype".to_string())?;
 
-                Ok(Self::Bytes(len))
+                if item_str == "Bytes" {
+                    Ok(Self::Bytes(len))
+                } else {
+                    // The arrays must not be empty, otherwise there is
+                    // nothing to store
+                    let datatype = item_str.parse::<Self>()?;
+                    if datatype.is_fixed() && len > 0 && datatype.size() > 0 {
+                        Ok(Self::Array(Box::new(datatype), len))
+                    } else {
+                        Err("Unknown datatype".to_string())
+                    }
+                }
             },
         }
     }
 }
 
 
+/// Parse the decimal string `s` into the integer scaled by `10^scale`. 
+/// It returns `None` if the string is not a decimal, has more than `scale`
+/// digits after the point or overflows.
+fn parse_decimal(s: &str, scale: u8) -> Option<i128> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (int_str, frac_str) = s.split_once('.').unwrap_or((s, ""));
+
+    if int_str.is_empty() && frac_str.is_empty() || 
+            frac_str.len() > scale as usize ||
+            !int_str.chars().chain(frac_str.chars())
+                .all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let digits = format!("{}{:0<width$}", int_str, frac_str, 
+                         width = scale as usize);
+    let value = digits.trim_start_matches('0');
+    let value = if value.is_empty() { 0 } else { value.parse::<i128>().ok()? };
+
+    Some(if negative { -value } else { value })
+}
+
+
+/// Format the integer `value` scaled by `10^scale` as a decimal string.
+fn format_decimal(value: i128, scale: u8) -> String {
+    let digits = format!("{:0>width$}", value.unsigned_abs(), 
+                         width = scale as usize + 1);
+    let (int_str, frac_str) = digits.split_at(digits.len() - scale as usize);
+    let sign = if value < 0 { "-" } else { "" };
+    if frac_str.is_empty() {
+        format!("{}{}", sign, int_str)
+    } else {
+        format!("{}{}.{}", sign, int_str, frac_str)
+    }
+}
+
+
+/// Parse the UUID string `s` given in the hyphenated (canonical) or simple
+/// form into 16 bytes. It returns `None` if the string is not a UUID.
+fn parse_uuid(s: &str) -> Option<[u8; 16]> {
+    let hex = match s.len() {
+        36 if [8, 13, 18, 23].iter().all(|&i| s.as_bytes()[i] == b'-') => {
+            s.replace('-', "")
+        },
+        32 => s.to_string(),
+        _ => return None,
+    };
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut block = [0u8; 16];
+    for (i, byte) in block.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(block)
+}
+
+
+/// Format 16 bytes `block` as the canonical hyphenated UUID string.
+fn format_uuid(block: &[u8; 16]) -> String {
+    let hex = block.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], 
+            &hex[16..20], &hex[20..])
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +512,16 @@ mod tests {
         assert_eq!(Datatype::Float64.size(), 8);
         assert_eq!(Datatype::Float32.size(), 4);
         assert_eq!(Datatype::Bytes(5).size(), 5);
+        assert_eq!(Datatype::VarBytes.size(), 8);
+        assert_eq!(Datatype::VarStr.size(), 8);
+        assert_eq!(Datatype::Category.size(), 4);
+        assert_eq!(
+            Datatype::Array(Box::new(Datatype::Float32), 768).size(), 3072
+        );
+        assert_eq!(Datatype::Decimal(18, 2).size(), 8);
+        assert_eq!(Datatype::Decimal(19, 2).size(), 16);
+        assert_eq!(Datatype::Uuid.size(), 16);
+        assert_eq!(Datatype::UInt128.size(), 16);
     }
 
     #[test]
@@ -187,6 +531,7 @@ mod tests {
 
         assert_eq!("Int32".parse::<Datatype>(), Ok(Datatype::Int32));
         assert_eq!("Bytes[25]".parse::<Datatype>(), Ok(Datatype::Bytes(25)));
+        assert_eq!("VarStr".parse::<Datatype>(), Ok(Datatype::VarStr));
 
         assert_eq!("Boolean".parse::<Datatype>(), 
                    Err("Unknown datatype".to_string()));
@@ -194,9 +539,37 @@ mod tests {
                    Err("Unknown datatype".to_string()));
         assert_eq!("Bytes[-12]".parse::<Datatype>(), 
                    Err("Unknown datatype".to_string()));
+
+        let datatype = Datatype::Array(
+            Box::new(Datatype::Array(Box::new(Datatype::Bytes(4)), 2)), 3
+        );
+        assert_eq!(datatype.to_string(), "Bytes[4][2][3]");
+        assert_eq!("Bytes[4][2][3]".parse::<Datatype>(), Ok(datatype));
+        assert_eq!("VarStr[3]".parse::<Datatype>(), 
+                   Err("Unknown datatype".to_string()));
+        assert_eq!("Int32[]".parse::<Datatype>(), 
+                   Err("Unknown datatype".to_string()));
+        assert_eq!("Int32[0]".parse::<Datatype>(), 
+                   Err("Unknown datatype".to_string()));
+        assert_eq!("Bytes[0][3]".parse::<Datatype>(), 
+                   Err("Unknown datatype".to_string()));
+
+        assert_eq!(Datatype::Decimal(10, 2).to_string(), "Decimal(10,2)");
+        assert_eq!("Decimal(10, 2)".parse::<Datatype>(), 
+                   Ok(Datatype::Decimal(10, 2)));
+        assert_eq!("Decimal(38,0)[2]".parse::<Datatype>(), 
+                   Ok(Datatype::Array(Box::new(Datatype::Decimal(38, 0)), 2)));
+        assert_eq!("Decimal(39,2)".parse::<Datatype>(), 
+                   Err("Unknown datatype".to_string()));
+        assert_eq!("Decimal(2,3)".parse::<Datatype>(), 
+                   Err("Unknown datatype".to_string()));
+
+        assert_eq!("Uuid".parse::<Datatype>(), Ok(Datatype::Uuid));
+        assert_eq!(Datatype::UInt128.to_string(), "UInt128");
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_dataunit_convert() {
         assert_eq!(
             Datatype::Int64.to_bytes(&Dataunit::I(25)).unwrap(), 
@@ -241,5 +614,117 @@ mod tests {
             Datatype::Bytes(5).from_bytes(&[250, 236, 32, 85, 0]), 
             Dataunit::S("+uwgVQA=".to_string())
         );
+
+        assert_eq!(
+            Datatype::VarStr.to_bytes(
+                &Dataunit::S("qwe".to_string())
+            ).unwrap(), 
+            vec![113, 119, 101]
+        );
+        assert_eq!(
+            Datatype::VarBytes.to_bytes(&Dataunit::S("?".to_string())), 
+            None
+        );
+        assert_eq!(
+            Datatype::VarStr.from_bytes(&[113, 119, 101]), 
+            Dataunit::S("qwe".to_string())
+        );
+        assert_eq!(
+            Datatype::VarBytes.from_bytes(&[250, 236, 32]), 
+            Dataunit::S("+uwg".to_string())
+        );
+
+        let datatype = Datatype::Array(Box::new(Datatype::Int32), 2);
+        assert_eq!(
+            datatype.to_bytes(
+                &Dataunit::List(vec![Dataunit::I(25), Dataunit::I(-1)])
+            ).unwrap(), 
+            vec![25, 0, 0, 0, 255, 255, 255, 255]
+        );
+        assert_eq!(
+            datatype.to_bytes(&Dataunit::List(vec![Dataunit::I(25)])), 
+            None
+        );
+        assert_eq!(
+            datatype.from_bytes(&[25, 0, 0, 0, 255, 255, 255, 255]), 
+            Dataunit::List(vec![Dataunit::I(25), Dataunit::I(-1)])
+        );
+    }
+
+    #[test]
+    fn test_decimal_convert() {
+        let datatype = Datatype::Decimal(6, 2);
+        let s = |x: &str| Dataunit::S(x.to_string());
+
+        assert_eq!(datatype.to_bytes(&s("-12.5")).unwrap(), 
+                   (-1250i64).to_le_bytes().to_vec());
+        assert_eq!(datatype.to_bytes(&Dataunit::I(12)).unwrap(), 
+                   1200i64.to_le_bytes().to_vec());
+        assert_eq!(datatype.to_bytes(&Dataunit::F(0.125)).unwrap(), 
+                   12i64.to_le_bytes().to_vec());
+        assert_eq!(datatype.to_bytes(&s("9999.99")).unwrap(), 
+                   999999i64.to_le_bytes().to_vec());
+        assert_eq!(datatype.to_bytes(&s("10000")), None);
+        assert_eq!(datatype.to_bytes(&s("1.234")), None);
+        assert_eq!(datatype.to_bytes(&s("1.2.3")), None);
+        assert_eq!(datatype.to_bytes(&s(".")), None);
+
+        assert_eq!(datatype.from_bytes(&(-1250i64).to_le_bytes()), 
+                   s("-12.50"));
+        assert_eq!(datatype.from_bytes(&5i64.to_le_bytes()), s("0.05"));
+        assert_eq!(
+            Datatype::Decimal(20, 0).from_bytes(&(-7i128).to_le_bytes()), 
+            s("-7")
+        );
+
+        let datatype = Datatype::Decimal(38, 10);
+        let value = "1234567890123456789012345678.0123456789";
+        let block = datatype.to_bytes(&s(value)).unwrap();
+        assert_eq!(block.len(), 16);
+        assert_eq!(datatype.from_bytes(&block), s(value));
+
+        let datatype = Datatype::Decimal(10, 2);
+        assert_eq!(datatype.to_i128(&s("-9.5")), Some(-950));
+        assert_eq!(datatype.to_i128(&Dataunit::I(10)), Some(1000));
+        assert_eq!(datatype.to_i128(&s("9.505")), None);
+        assert_eq!(datatype.to_i128(&Dataunit::F(9.5)), None);
+        assert_eq!(datatype.from_i128(-950), Some(s("-9.50")));
+        assert_eq!(Datatype::Int64.from_i128(1), None);
+        assert_eq!(Datatype::UInt128.to_u128(&s(&u128::MAX.to_string())), 
+                   Some(u128::MAX));
+        assert_eq!(Datatype::UInt128.to_u128(&Dataunit::I(-1)), None);
+        assert_eq!(Datatype::Int128.to_u128(&Dataunit::I(1)), None);
+    }
+
+    #[test]
+    fn test_int128_uuid_convert() {
+        let s = |x: &str| Dataunit::S(x.to_string());
+
+        let uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let block = Datatype::Uuid.to_bytes(&s(uuid)).unwrap();
+        assert_eq!(&block[..4], &[0x67, 0xe5, 0x50, 0x44]);
+        assert_eq!(Datatype::Uuid.from_bytes(&block), s(uuid));
+        assert_eq!(
+            Datatype::Uuid.to_bytes(&s("67E5504410B1426F9247BB680E5FE0C8")), 
+            Some(block)
+        );
+        assert_eq!(Datatype::Uuid.to_bytes(&s("67e55044-10b1-426f")), None);
+        assert_eq!(
+            Datatype::Uuid.to_bytes(&s("67e55044-10b1-426f-9247-+b680e5fe0c8")),
+            None
+        );
+
+        let value = "-170141183460469231731687303715884105728";
+        let block = Datatype::Int128.to_bytes(&s(value)).unwrap();
+        assert_eq!(block, i128::MIN.to_le_bytes().to_vec());
+        assert_eq!(Datatype::Int128.from_bytes(&block), s(value));
+        assert_eq!(Datatype::Int128.to_bytes(&Dataunit::I(-1)).unwrap(), 
+                   vec![255; 16]);
+
+        let value = "340282366920938463463374607431768211455";
+        let block = Datatype::UInt128.to_bytes(&s(value)).unwrap();
+        assert_eq!(block, vec![255; 16]);
+        assert_eq!(Datatype::UInt128.from_bytes(&block), s(value));
+        assert_eq!(Datatype::UInt128.to_bytes(&Dataunit::I(-1)), None);
     }
 }
@@ -0,0 +1,138 @@
+//! `Dict` is a dictionary of distinct byte values that assigns them integer
+//! codes. It is stored as a `VarSeq`, where the code of a value is its
+//! index, so the codes are stable and new values are only appended.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use tokio::io::{Error, ErrorKind, Result as TokioResult};
+
+use crate::varseq::VarSeq;
+
+
+/// Integer code of a dictionary value.
+pub type Code = u32;
+
+
+/// `Dict` keeps all the values in the memory together with the reverse
+/// mapping, so encoding and decoding do not access the files. It is
+/// supposed to hold a relatively small number of distinct values. The code
+/// `0` is always the empty value, so zero filled codes decode to it.
+pub struct Dict {
+    varseq: VarSeq,
+    values: Vec<Vec<u8>>,
+    codes: HashMap<Vec<u8>, Code>,
+}
+
+
+impl Dict {
+    /// Create a `Dict` object located by the paths of the offsets file
+    /// `offsets_path` and the heap file `heap_path`. If no files exist, it
+    /// creates them with the empty value only.
+    pub async fn new(offsets_path: impl AsRef<Path>,
+                     heap_path: impl AsRef<Path>) -> TokioResult<Self> {
+        let mut varseq = VarSeq::new(offsets_path, heap_path).await?;
+
+        let size = varseq.size().await?;
+        let values = if size > 0 {
+            varseq.get(0, size).await?
+        } else {
+            varseq.push(&[vec![]]).await?;
+            vec![vec![]]
+        };
+
+        let codes = values.iter().enumerate()
+            .map(|(code, value)| (value.clone(), code as Code))
+            .collect();
+
+        Ok(Self { varseq, values, codes })
+    }
+
+    /// Get the number of values.
+    pub fn size(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Get all values ordered by their codes.
+    pub fn values(&self) -> &[Vec<u8>] {
+        &self.values
+    }
+
+    /// Get the codes of `values`. The missing values are added to the
+    /// dictionary.
+    pub async fn encode(&mut self, values: &[Vec<u8>]) ->
+                        TokioResult<Vec<Code>> {
+        // Assign the codes, the new values get them after the stored ones
+        let mut new_codes = HashMap::new();
+        let mut new_values = vec![];
+
+        let codes = values.iter().map(|value| {
+            if let Some(&code) = self.codes.get(value)
+                    .or_else(|| new_codes.get(value)) {
+                Ok(code)
+            } else {
+                let code = Code::try_from(
+                    self.values.len() + new_values.len()
+                ).map_err(|_| Error::from(ErrorKind::StorageFull))?;
+                new_codes.insert(value.clone(), code);
+                new_values.push(value.clone());
+                Ok(code)
+            }
+        }).collect::<TokioResult<Vec<Code>>>()?;
+
+        // Persist the new values before they become visible in the memory
+        if !new_values.is_empty() {
+            self.varseq.push(&new_values).await?;
+            self.values.extend(new_values);
+            self.codes.extend(new_codes);
+        }
+
+        Ok(codes)
+    }
+
+    /// Get the values of `codes`. It returns an error if some code is unknown.
+    pub fn decode(&self, codes: &[Code]) -> TokioResult<Vec<&[u8]>> {
+        codes.iter().map(|&code| {
+            self.values.get(code as usize)
+                .map(|value| value.as_slice())
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData,
+                                          code.to_string()))
+        }).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dict() -> TokioResult<()> {
+        let offsets_path = "./tmp/test_dict.offsets";
+        let heap_path = "./tmp/test_dict.heap";
+        let _ = tokio::fs::remove_file(offsets_path).await;
+        let _ = tokio::fs::remove_file(heap_path).await;
+
+        let mut dict = Dict::new(offsets_path, heap_path).await?;
+        assert_eq!(dict.size(), 1);
+        assert_eq!(dict.decode(&[0])?, vec![b"".as_slice()]);
+
+        let values = vec![b"ab".to_vec(), b"".to_vec(), b"cd".to_vec(),
+                          b"ab".to_vec()];
+        assert_eq!(dict.encode(&values).await?, vec![1, 0, 2, 1]);
+        assert_eq!(dict.encode(&[b"cd".to_vec()]).await?, vec![2]);
+        assert_eq!(dict.decode(&[2, 1, 0])?,
+                   vec![b"cd".as_slice(), b"ab", b""]);
+        assert!(dict.decode(&[3]).is_err());
+
+        drop(dict);
+        let mut dict = Dict::new(offsets_path, heap_path).await?;
+        assert_eq!(dict.values(), &[b"".to_vec(), b"ab".to_vec(),
+                                    b"cd".to_vec()]);
+        assert_eq!(dict.encode(&[b"ef".to_vec(), b"ab".to_vec()]).await?,
+                   vec![3, 1]);
+        assert_eq!(dict.size(), 4);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,405 @@
+//! `GroupTable` is a hash table of the groups of rows with the same keys
+//! used by `Conn::group_by`. Every group keeps the accumulators of
+//! the aggregated columns. If the number of the groups exceeds the limit,
+//! the groups are spilled to the files partitioned by the hash of the keys,
+//! so every partition is merged separately in the end. A partition that
+//! still has too many groups is partitioned again by another hash.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use tokio::fs::{File, OpenOptions, create_dir_all, read_dir, remove_file};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, 
+                BufWriter, Result as TokioResult};
+
+use crate::path_concat;
+use crate::datatype::{Datatype, Dataunit};
+use crate::predicate::compare;
+use crate::aggregate::Accumulator;
+use crate::record::{RecordTrait, RecordWriter, RecordReader};
+
+
+/// Number of the files the spilled groups are partitioned into.
+pub const SPILL_PARTS: usize = 16;
+
+/// Maximum depth of the partitioning, the deeper partitions are merged 
+/// in memory whatever the number of the groups.
+pub const SPILL_LEVELS: usize = 8;
+
+/// Default limit of the groups kept in memory.
+pub const MAX_GROUPS: usize = 1 << 20;
+
+
+/// Keys of a group and the accumulators of its aggregated columns.
+pub type Group = (Vec<Dataunit>, Vec<Accumulator>);
+
+
+// Counter of the tables to make the names of the spilled files unique
+static TABLE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+
+/// Options of the grouping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupOptions {
+    /// Maximum number of the groups kept in memory, the rest are spilled
+    /// to the disk.
+    pub max_groups: usize,
+}
+
+
+impl Default for GroupOptions {
+    fn default() -> Self {
+        Self { max_groups: MAX_GROUPS }
+    }
+}
+
+
+/// `GroupTable` collects the accumulators of the groups, the spilled
+/// groups are stored in the files `group~<pid>~<n>.<part>` in the directory
+/// of the table (`group~<pid>~<n>.<part>.<subpart>` and so on for 
+/// the partitions split again), so the tables of different processes do 
+/// not clash.
+pub struct GroupTable {
+    dir: String,
+    name: String,
+    key_datatypes: Vec<Datatype>,
+    datatypes: Vec<Datatype>,
+    max_groups: usize,
+    groups: HashMap<Vec<u8>, Group>,
+    spilled: bool,
+}
+
+
+impl GroupTable {
+    /// Create an empty table in the directory `dir`, `key_datatypes` are
+    /// the datatypes of the keys to order the groups by and `datatypes` are
+    /// the datatypes of the aggregated columns of every group. The directory
+    /// is created on the first spill.
+    pub fn new(dir: &str, key_datatypes: Vec<Datatype>, 
+               datatypes: Vec<Datatype>, max_groups: usize) -> Self {
+        let n = TABLE_COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
+        Self {
+            dir: dir.to_string(),
+            name: format!("group~{}~{}", process::id(), n),
+            key_datatypes,
+            datatypes,
+            max_groups: max_groups.max(1),
+            groups: HashMap::new(),
+            spilled: false,
+        }
+    }
+
+    /// Add the values `values` of the row to the group with the keys
+    /// `keys`.
+    pub async fn push(&mut self, keys: &[Dataunit],
+                      values: &[&Dataunit]) -> TokioResult<()> {
+        let code = Self::_encode_keys(keys);
+        if !self.groups.contains_key(&code) {
+            // Spill the groups if there is no room for a new one
+            if self.groups.len() >= self.max_groups {
+                self._spill().await?;
+            }
+            let accs = self.datatypes.iter()
+                .map(Accumulator::new)
+                .collect();
+            self.groups.insert(code.clone(), (keys.to_vec(), accs));
+        }
+        let (_, accs) = self.groups.get_mut(&code).unwrap();
+        for (acc, unit) in accs.iter_mut().zip(values.iter()) {
+            acc.push(unit);
+        }
+        Ok(())
+    }
+
+    /// Get the groups ordered by the keys. The spilled files are merged 
+    /// one by one and removed, the caller should `clear` the table if 
+    /// the merging fails.
+    pub async fn finish(&mut self) -> TokioResult<Vec<Group>> {
+        let mut groups = vec![];
+
+        if self.spilled {
+            // Merge the spilled groups part by part
+            self._spill().await?;
+            for part in 0..SPILL_PARTS {
+                let part_path = self._get_part_path(part);
+                self._merge_part(&part_path, 1, &mut groups).await?;
+            }
+        } else {
+            groups.extend(self.groups.drain().map(|(_, group)| group));
+        }
+
+        groups.sort_by(|a, b| self._cmp_keys(&a.0, &b.0));
+        Ok(groups)
+    }
+
+    /// Remove the spilled files if there are any, including the files of 
+    /// the partitions split again.
+    pub async fn clear(&self) -> TokioResult<()> {
+        if self.spilled {
+            let prefix = format!("{}.", self.name);
+            let mut entries = read_dir(&self.dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    Self::_remove_file(&entry.path()).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn _spill(&mut self) -> TokioResult<()> {
+        // Encode the groups into the parts by the hash of the keys
+        let mut writers = (0..SPILL_PARTS)
+            .map(|_| RecordWriter::new())
+            .collect::<Vec<RecordWriter>>();
+        for (code, (keys, accs)) in self.groups.drain() {
+            let part = Self::_get_part(&code, 0);
+            writers[part].put_bytes(&Self::_encode_group(&keys, &accs));
+        }
+
+        // Append the parts to the files
+        create_dir_all(&self.dir).await?;
+        self.spilled = true;
+        for (part, writer) in writers.into_iter().enumerate() {
+            let block = writer.into_bytes();
+            if !block.is_empty() {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self._get_part_path(part)).await?;
+                file.write_all(&block).await?;
+            }
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _merge_part(&self, part_path: &str, level: usize, 
+                         groups: &mut Vec<Group>) -> TokioResult<()> {
+        // Open the part if it exists
+        let file = match File::open(part_path).await {
+            Ok(file) => file,
+            Err(err) if err.kind() == tokio::io::ErrorKind::NotFound => {
+                return Ok(());
+            },
+            Err(err) => return Err(err),
+        };
+        let mut reader = BufReader::new(file);
+
+        // Merge the groups while there is room for them, otherwise split 
+        // the part into the subparts by the hash of the next level
+        let mut merged: HashMap<Vec<u8>, Group> = HashMap::new();
+        let mut subparts: Vec<BufWriter<File>> = vec![];
+        while let Some((code, keys, accs)) = 
+                self._read_group(&mut reader).await? {
+            if !subparts.is_empty() {
+                Self::_write_group(&mut subparts, level, &code, 
+                                   &keys, &accs).await?;
+            } else if let Some((_, merged_accs)) = merged.get_mut(&code) {
+                for (acc, other) in merged_accs.iter_mut().zip(accs) {
+                    acc.merge(&other);
+                }
+            } else if merged.len() < self.max_groups || 
+                      level >= SPILL_LEVELS {
+                merged.insert(code, (keys, accs));
+            } else {
+                for subpart in 0..SPILL_PARTS {
+                    let subpart_path = format!("{}.{}", part_path, subpart);
+                    subparts.push(BufWriter::new(
+                        File::create(subpart_path).await?
+                    ));
+                }
+                for (code, (keys, accs)) in merged.drain() {
+                    Self::_write_group(&mut subparts, level, &code, 
+                                       &keys, &accs).await?;
+                }
+                Self::_write_group(&mut subparts, level, &code, 
+                                   &keys, &accs).await?;
+            }
+        }
+        drop(reader);
+        remove_file(part_path).await?;
+
+        // Merge the subparts if the part has been split
+        if subparts.is_empty() {
+            groups.extend(merged.into_values());
+        } else {
+            for writer in subparts.iter_mut() {
+                writer.flush().await?;
+            }
+            drop(subparts);
+            for subpart in 0..SPILL_PARTS {
+                let subpart_path = format!("{}.{}", part_path, subpart);
+                Box::pin(self._merge_part(&subpart_path, level + 1, 
+                                          groups)).await?;
+            }
+        }
+
+        // Ok
+        Ok(())
+    }
+
+    async fn _read_group(&self, reader: &mut BufReader<File>) ->
+                         TokioResult<Option<(Vec<u8>, Vec<Dataunit>, 
+                                             Vec<Accumulator>)>> {
+        // Read the block of the group, the end of the file is the end of 
+        // the groups
+        if reader.fill_buf().await?.is_empty() {
+            return Ok(None);
+        }
+        let size = reader.read_u64_le().await? as usize;
+        let mut block = vec![0u8; size];
+        reader.read_exact(&mut block).await?;
+
+        // Decode the keys and the accumulators
+        let mut reader = RecordReader::new(&block);
+        let count = reader.get_u64()? as usize;
+        let keys = (0..count)
+            .map(|_| reader.get_unit())
+            .collect::<TokioResult<Vec<Dataunit>>>()?;
+        let accs = self.datatypes.iter()
+            .map(|_| Accumulator::decode(&mut reader, 0))
+            .collect::<TokioResult<Vec<Accumulator>>>()?;
+        Ok(Some((Self::_encode_keys(&keys), keys, accs)))
+    }
+
+    async fn _write_group(subparts: &mut [BufWriter<File>], level: usize, 
+                          code: &[u8], keys: &[Dataunit], 
+                          accs: &[Accumulator]) -> TokioResult<()> {
+        let mut writer = RecordWriter::new();
+        writer.put_bytes(&Self::_encode_group(keys, accs));
+        subparts[Self::_get_part(code, level)]
+            .write_all(&writer.into_bytes()).await
+    }
+
+    fn _encode_group(keys: &[Dataunit], accs: &[Accumulator]) -> Vec<u8> {
+        let mut writer = RecordWriter::new();
+        writer.put_u64(keys.len() as u64);
+        for unit in keys.iter() {
+            writer.put_unit(unit);
+        }
+        for acc in accs.iter() {
+            acc.encode(&mut writer);
+        }
+        writer.into_bytes()
+    }
+
+    fn _encode_keys(keys: &[Dataunit]) -> Vec<u8> {
+        let mut writer = RecordWriter::new();
+        for unit in keys.iter() {
+            writer.put_unit(unit);
+        }
+        writer.into_bytes()
+    }
+
+    fn _cmp_keys(&self, a: &[Dataunit], b: &[Dataunit]) -> Ordering {
+        // The nulls go first
+        for (i, (x, y)) in a.iter().zip(b.iter()).enumerate() {
+            let ord = match (x, y) {
+                (Dataunit::Null, Dataunit::Null) => Ordering::Equal,
+                (Dataunit::Null, _) => Ordering::Less,
+                (_, Dataunit::Null) => Ordering::Greater,
+                _ => compare(x, y, self.key_datatypes.get(i))
+                    .unwrap_or(Ordering::Equal),
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    fn _get_part(code: &[u8], level: usize) -> usize {
+        // The level seeds the hash, so a part is split evenly at the next
+        // level
+        let mut hasher = DefaultHasher::new();
+        level.hash(&mut hasher);
+        code.hash(&mut hasher);
+        hasher.finish() as usize % SPILL_PARTS
+    }
+
+    fn _get_part_path(&self, part: usize) -> String {
+        path_concat!(&self.dir, format!("{}.{}", self.name, part))
+    }
+
+    async fn _remove_file(path: &Path) -> TokioResult<()> {
+        match remove_file(path).await {
+            Err(err) if err.kind() != tokio::io::ErrorKind::NotFound => {
+                Err(err)
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::AggFn;
+
+    #[tokio::test]
+    async fn test_group_table() -> TokioResult<()> {
+        let dir = "./tmp/test_group_table";
+        let _ = tokio::fs::remove_dir_all(dir).await;
+        let mut table = GroupTable::new(dir, 
+                                        vec![Datatype::Int64, 
+                                             Datatype::VarStr],
+                                        vec![Datatype::Int64], 3);
+        for i in 0..100 {
+            let keys = [Dataunit::I(i % 7), Dataunit::S("a".to_string())];
+            table.push(&keys, &[&Dataunit::I(i)]).await?;
+        }
+        table.push(&[Dataunit::Null, Dataunit::Null],
+                   &[&Dataunit::I(1000)]).await?;
+
+        let part_path = table._get_part_path(0);
+        let groups = table.finish().await?;
+        assert_eq!(groups.len(), 8);
+        assert_eq!(groups[0].0, [Dataunit::Null, Dataunit::Null]);
+        assert_eq!(groups[0].1[0].get(AggFn::Sum)?, Dataunit::I(1000));
+        assert_eq!(groups[1].0[0], Dataunit::I(0));
+        assert_eq!(groups[1].1[0].get(AggFn::Count)?, Dataunit::I(15));
+        assert_eq!(groups[1].1[0].get(AggFn::Sum)?, Dataunit::I(735));
+        assert_eq!(groups[7].0[0], Dataunit::I(6));
+        assert_eq!(groups[7].1[0].get(AggFn::Max)?, Dataunit::I(97));
+        assert!(!tokio::fs::try_exists(part_path).await?);
+
+        // The parts with too many groups are split again
+        let mut table = GroupTable::new(dir, vec![Datatype::Int64], 
+                                        vec![Datatype::Int64], 2);
+        for i in 0..1000 {
+            table.push(&[Dataunit::I(i % 500)], &[&Dataunit::I(i)]).await?;
+        }
+        let groups = table.finish().await?;
+        assert_eq!(groups.len(), 500);
+        for (i, (keys, accs)) in groups.iter().enumerate() {
+            assert_eq!(keys[0], Dataunit::I(i as i64));
+            assert_eq!(accs[0].get(AggFn::Sum)?, 
+                       Dataunit::I(2 * i as i64 + 500));
+        }
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        assert!(entries.next_entry().await?.is_none());
+
+        // The files are removed if the merging fails
+        let mut table = GroupTable::new(dir, vec![Datatype::Int64], 
+                                        vec![Datatype::Int64], 2);
+        for i in 0..100 {
+            table.push(&[Dataunit::I(i)], &[&Dataunit::I(i)]).await?;
+        }
+        table._spill().await?;
+        tokio::fs::write(table._get_part_path(0), 100u64.to_le_bytes())
+            .await?;
+        assert!(table.finish().await.is_err());
+        table.clear().await?;
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        assert!(entries.next_entry().await?.is_none());
+
+        Ok(())
+    }
+}
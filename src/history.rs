@@ -0,0 +1,149 @@
+//! `History` is an append-only log of the operations that change
+//! the structure of the database (adding, renaming, removing feeds and
+//! columns, casting columns, truncating, trimming and compacting feeds).
+//! The records are never modified, so the log tells when and how
+//! the structure was changed.
+
+use std::path::Path;
+
+use tokio::io::{Error, ErrorKind, Result as TokioResult};
+
+use crate::varseq::VarSeq;
+use crate::record::{RecordTrait, RecordWriter, RecordReader,
+                    encode_record, decode_record};
+
+
+/// Operation recorded in the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryOp {
+    /// The feed was added.
+    FeedAdd,
+
+    /// The feed was renamed, the details are the new name.
+    FeedRename,
+
+    /// The feed was removed.
+    FeedRemove,
+
+    /// The column was added, the details are its datatype.
+    ColAdd,
+
+    /// The column was renamed, the details are the new name.
+    ColRename,
+
+    /// The column was removed.
+    ColRemove,
+
+    /// The datatype of the column was changed, the details are the old and
+    /// the new datatypes.
+    ColCast,
+
+    /// The feed was truncated, the details are the old and the new sizes.
+    SizeTruncate,
+
+    /// The deleted rows were removed from the feed, the details are the old
+    /// and the new sizes.
+    FeedCompact,
+
+    /// The oldest rows were removed from the feed, the details are their
+    /// number.
+    FeedTrim,
+}
+
+
+impl HistoryOp {
+    const ALL: [Self; 10] = [
+        Self::FeedAdd, Self::FeedRename, Self::FeedRemove, Self::ColAdd,
+        Self::ColRename, Self::ColRemove, Self::ColCast, Self::SizeTruncate,
+        Self::FeedCompact, Self::FeedTrim,
+    ];
+
+    fn code(&self) -> u8 {
+        Self::ALL.iter().position(|op| op == self).unwrap() as u8
+    }
+
+    fn from_code(code: u8) -> TokioResult<Self> {
+        Self::ALL.get(code as usize).copied().ok_or_else(
+            || Error::new(ErrorKind::InvalidData, format!("operation {}", code))
+        )
+    }
+}
+
+
+/// Record of the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryItem {
+    /// Time of the operation as the number of seconds since the Unix epoch.
+    pub time: u64,
+
+    /// Operation.
+    pub op: HistoryOp,
+
+    /// Name of the feed.
+    pub feed: String,
+
+    /// Name of the column, it is empty for the operations over feeds.
+    pub col: String,
+
+    /// Details of the operation depending on `op`.
+    pub details: String,
+}
+
+
+impl RecordTrait for HistoryItem {
+    fn encode(&self, writer: &mut RecordWriter) {
+        writer.put_u64(self.time);
+        writer.put_u8(self.op.code());
+        writer.put_str(&self.feed);
+        writer.put_str(&self.col);
+        writer.put_str(&self.details);
+    }
+
+    fn decode(reader: &mut RecordReader, _version: u8) -> TokioResult<Self> {
+        Ok(Self {
+            time: reader.get_u64()?,
+            op: HistoryOp::from_code(reader.get_u8()?)?,
+            feed: reader.get_str()?,
+            col: reader.get_str()?,
+            details: reader.get_str()?,
+        })
+    }
+}
+
+
+/// `History` stores the records in a `VarSeq` located by the path `path`
+/// and the heap `<path>.heap`.
+pub struct History {
+    varseq: VarSeq,
+}
+
+
+impl History {
+    /// Open the history located at `path`. If no files exist, they will be
+    /// created.
+    pub async fn new(path: impl AsRef<Path>) -> TokioResult<Self> {
+        let heap_path = format!("{}.heap", path.as_ref().display());
+        let varseq = VarSeq::new(path, heap_path).await?;
+        Ok(Self { varseq })
+    }
+
+    /// Get the number of records.
+    pub async fn size(&self) -> TokioResult<usize> {
+        self.varseq.size().await
+    }
+
+    /// Append the records to the end.
+    pub async fn push(&mut self, items: &[HistoryItem]) -> TokioResult<()> {
+        let blocks = items.iter().map(encode_record).collect::<Vec<Vec<u8>>>();
+        self.varseq.push(&blocks).await?;
+        Ok(())
+    }
+
+    /// Get `count` records located from `ix`.
+    pub async fn get(&mut self, ix: usize, count: usize) ->
+                     TokioResult<Vec<HistoryItem>> {
+        self.varseq.get(ix, count).await?.iter()
+            .map(|block| decode_record(block))
+            .collect()
+    }
+}
@@ -2,32 +2,124 @@
 //! responsible for the options of feeds (like tables or collections) and
 //! cols (like columns of fields).
 
-use crate::utils::{str_to_bytes, bytes_to_str, validate_allowed_name};
-use crate::datatype::Datatype;
-use crate::list::ListKeyTrait;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::io::{Error, ErrorKind};
 
+use crate::validate;
+use crate::utils::{validate_allowed_name, timestamp, MAX_NAME_PART_SIZE};
+use crate::datatype::{Datatype, Dataunit};
+use crate::list::{ListKeyTrait, ListLegacyTrait};
+use crate::record::{RecordTrait, RecordWriter, RecordReader};
 
-/// Maximum size for the stored names
-const MAX_NAME_SIZE: usize = 256;
 
-/// Type for the names as a static byte array.
-type NameType = [u8; MAX_NAME_SIZE];
+/// Size of the names in the legacy layout
+const LEGACY_NAME_SIZE: usize = 256;
+
+/// Files of a feed that are stored next to its column files, the columns
+/// cannot take their names.
+const RESERVED_COL_NAMES: [&str; 3] = [
+    "col.list", "partitions.list", "deleted.bitmap",
+];
 
 
 /// Feed structure.
 #[derive(Clone, Debug)]
 pub struct FeedItem {
     /// Name of the feed.
-    pub name: NameType,
+    pub name: String,
 
-    /// Size of the feed.
+    /// Size of the feed as the number of the stored rows including 
+    /// the trimmed ones.
     pub size: usize,
+
+    /// Metadata of the feed.
+    pub meta: Meta,
+
+    /// Number of the first rows removed by `Conn::feed_trim_front`, their
+    /// data are not stored anymore.
+    pub base: usize,
+
+    /// Number of the trimmed rows excluded from the row indices, so the row
+    /// stored at `ix` has the index `ix - shift`.
+    pub shift: usize,
+
+    /// Maximum number of the rows of a capped feed, it is zero if the feed
+    /// is not capped. A capped feed overwrites its oldest rows.
+    pub cap: usize,
+
+    /// Stored index of the oldest row of a capped feed.
+    pub head: usize,
+
+    /// Number of the rows per segment of a segmented feed, it is zero if
+    /// the columns are stored in one file each.
+    pub segment: usize,
+
+    /// Timestamp column of a partitioned feed, its rows are stored in
+    /// the partitions by the time periods.
+    pub time_col: String,
+
+    /// Length of the time period of a partition in the units of the time
+    /// column, it is zero if the feed is not partitioned.
+    pub period: u64,
+
+    /// Generation of the column list and the tombstones. A compacted feed 
+    /// gets the next generation, so it switches to the compacted columns 
+    /// only when its record is updated in the feed list.
+    pub generation: u64,
 }
 
 
 impl ListKeyTrait<String> for FeedItem {
     fn key(&self) -> String {
-        bytes_to_str(&self.name).to_string()
+        self.name.clone()
+    }
+}
+
+
+impl RecordTrait for FeedItem {
+    fn encode(&self, writer: &mut RecordWriter) {
+        writer.put_str(&self.name);
+        writer.put_u64(self.size as u64);
+        self.meta.encode(writer);
+        writer.put_u64(self.base as u64);
+        writer.put_u64(self.shift as u64);
+        writer.put_u64(self.cap as u64);
+        writer.put_u64(self.head as u64);
+        writer.put_u64(self.segment as u64);
+        writer.put_str(&self.time_col);
+        writer.put_u64(self.period);
+        writer.put_u64(self.generation);
+    }
+
+    fn decode(reader: &mut RecordReader, version: u8) -> 
+              std::io::Result<Self> {
+        Ok(Self {
+            name: reader.get_str()?,
+            size: reader.get_u64()? as usize,
+            meta: Meta::decode(reader, version)?,
+            base: reader.get_u64()? as usize,
+            shift: reader.get_u64()? as usize,
+            cap: reader.get_u64()? as usize,
+            head: reader.get_u64()? as usize,
+            segment: reader.get_u64()? as usize,
+            time_col: reader.get_str()?,
+            period: reader.get_u64()?,
+            generation: if version >= 2 { reader.get_u64()? } else { 0 },
+        })
+    }
+}
+
+
+impl ListLegacyTrait for FeedItem {
+    const LEGACY_SIZE: usize = LEGACY_NAME_SIZE + 8;
+
+    fn from_legacy(block: &[u8]) -> std::io::Result<Self> {
+        // The name goes first and the size after it
+        let (name, size) = block.split_at(LEGACY_NAME_SIZE);
+        let mut feed_item = Self::new(_legacy_name(name)?)?;
+        feed_item.size = _legacy_u64(size) as usize;
+        Ok(feed_item)
     }
 }
 
@@ -37,39 +129,260 @@ impl FeedItem {
     pub fn new(name: &str) -> std::io::Result<Self> {
         validate_allowed_name(name)?;
         Ok(Self {
-            name: str_to_bytes::<MAX_NAME_SIZE>(name),
+            name: name.to_string(),
             size: 0,
+            meta: Meta::new(),
+            base: 0,
+            shift: 0,
+            cap: 0,
+            head: 0,
+            segment: 0,
+            time_col: String::new(),
+            period: 0,
+            generation: 0,
         })
     }
 
     /// Get name as string.
     pub fn get_name(&self) -> String {
-        bytes_to_str(&self.name).to_string()
+        self.name.clone()
+    }
+
+    /// Get the index of the first available row.
+    pub fn first(&self) -> usize {
+        self.base - self.shift
+    }
+
+    /// Get the index next to the last row, it is the size of the feed
+    /// visible through the row indices.
+    pub fn end(&self) -> usize {
+        self.size - self.shift
+    }
+
+    /// Check whether the feed is capped.
+    pub fn is_capped(&self) -> bool {
+        self.cap > 0
+    }
+
+    /// Check whether the feed is segmented.
+    pub fn is_segmented(&self) -> bool {
+        self.segment > 0
+    }
+
+    /// Check whether the feed is partitioned.
+    pub fn is_partitioned(&self) -> bool {
+        self.period > 0
     }
 
     /// Rename the feed.
     pub fn rename(&mut self, name: &str) -> std::io::Result<()> {
         validate_allowed_name(name)?;
-        self.name = str_to_bytes::<MAX_NAME_SIZE>(name);
+        self.name = name.to_string();
         Ok(())
     }
 }
 
 
+/// Partition of a partitioned feed. It is stored as the feed
+/// `<feed>.<name>` that keeps the rows with the time in the period `key`,
+/// so the time `t` belongs to the partition `t.div_euclid(period)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartitionItem {
+    /// Number of the time period.
+    pub key: i64,
+
+    /// Index of the first row in the partitioned feed, the row indices of
+    /// the feed go through the partitions in the order of their periods.
+    pub start: usize,
+
+    /// Number of the rows.
+    pub size: usize,
+
+    /// Minimum time of the rows.
+    pub time_min: i64,
+
+    /// Maximum time of the rows.
+    pub time_max: i64,
+}
+
+
+impl ListKeyTrait<i64> for PartitionItem {
+    fn key(&self) -> i64 {
+        self.key
+    }
+}
+
+
+impl RecordTrait for PartitionItem {
+    fn encode(&self, writer: &mut RecordWriter) {
+        writer.put_u64(self.key as u64);
+        writer.put_u64(self.start as u64);
+        writer.put_u64(self.size as u64);
+        writer.put_u64(self.time_min as u64);
+        writer.put_u64(self.time_max as u64);
+    }
+
+    fn decode(reader: &mut RecordReader, _version: u8) ->
+              std::io::Result<Self> {
+        Ok(Self {
+            key: reader.get_u64()? as i64,
+            start: reader.get_u64()? as usize,
+            size: reader.get_u64()? as usize,
+            time_min: reader.get_u64()? as i64,
+            time_max: reader.get_u64()? as i64,
+        })
+    }
+}
+
+
+impl ListLegacyTrait for PartitionItem {}
+
+
+impl PartitionItem {
+    /// Create an empty partition object for the period `key`.
+    pub fn new(key: i64) -> Self {
+        Self {
+            key, start: 0, size: 0, time_min: i64::MAX, time_max: i64::MIN,
+        }
+    }
+
+    /// Get the index next to the last row in the partitioned feed.
+    pub fn end(&self) -> usize {
+        self.start + self.size
+    }
+
+    /// Get the name of the partition feed in the namespace of 
+    /// the partitioned feed, the negative keys are prefixed with `m`.
+    pub fn get_name(&self) -> String {
+        if self.key < 0 {
+            format!("m{}", self.key.unsigned_abs())
+        } else {
+            format!("p{}", self.key)
+        }
+    }
+
+    /// Check whether the partition can contain the time in `range`.
+    pub fn overlaps(&self, range: &Range<i64>) -> bool {
+        self.size > 0 && self.time_min < range.end && 
+            self.time_max >= range.start
+    }
+}
+
+
+/// Defines how the row indices change after the oldest rows are removed by
+/// `Conn::feed_trim_front`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimMode {
+    /// The remaining rows keep their indices, the indices of the removed
+    /// rows become unavailable.
+    Stable,
+
+    /// The rows are renumbered, so the first remaining row gets the index 
+    /// zero.
+    Renumber,
+}
+
+
 /// Column structure.
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct ColItem {
-    /// Name of the column,
-    pub name: NameType,
+    /// Name of the column. It is also the name of the column files, so it
+    /// cannot be longer than `MAX_NAME_PART_SIZE` or be the name of another
+    /// feed file.
+    pub name: String,
 
     /// Datatype of the column.
     pub datatype: Datatype,
+
+    /// Whether the column can contain null values.
+    pub nullable: bool,
+
+    /// Default value of the column if it is set.
+    pub default: Option<Dataunit>,
+
+    /// Index of the first stored row. The rows before it are not stored
+    /// and have the default value (null or zero if it is not set).
+    pub start: usize,
+
+    /// Metadata of the column.
+    pub meta: Meta,
+
+    /// Generation of the column files. The files of a rewritten column 
+    /// get the next generation, so the column switches to them only when
+    /// its record is updated in the column list.
+    pub generation: u64,
 }
 
 
 impl ListKeyTrait<String> for ColItem {
     fn key(&self) -> String {
-        bytes_to_str(&self.name).to_string()
+        self.name.clone()
+    }
+}
+
+
+impl RecordTrait for ColItem {
+    fn encode(&self, writer: &mut RecordWriter) {
+        writer.put_str(&self.name);
+        writer.put_str(&self.datatype.to_string());
+        writer.put_bool(self.nullable);
+        writer.put_bool(self.default.is_some());
+        writer.put_bytes(&self.get_default_bytes().unwrap_or_default());
+        writer.put_u64(self.start as u64);
+        self.meta.encode(writer);
+        writer.put_u64(self.generation);
+    }
+
+    fn decode(reader: &mut RecordReader, version: u8) -> 
+              std::io::Result<Self> {
+        // The datatype is stored as string
+        let name = reader.get_str()?;
+        let datatype = reader.get_str()?.parse::<Datatype>()
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        let nullable = reader.get_bool()?;
+
+        // The default value is stored as bytes according to the datatype,
+        // they are copied because the numbers are read from aligned bytes
+        let has_default = reader.get_bool()?;
+        let block = reader.get_bytes()?.to_vec();
+        let default = if has_default {
+            validate!(!datatype.is_fixed() || block.len() == datatype.size(),
+                      InvalidData, format!("default of {}", name))?;
+            Some(datatype.from_bytes(&block))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name,
+            datatype,
+            nullable,
+            default,
+            start: reader.get_u64()? as usize,
+            meta: Meta::decode(reader, version)?,
+            generation: if version >= 2 { reader.get_u64()? } else { 0 },
+        })
+    }
+}
+
+
+impl ListLegacyTrait for ColItem {
+    const LEGACY_SIZE: usize = 16 + LEGACY_NAME_SIZE;
+
+    fn from_legacy(block: &[u8]) -> std::io::Result<Self> {
+        // The compiler placed the datatype before the name: the tag byte,
+        // the padding and the length of bytes in the next 8 bytes
+        let datatype = match block[0] {
+            0 => "Int64".to_string(),
+            1 => "Float64".to_string(),
+            2 => "Int32".to_string(),
+            3 => "Float32".to_string(),
+            4 => format!("Bytes[{}]", _legacy_u64(&block[8..16])),
+            tag => return Err(Error::new(
+                ErrorKind::InvalidData, format!("legacy datatype {}", tag)
+            )),
+        };
+        Self::new(_legacy_name(&block[16..])?, &datatype)
     }
 }
 
@@ -77,16 +390,47 @@ impl ListKeyTrait<String> for ColItem {
 impl ColItem {
     /// Create a column object by the name as string and the datatype.
     pub fn new(name: &str, datatype: &str) -> std::io::Result<Self> {
-        validate_allowed_name(name)?;
+        Self::with_options(name, datatype, &ColOptions::default())
+    }
+
+    /// Create a column object by the name as string, the datatype and
+    /// the column options.
+    pub fn with_options(name: &str, datatype: &str, 
+                        options: &ColOptions) -> std::io::Result<Self> {
+        Self::_validate_name(name)?;
+        let datatype = datatype.parse::<Datatype>()
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+
+        // The default value must be convertible to the datatype
+        if let Some(unit) = &options.default {
+            validate!(datatype.to_bytes(unit).is_some(), InvalidInput,
+                      format!("{:?} does not match {}", unit, datatype))?;
+        }
+
         Ok(Self {
-            name: str_to_bytes::<MAX_NAME_SIZE>(name),
-            datatype: datatype.parse().unwrap(),
+            name: name.to_string(),
+            datatype,
+            nullable: options.nullable,
+            default: options.default.clone(),
+            start: 0,
+            meta: Meta::new(),
+            generation: 0,
         })
     }
 
     /// Get name as string.
     pub fn get_name(&self) -> String {
-        bytes_to_str(&self.name).to_string()
+        self.name.clone()
+    }
+
+    /// Get the name of the column files, the files of the first generation
+    /// are named after the column.
+    pub fn get_file_name(&self) -> String {
+        if self.generation == 0 {
+            self.name.clone()
+        } else {
+            format!("{}~{}", self.name, self.generation)
+        }
     }
 
     /// Get datatype as string.
@@ -94,10 +438,171 @@ impl ColItem {
         self.datatype.to_string()
     }
 
+    /// Get datatype.
+    pub fn parse_datatype(&self) -> Datatype {
+        self.datatype.clone()
+    }
+
+    /// Get the default value as bytes if it is set.
+    pub fn get_default_bytes(&self) -> Option<Vec<u8>> {
+        self.default.as_ref()
+            .and_then(|unit| self.datatype.to_bytes(unit))
+    }
+
+    /// Get the default value if it is set.
+    pub fn get_default(&self) -> Option<Dataunit> {
+        self.default.clone()
+    }
+
+    /// Get the column options.
+    pub fn get_options(&self) -> ColOptions {
+        ColOptions {
+            nullable: self.nullable,
+            default: self.get_default(),
+        }
+    }
+
     /// Rename the column.
     pub fn rename(&mut self, name: &str) -> std::io::Result<()> {
-        validate_allowed_name(name)?;
-        self.name = str_to_bytes::<MAX_NAME_SIZE>(name);
+        Self::_validate_name(name)?;
+        self.name = name.to_string();
         Ok(())
     }
+
+    fn _validate_name(name: &str) -> std::io::Result<()> {
+        validate_allowed_name(name)?;
+        validate!(name.len() <= MAX_NAME_PART_SIZE, InvalidInput, format!(
+            "column name is longer than {} bytes", MAX_NAME_PART_SIZE
+        ))?;
+        validate!(!RESERVED_COL_NAMES.contains(&name), InvalidInput, format!(
+            "column name {} is reserved", name
+        ))
+    }
+}
+
+
+/// Template structure that keeps a stored feed schema as the column items.
+#[derive(Clone, Debug)]
+pub struct TemplateItem {
+    /// Name of the template.
+    pub name: String,
+
+    /// Columns of the template.
+    pub cols: Vec<ColItem>,
+}
+
+
+impl ListKeyTrait<String> for TemplateItem {
+    fn key(&self) -> String {
+        self.name.clone()
+    }
+}
+
+
+impl RecordTrait for TemplateItem {
+    fn encode(&self, writer: &mut RecordWriter) {
+        writer.put_str(&self.name);
+        writer.put_u64(self.cols.len() as u64);
+        for col_item in self.cols.iter() {
+            col_item.encode(writer);
+        }
+    }
+
+    fn decode(reader: &mut RecordReader, version: u8) -> 
+              std::io::Result<Self> {
+        let name = reader.get_str()?;
+        let size = reader.get_u64()? as usize;
+        let cols = (0..size)
+            .map(|_| ColItem::decode(reader, version))
+            .collect::<std::io::Result<Vec<ColItem>>>()?;
+        Ok(Self { name, cols })
+    }
+}
+
+
+impl ListLegacyTrait for TemplateItem {}
+
+
+impl TemplateItem {
+    /// Create a template object by name and the column items.
+    pub fn new(name: &str, cols: Vec<ColItem>) -> std::io::Result<Self> {
+        validate_allowed_name(name)?;
+        Ok(Self { name: name.to_string(), cols })
+    }
+}
+
+
+/// Options of a column that are given on its creation.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct ColOptions {
+    /// Whether the column can contain null values. Nullable columns keep
+    /// a validity bitmap, so the new and missing values become null instead
+    /// of zeros.
+    pub nullable: bool,
+
+    /// Default value for the new and missing values instead of zeros or 
+    /// nulls.
+    pub default: Option<Dataunit>,
+}
+
+
+/// Metadata of a feed or a column that is managed by the users.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Meta {
+    /// Creation time as the number of seconds since the Unix epoch.
+    pub created: u64,
+
+    /// Time of the last change of the structure (the name, the datatype,
+    /// the columns of a feed) or the metadata as the number of seconds since
+    /// the Unix epoch. Changes of the data do not affect it.
+    pub modified: u64,
+
+    /// Free-form description.
+    pub description: String,
+
+    /// User key-value tags (like the unit or the owner).
+    pub tags: HashMap<String, String>,
+}
+
+
+impl Meta {
+    /// Create the metadata of a new entity created now.
+    pub fn new() -> Self {
+        let now = timestamp();
+        Self { created: now, modified: now, ..Default::default() }
+    }
+
+    /// Set the modification time to now.
+    pub fn touch(&mut self) {
+        self.modified = timestamp();
+    }
+
+    fn encode(&self, writer: &mut RecordWriter) {
+        writer.put_u64(self.created);
+        writer.put_u64(self.modified);
+        writer.put_str(&self.description);
+        writer.put_map(&self.tags);
+    }
+
+    fn decode(reader: &mut RecordReader, _version: u8) -> 
+              std::io::Result<Self> {
+        Ok(Self {
+            created: reader.get_u64()?,
+            modified: reader.get_u64()?,
+            description: reader.get_str()?,
+            tags: reader.get_map()?,
+        })
+    }
+}
+
+
+fn _legacy_name(name: &[u8]) -> std::io::Result<&str> {
+    std::str::from_utf8(name)
+        .map(|name| name.trim_end_matches('\0'))
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+
+fn _legacy_u64(block: &[u8]) -> u64 {
+    u64::from_le_bytes(block.try_into().unwrap())
 }
@@ -1,9 +1,12 @@
 //! `lbasedb` is a powerful low level DBMS that is focused on dataset structure.
 //! The algorithms are optimized for the compact data storage and for high
 //! performance on get and append operations. Particularly, due to this, 
-//! deleting or indexing are not supported. The allowed data types are also
-//! limited (integers, floats and bytes) for making easy integration with
-//! C-like or similar common interfaces (like Python, CUDA, JSON and so on).
+//! indexing is not supported and the deleted rows are only marked until
+//! the feed is compacted. The allowed data types are also limited
+//! (integers including 128-bit ones, floats, decimals, UUIDs, fixed size
+//! bytes and arrays, variable length bytes and strings, and categories)
+//! for making easy integration with C-like or similar common interfaces
+//! (like Python, CUDA, JSON and so on).
 //! The database has asynchronous access to the entities powered by `tokio`.
 //! It is supposed to be used for the data that have billions and more records
 //! and thousands columns of simple data types that must be appended without
@@ -16,9 +19,10 @@
 //!
 //! let conn = Conn::new("./tmp/db").await?;
 //! 
-//! if !conn.feed_exists("xyz").await {
-//!     conn.feed_add("xyz").await?;
-//! }
+//! let schema = FeedSchema::new()
+//!     .col("x", "Int64")
+//!     .col("y", "Float64");
+//! conn.feed_ensure("xyz", &schema).await?;
 //! 
 //! println!(
 //!     "Feed list: {:?}", 
@@ -27,14 +31,6 @@
 //!          .collect::<Vec<String>>()
 //! );
 //! 
-//! if !conn.col_exists("xyz", "x").await? {
-//!     conn.col_add("xyz", "x", "Int64").await?;
-//! }
-//! 
-//! if !conn.col_exists("xyz", "y").await? {
-//!     conn.col_add("xyz", "y", "Float64").await?;
-//! }
-//! 
 //! println!(
 //!     "Col list: {:?}", 
 //!     conn.col_list("xyz")?.iter()
@@ -62,11 +58,23 @@
 
 pub mod utils;
 pub mod seq;
+pub mod varseq;
+pub mod dict;
+pub mod bitmap;
 pub mod col;
+pub mod record;
 pub mod list;
 pub mod items;
 pub mod datatype;
 pub mod dataset;
+pub mod remap;
+pub mod predicate;
+pub mod aggregate;
+pub mod group;
+pub mod cast;
+pub mod schema;
+pub mod history;
+pub mod series;
 pub mod conn;
 pub mod prelude;
 
@@ -1,16 +1,21 @@
 //! `List` is a way to store structures in a file mainly for the management 
 //! purposes. Unlike `Seq` or `Col` philosophy it supports fetching by unique 
-//! key and removing, although it is a wrapper over `Col`. The performance
-//! of the operations is relatively low.
+//! key and removing, although it is a wrapper over `VarSeq` keeping
+//! the records encoded by `RecordTrait`. The performance of the operations
+//! is relatively low. The lists written by older versions as the raw
+//! structs in a `Col` file are converted on open.
 
 use std::path::Path;
 use std::hash::Hash;
 use std::collections::HashMap;
+use std::marker::PhantomData;
 
+use tokio::fs::{read, rename, remove_file, try_exists};
 use tokio::io::Result as TokioResult;
-use tokio::io::ErrorKind;
+use tokio::io::{Error, ErrorKind};
 
-use crate::col::Col;
+use crate::varseq::VarSeq;
+use crate::record::{RecordTrait, encode_record, decode_record};
 
 
 /// Key trait so a struct can be stored in `List`.
@@ -20,23 +25,47 @@ pub trait ListKeyTrait<K> {
 }
 
 
-/// `List` implements methods to work with small lists stored as a `Col` object.
-/// `List` keeps index map in the memory to reduce the access to the data
-/// in the file, so if there are too many records, `List` object may be 
-/// consuming. The main purpose of `List` the is inner data management between
-/// files, data types, structeres and so on in the DBSM.
+/// Legacy trait so a struct stored by older versions as its raw bytes in
+/// a `Col` file can be converted to the current format.
+pub trait ListLegacyTrait: Sized {
+    /// Size of the struct in the legacy layout, it is zero if there is
+    /// no legacy layout.
+    const LEGACY_SIZE: usize = 0;
+
+    /// Convert the struct from its legacy bytes `block`.
+    fn from_legacy(_block: &[u8]) -> TokioResult<Self> {
+        Err(ErrorKind::Unsupported.into())
+    }
+}
+
+
+/// `List` implements methods to work with small lists stored as a `VarSeq` 
+/// object, the records are located by the path `path` and the heap 
+/// `<path>.heap`. `List` keeps index map in the memory to reduce the access
+/// to the data in the file, so if there are too many records, `List` object
+/// may be consuming. The main purpose of `List` the is inner data management
+/// between files, data types, structeres and so on in the DBSM.
 pub struct List<T, K> {
-    col: Col<T>,
+    varseq: VarSeq,
     ixmap: HashMap<K, usize>,
+    phantom: PhantomData<T>,
 }
 
 
-impl<K: Clone + Eq + Hash, T: Clone + ListKeyTrait<K>> List<T, K> {
-    /// Create a new `List` object located at `path`.
+impl<K: Clone + Eq + Hash, 
+     T: Clone + ListKeyTrait<K> + RecordTrait + ListLegacyTrait> List<T, K> {
+    /// Create a new `List` object located at `path`. If the file at `path`
+    /// is in the legacy layout, it is converted first.
     pub async fn new(path: impl AsRef<Path>) -> TokioResult<Self> {
-        let mut col = Col::<T>::new(path).await?;
-        let ixmap = Self::_build_ixmap(&mut col).await?;
-        Ok(Self { col, ixmap })
+        let heap_path = format!("{}.heap", path.as_ref().display());
+        Self::_convert_legacy(path.as_ref(), &heap_path).await?;
+        let varseq = VarSeq::new(path, heap_path).await?;
+        let mut list = Self { varseq, ixmap: HashMap::new(), 
+                              phantom: PhantomData };
+        list.ixmap = list.list().await?.iter().enumerate()
+            .map(|(ix, rec)| (rec.key(), ix))
+            .collect();
+        Ok(list)
     }
 
     /// Check whether the key exists.
@@ -46,18 +75,21 @@ impl<K: Clone + Eq + Hash, T: Clone + ListKeyTrait<K>> List<T, K> {
 
     /// Size of the list.
     pub async fn size(&self) -> TokioResult<usize> {
-        self.col.size().await
+        self.varseq.size().await
     }
 
     /// List all records as a vector.
     pub async fn list(&mut self) -> TokioResult<Vec<T>> {
-        self.col.get_all().await
+        let size = self.varseq.size().await?;
+        self.varseq.get(0, size).await?.iter()
+            .map(|block| decode_record(block))
+            .collect()
     }
 
     /// Mapping of all records by key.
     pub async fn map(&mut self) -> TokioResult<HashMap<K, T>> {
         Ok(
-            self.col.get_all().await?
+            self.list().await?
                 .into_iter()
                 .map(|rec| (rec.key(), rec))
                 .collect()
@@ -67,7 +99,7 @@ impl<K: Clone + Eq + Hash, T: Clone + ListKeyTrait<K>> List<T, K> {
     /// Get record by key.
     pub async fn detail(&mut self, key: &K) -> TokioResult<T> {
         if let Some(&ix) = self.ixmap.get(key) {
-            self.col.get(ix).await
+            decode_record(&self.varseq.get(ix, 1).await?[0])
         } else {
             Err(ErrorKind::NotFound.into())
         }
@@ -77,7 +109,7 @@ impl<K: Clone + Eq + Hash, T: Clone + ListKeyTrait<K>> List<T, K> {
     pub async fn add(&mut self, rec: &T) -> TokioResult<()> {
         let key = rec.key();
         if !self.ixmap.contains_key(&key) {
-            let ix = self.col.push(rec).await?;
+            let ix = self.varseq.push(&[encode_record(rec)]).await?;
             self.ixmap.insert(key, ix);
             Ok(())
         } else {
@@ -85,14 +117,35 @@ impl<K: Clone + Eq + Hash, T: Clone + ListKeyTrait<K>> List<T, K> {
         }
     }
 
+    /// Add new records at once. No record is added if some key exists or
+    /// repeats.
+    pub async fn add_many(&mut self, recs: &[T]) -> TokioResult<()> {
+        let keys = recs.iter().map(|rec| rec.key()).collect::<Vec<K>>();
+        for (ix, key) in keys.iter().enumerate() {
+            if self.ixmap.contains_key(key) || keys[..ix].contains(key) {
+                return Err(ErrorKind::AlreadyExists.into());
+            }
+        }
+        let blocks = recs.iter().map(encode_record).collect::<Vec<Vec<u8>>>();
+        let ix = self.varseq.push(&blocks).await?;
+        for (i, key) in keys.into_iter().enumerate() {
+            self.ixmap.insert(key, ix + i);
+        }
+        Ok(())
+    }
+
     /// Remove the record by key.
     pub async fn remove(&mut self, key: &K) -> TokioResult<()> {
         if let Some(&ix) = self.ixmap.get(key) {
-            let size = self.col.size().await?;
-            let rec = self.col.get(size - 1).await?;
-            self.col.update(ix, &rec).await?;
-            self.col.resize(size - 1).await?;
+            let size = self.varseq.size().await?;
+            let block = self.varseq.get(size - 1, 1).await?;
+            let rec: T = decode_record(&block[0])?;
+            self.varseq.update(ix, &block).await?;
+            self.varseq.resize(size - 1).await?;
             self.ixmap.remove(key);
+            if ix < size - 1 {
+                self.ixmap.insert(rec.key(), ix);
+            }
             Ok(())
         } else {
             Err(ErrorKind::NotFound.into())
@@ -105,12 +158,12 @@ impl<K: Clone + Eq + Hash, T: Clone + ListKeyTrait<K>> List<T, K> {
             let new_key = rec.key();
 
             if &new_key == key {
-                self.col.update(ix, rec).await?;
+                self.varseq.update(ix, &[encode_record(rec)]).await?;
                 Ok(())
             } else if self.ixmap.contains_key(&new_key) {
                 Err(ErrorKind::AlreadyExists.into())
             } else {
-                self.col.update(ix, rec).await?;
+                self.varseq.update(ix, &[encode_record(rec)]).await?;
                 self.ixmap.remove(key);
                 self.ixmap.insert(new_key, ix);
                 Ok(())
@@ -120,11 +173,43 @@ impl<K: Clone + Eq + Hash, T: Clone + ListKeyTrait<K>> List<T, K> {
         }
     }
 
-    async fn _build_ixmap(col: &mut Col<T>) -> TokioResult<HashMap<K, usize>> {
-        Ok(col.get_all().await?
-                .iter().enumerate()
-                .map(|(ix, rec)| (rec.key(), ix))
-                .collect()
-        )
+    async fn _convert_legacy(path: &Path, heap_path: &str) -> 
+                             TokioResult<()> {
+        // The legacy file is moved aside first, so the conversion is
+        // repeated if it is interrupted
+        let legacy_path = format!("{}.legacy", path.display());
+        if T::LEGACY_SIZE == 0 {
+            return Ok(());
+        } else if !try_exists(&legacy_path).await? {
+            if !try_exists(path).await? || try_exists(heap_path).await? {
+                return Ok(());
+            }
+            rename(path, &legacy_path).await?;
+        }
+
+        // Decode the legacy structs
+        let block = read(&legacy_path).await?;
+        if block.len() % T::LEGACY_SIZE != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, format!(
+                "{} is not a multiple of {}", block.len(), T::LEGACY_SIZE
+            )));
+        }
+        let recs = block.chunks(T::LEGACY_SIZE)
+            .map(T::from_legacy)
+            .collect::<TokioResult<Vec<T>>>()?;
+
+        // Write the records in the current format
+        for file_path in [path.display().to_string(), heap_path.to_string()] {
+            if let Err(err) = remove_file(&file_path).await
+                    && err.kind() != ErrorKind::NotFound {
+                return Err(err);
+            }
+        }
+        let mut varseq = VarSeq::new(path, heap_path).await?;
+        let blocks = recs.iter().map(encode_record).collect::<Vec<Vec<u8>>>();
+        varseq.push(&blocks).await?;
+
+        // Ok
+        remove_file(&legacy_path).await
     }
 }
@@ -0,0 +1,367 @@
+//! `Predicate` is a condition on the column values that selects the rows
+//! of a feed in `Conn::data_filter`. It is a small expression tree of
+//! the comparisons combined with the logical operations.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use base64::prelude::*;
+
+use crate::datatype::{Datatype, Dataunit};
+use crate::dataset::Dataset;
+
+
+/// Comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    /// Equal.
+    Eq,
+
+    /// Not equal.
+    Ne,
+
+    /// Less than.
+    Lt,
+
+    /// Less than or equal.
+    Le,
+
+    /// Greater than.
+    Gt,
+
+    /// Greater than or equal.
+    Ge,
+}
+
+
+/// Condition on the values of the columns. Integers and floats are compared
+/// as numbers, strings are compared lexicographically, the values of bytes
+/// columns (given in Base64) are compared by their bytes. The values of
+/// `Decimal`, `Int128` and `UInt128` columns are compared as numbers too,
+/// they can be compared with strings, integers and floats. The comparisons
+/// with null values are false, so `Not` of them is true.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    /// Comparison of the column value with the given one.
+    Cmp(String, CmpOp, Dataunit),
+
+    /// The column value is equal to one of the given ones.
+    In(String, Vec<Dataunit>),
+
+    /// The column value is between the given ones inclusively.
+    Between(String, Dataunit, Dataunit),
+
+    /// The column value is null.
+    IsNull(String),
+
+    /// All the conditions are true, it is true if there are no conditions.
+    And(Vec<Predicate>),
+
+    /// Some of the conditions is true, it is false if there are no
+    /// conditions.
+    Or(Vec<Predicate>),
+
+    /// The condition is false.
+    Not(Box<Predicate>),
+}
+
+
+impl Predicate {
+    /// Create a comparison of the column `col` with `value`.
+    pub fn cmp(col: &str, op: CmpOp, value: Dataunit) -> Self {
+        Self::Cmp(col.to_string(), op, value)
+    }
+
+    /// Create a check whether the value of the column `col` is one of
+    /// `values`.
+    pub fn is_in(col: &str, values: Vec<Dataunit>) -> Self {
+        Self::In(col.to_string(), values)
+    }
+
+    /// Create a check whether the value of the column `col` is between
+    /// `low` and `high` inclusively.
+    pub fn between(col: &str, low: Dataunit, high: Dataunit) -> Self {
+        Self::Between(col.to_string(), low, high)
+    }
+
+    /// Create a check whether the value of the column `col` is null.
+    pub fn is_null(col: &str) -> Self {
+        Self::IsNull(col.to_string())
+    }
+
+    /// Combine with `other` so both must be true.
+    pub fn and(self, other: Self) -> Self {
+        match self {
+            Self::And(mut preds) => {
+                preds.push(other);
+                Self::And(preds)
+            },
+            _ => Self::And(vec![self, other]),
+        }
+    }
+
+    /// Combine with `other` so one of them must be true.
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Self::Or(mut preds) => {
+                preds.push(other);
+                Self::Or(preds)
+            },
+            _ => Self::Or(vec![self, other]),
+        }
+    }
+
+    /// Get the names of the columns used in the condition without
+    /// duplicates.
+    pub fn cols(&self) -> Vec<String> {
+        let mut cols = vec![];
+        self._collect_cols(&mut cols);
+        cols
+    }
+
+    /// Evaluate the condition for `size` rows of `ds` that must contain
+    /// the used columns, the missing values are considered as nulls.
+    /// `datatypes` are the datatypes of the columns, they define how
+    /// the values are compared.
+    pub fn eval(&self, ds: &Dataset, size: usize,
+                datatypes: &HashMap<String, Datatype>) -> Vec<bool> {
+        match self {
+            Self::Cmp(col, op, value) => {
+                Self::_eval_col(ds, size, col, |unit| {
+                    let ord = compare(unit, value, datatypes.get(col));
+                    ord.is_some_and(|ord| match op {
+                        CmpOp::Eq => ord == Ordering::Equal,
+                        CmpOp::Ne => ord != Ordering::Equal,
+                        CmpOp::Lt => ord == Ordering::Less,
+                        CmpOp::Le => ord != Ordering::Greater,
+                        CmpOp::Gt => ord == Ordering::Greater,
+                        CmpOp::Ge => ord != Ordering::Less,
+                    })
+                })
+            },
+            Self::In(col, values) => {
+                Self::_eval_col(ds, size, col, |unit| {
+                    values.iter().any(|value| {
+                        compare(unit, value, datatypes.get(col)) ==
+                            Some(Ordering::Equal)
+                    })
+                })
+            },
+            Self::Between(col, low, high) => {
+                Self::_eval_col(ds, size, col, |unit| {
+                    let datatype = datatypes.get(col);
+                    compare(unit, low, datatype)
+                        .is_some_and(|ord| ord != Ordering::Less) &&
+                    compare(unit, high, datatype)
+                        .is_some_and(|ord| ord != Ordering::Greater)
+                })
+            },
+            Self::IsNull(col) => {
+                match ds.get(col) {
+                    Some(values) => values.iter()
+                        .map(|unit| *unit == Dataunit::Null)
+                        .collect(),
+                    None => vec![true; size],
+                }
+            },
+            Self::And(preds) => {
+                let mut res = vec![true; size];
+                for pred in preds.iter() {
+                    for (r, x) in res.iter_mut()
+                            .zip(pred.eval(ds, size, datatypes)) {
+                        *r &= x;
+                    }
+                }
+                res
+            },
+            Self::Or(preds) => {
+                let mut res = vec![false; size];
+                for pred in preds.iter() {
+                    for (r, x) in res.iter_mut()
+                            .zip(pred.eval(ds, size, datatypes)) {
+                        *r |= x;
+                    }
+                }
+                res
+            },
+            Self::Not(pred) => {
+                pred.eval(ds, size, datatypes).into_iter()
+                    .map(|x| !x)
+                    .collect()
+            },
+        }
+    }
+
+    fn _eval_col(ds: &Dataset, size: usize, col: &str,
+                 f: impl Fn(&Dataunit) -> bool) -> Vec<bool> {
+        match ds.get(col) {
+            Some(values) => values.iter().map(f).collect(),
+            None => vec![false; size],
+        }
+    }
+
+    fn _collect_cols(&self, cols: &mut Vec<String>) {
+        match self {
+            Self::Cmp(col, _, _) | Self::In(col, _) |
+                    Self::Between(col, _, _) | Self::IsNull(col) => {
+                if !cols.contains(col) {
+                    cols.push(col.clone());
+                }
+            },
+            Self::And(preds) | Self::Or(preds) => {
+                for pred in preds.iter() {
+                    pred._collect_cols(cols);
+                }
+            },
+            Self::Not(pred) => pred._collect_cols(cols),
+        }
+    }
+}
+
+
+impl std::ops::Not for Predicate {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+}
+
+
+/// Compare the values of the column with the datatype `datatype`. It is
+/// `None` if the values are not comparable (like nulls or a number and
+/// a string).
+pub fn compare(a: &Dataunit, b: &Dataunit, datatype: Option<&Datatype>) ->
+               Option<Ordering> {
+    // The numbers given as strings are compared exactly if possible, 
+    // the unsigned ones may exceed `i128::MAX`, and a negative operand is
+    // less than any of them, so it is compared right by the floats
+    if let Some(datatype @ (Datatype::Decimal(..) | Datatype::Int128 | 
+                            Datatype::UInt128)) = datatype {
+        if let (Some(x), Some(y)) = (datatype.to_u128(a), 
+                                     datatype.to_u128(b)) {
+            return Some(x.cmp(&y));
+        }
+        return match (datatype.to_i128(a), datatype.to_i128(b)) {
+            (Some(x), Some(y)) => Some(x.cmp(&y)),
+            _ => _to_f64(a)?.partial_cmp(&_to_f64(b)?),
+        };
+    }
+
+    match (a, b) {
+        (Dataunit::I(x), Dataunit::I(y)) => Some(x.cmp(y)),
+        (Dataunit::F(x), Dataunit::F(y)) => x.partial_cmp(y),
+        (Dataunit::I(x), Dataunit::F(y)) => (*x as f64).partial_cmp(y),
+        (Dataunit::F(x), Dataunit::I(y)) => x.partial_cmp(&(*y as f64)),
+        (Dataunit::S(x), Dataunit::S(y)) => {
+            let is_bytes = matches!(datatype,
+                                    Some(Datatype::Bytes(_) |
+                                         Datatype::VarBytes));
+            match (BASE64_STANDARD.decode(x), BASE64_STANDARD.decode(y)) {
+                (Ok(x), Ok(y)) if is_bytes => Some(x.cmp(&y)),
+                _ => Some(x.cmp(y)),
+            }
+        },
+        (Dataunit::List(x), Dataunit::List(y)) if x == y => {
+            Some(Ordering::Equal)
+        },
+        _ => None,
+    }
+}
+
+
+fn _to_f64(unit: &Dataunit) -> Option<f64> {
+    match unit {
+        Dataunit::I(x) => Some(*x as f64),
+        Dataunit::F(x) => Some(*x),
+        Dataunit::S(x) => x.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_predicate() {
+        let ds = Dataset::from([
+            ("x".to_string(), vec![
+                Dataunit::I(1), Dataunit::I(5), Dataunit::Null, Dataunit::I(8),
+            ]),
+            ("b".to_string(), ["AA==", "/w==", "gA==", "fw=="].iter()
+                .map(|s| Dataunit::S(s.to_string()))
+                .collect()),
+        ]);
+        let datatypes = HashMap::from([
+            ("x".to_string(), Datatype::Int64),
+            ("b".to_string(), Datatype::Bytes(1)),
+        ]);
+
+        let pred = Predicate::cmp("x", CmpOp::Ge, Dataunit::F(4.5))
+            .and(Predicate::cmp("x", CmpOp::Ne, Dataunit::I(8)))
+            .or(Predicate::is_null("x"));
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [false, true, true, false]);
+        assert_eq!(pred.cols(), ["x"]);
+
+        let pred = !Predicate::between("x", Dataunit::I(2), Dataunit::I(8));
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [true, false, true, false]);
+
+        let pred = Predicate::cmp("b", CmpOp::Gt,
+                                  Dataunit::S("fw==".to_string()))
+            .and(Predicate::is_in("x", vec![Dataunit::I(5), Dataunit::Null]));
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [false, true, false, false]);
+        assert_eq!(pred.cols(), ["b", "x"]);
+
+        let ds = Dataset::from([
+            ("p".to_string(), ["9.00", "10.00", "-0.50", "9.99"].iter()
+                .map(|s| Dataunit::S(s.to_string()))
+                .collect()),
+            ("n".to_string(), [
+                "9", "10", &i128::MIN.to_string(), &i128::MAX.to_string(),
+            ].iter()
+                .map(|s| Dataunit::S(s.to_string()))
+                .collect()),
+            ("u".to_string(), [
+                "0", &(u128::MAX - 1).to_string(), &u128::MAX.to_string(), "9",
+            ].iter()
+                .map(|s| Dataunit::S(s.to_string()))
+                .collect()),
+        ]);
+        let datatypes = HashMap::from([
+            ("p".to_string(), Datatype::Decimal(10, 2)),
+            ("n".to_string(), Datatype::Int128),
+            ("u".to_string(), Datatype::UInt128),
+        ]);
+
+        let pred = Predicate::cmp("p", CmpOp::Lt, 
+                                  Dataunit::S("10.00".to_string()));
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [true, false, true, true]);
+        let pred = Predicate::cmp("p", CmpOp::Ge, Dataunit::I(10))
+            .or(Predicate::cmp("p", CmpOp::Gt, Dataunit::F(9.995)));
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [false, true, false, false]);
+        let pred = Predicate::is_in("p", vec![Dataunit::S("9".to_string())]);
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [true, false, false, false]);
+
+        let pred = Predicate::cmp("n", CmpOp::Gt, Dataunit::I(9));
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [false, true, false, true]);
+        let pred = Predicate::between("n", Dataunit::S("-1".to_string()),
+                                      Dataunit::F(9.5));
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [true, false, false, false]);
+
+        let max = Dataunit::S(u128::MAX.to_string());
+        let pred = Predicate::cmp("u", CmpOp::Eq, max.clone());
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [false, false, true, false]);
+        let pred = Predicate::cmp("u", CmpOp::Lt, max);
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [true, true, false, true]);
+        let pred = Predicate::between("u", Dataunit::I(-1), Dataunit::S(
+            (u128::MAX - 1).to_string()
+        ));
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [true, true, false, true]);
+        let pred = Predicate::is_in("u", vec![
+            Dataunit::S((u128::MAX - 1).to_string()), Dataunit::F(9.0),
+        ]);
+        assert_eq!(pred.eval(&ds, 4, &datatypes), [false, true, false, true]);
+    }
+}
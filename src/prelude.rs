@@ -1,5 +1,12 @@
 //! Common used imports such that `Conn`, `Dataset` and others.
 
 pub use crate::datatype::{Dataunit, Datatype};
-pub use crate::dataset::Dataset;
+pub use crate::dataset::{Dataset, ReadOptions};
+pub use crate::remap::IndexRemap;
+pub use crate::predicate::{Predicate, CmpOp};
+pub use crate::aggregate::AggFn;
+pub use crate::group::GroupOptions;
+pub use crate::items::{ColOptions, Meta, TrimMode, PartitionItem};
+pub use crate::cast::CastPolicy;
+pub use crate::schema::FeedSchema;
 pub use crate::conn::Conn;
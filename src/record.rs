@@ -0,0 +1,239 @@
+//! Binary format of the records stored in `List`. Every record starts with
+//! the version of the format, so the records written by older versions stay
+//! readable after new fields are added. The values are written one after
+//! another: integers in little endian, byte strings and strings with their
+//! length before them.
+
+use std::collections::HashMap;
+
+use tokio::io::{Error, ErrorKind, Result as TokioResult};
+
+use crate::datatype::Dataunit;
+
+
+/// Current version of the record format.
+pub const RECORD_VERSION: u8 = 2;
+
+
+/// Record trait so a struct can be encoded into bytes and stored in `List`.
+pub trait RecordTrait: Sized {
+    /// Write the fields of the record.
+    fn encode(&self, writer: &mut RecordWriter);
+
+    /// Read the fields of the record written with the format `version`.
+    fn decode(reader: &mut RecordReader, version: u8) -> TokioResult<Self>;
+}
+
+
+/// Encode the record into bytes with the current version.
+pub fn encode_record<T: RecordTrait>(rec: &T) -> Vec<u8> {
+    let mut writer = RecordWriter::new();
+    writer.put_u8(RECORD_VERSION);
+    rec.encode(&mut writer);
+    writer.into_bytes()
+}
+
+
+/// Decode the record from bytes. It returns an error if the bytes are
+/// corrupted or written by a newer version.
+pub fn decode_record<T: RecordTrait>(block: &[u8]) -> TokioResult<T> {
+    let mut reader = RecordReader::new(block);
+    let version = reader.get_u8()?;
+    if version == 0 || version > RECORD_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData, format!("record version {}", version)
+        ));
+    }
+    T::decode(&mut reader, version)
+}
+
+
+/// `RecordWriter` collects the encoded values into bytes.
+#[derive(Default)]
+pub struct RecordWriter {
+    block: Vec<u8>,
+}
+
+
+impl RecordWriter {
+    /// Create an empty writer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the written bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.block
+    }
+
+    /// Write a byte.
+    pub fn put_u8(&mut self, x: u8) {
+        self.block.push(x);
+    }
+
+    /// Write a boolean as a byte.
+    pub fn put_bool(&mut self, x: bool) {
+        self.put_u8(x as u8);
+    }
+
+    /// Write an unsigned integer.
+    pub fn put_u64(&mut self, x: u64) {
+        self.block.extend_from_slice(&x.to_le_bytes());
+    }
+
+    /// Write a byte string with its length.
+    pub fn put_bytes(&mut self, x: &[u8]) {
+        self.put_u64(x.len() as u64);
+        self.block.extend_from_slice(x);
+    }
+
+    /// Write a string with its length.
+    pub fn put_str(&mut self, x: &str) {
+        self.put_bytes(x.as_bytes());
+    }
+
+    /// Write the string pairs ordered by the keys, so the same map is always
+    /// encoded in the same way.
+    pub fn put_map(&mut self, x: &HashMap<String, String>) {
+        let mut pairs = x.iter().collect::<Vec<_>>();
+        pairs.sort();
+        self.put_u64(pairs.len() as u64);
+        for (key, value) in pairs {
+            self.put_str(key);
+            self.put_str(value);
+        }
+    }
+
+    /// Write a value of a dataset with its kind before it. The lists are
+    /// not supported and written as nulls.
+    pub fn put_unit(&mut self, x: &Dataunit) {
+        match x {
+            Dataunit::I(x) => {
+                self.put_u8(1);
+                self.put_u64(*x as u64);
+            },
+            Dataunit::F(x) => {
+                self.put_u8(2);
+                self.put_u64(x.to_bits());
+            },
+            Dataunit::S(x) => {
+                self.put_u8(3);
+                self.put_str(x);
+            },
+            _ => self.put_u8(0),
+        }
+    }
+}
+
+
+/// `RecordReader` reads the encoded values from bytes in the order they
+/// were written.
+pub struct RecordReader<'a> {
+    block: &'a [u8],
+    pos: usize,
+}
+
+
+impl<'a> RecordReader<'a> {
+    /// Create a reader over `block`.
+    pub fn new(block: &'a [u8]) -> Self {
+        Self { block, pos: 0 }
+    }
+
+    /// Read a byte.
+    pub fn get_u8(&mut self) -> TokioResult<u8> {
+        Ok(self._take(1)?[0])
+    }
+
+    /// Read a boolean.
+    pub fn get_bool(&mut self) -> TokioResult<bool> {
+        Ok(self.get_u8()? != 0)
+    }
+
+    /// Read an unsigned integer.
+    pub fn get_u64(&mut self) -> TokioResult<u64> {
+        let bytes = self._take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Read a byte string.
+    pub fn get_bytes(&mut self) -> TokioResult<&'a [u8]> {
+        let size = self.get_u64()? as usize;
+        self._take(size)
+    }
+
+    /// Read a string.
+    pub fn get_str(&mut self) -> TokioResult<String> {
+        let bytes = self.get_bytes()?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    /// Read the string pairs.
+    pub fn get_map(&mut self) -> TokioResult<HashMap<String, String>> {
+        let size = self.get_u64()? as usize;
+        (0..size).map(|_| Ok((self.get_str()?, self.get_str()?))).collect()
+    }
+
+    /// Read a value of a dataset.
+    pub fn get_unit(&mut self) -> TokioResult<Dataunit> {
+        Ok(match self.get_u8()? {
+            0 => Dataunit::Null,
+            1 => Dataunit::I(self.get_u64()? as i64),
+            2 => Dataunit::F(f64::from_bits(self.get_u64()?)),
+            3 => Dataunit::S(self.get_str()?),
+            kind => return Err(Error::new(
+                ErrorKind::InvalidData, format!("unit kind {}", kind)
+            )),
+        })
+    }
+
+    /// Check whether all the bytes are read.
+    pub fn is_end(&self) -> bool {
+        self.pos == self.block.len()
+    }
+
+    fn _take(&mut self, size: usize) -> TokioResult<&'a [u8]> {
+        if size > self.block.len() - self.pos {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+        let bytes = &self.block[self.pos..self.pos + size];
+        self.pos += size;
+        Ok(bytes)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() -> TokioResult<()> {
+        let tags = HashMap::from([
+            ("unit".to_string(), "kg".to_string()),
+            ("owner".to_string(), "qwe".to_string()),
+        ]);
+
+        let mut writer = RecordWriter::new();
+        writer.put_bool(true);
+        writer.put_u64(42);
+        writer.put_str("qwe");
+        writer.put_map(&tags);
+        writer.put_unit(&Dataunit::I(-5));
+        writer.put_unit(&Dataunit::Null);
+        let block = writer.into_bytes();
+
+        let mut reader = RecordReader::new(&block);
+        assert!(reader.get_bool()?);
+        assert_eq!(reader.get_u64()?, 42);
+        assert_eq!(reader.get_str()?, "qwe");
+        assert_eq!(reader.get_map()?, tags);
+        assert_eq!(reader.get_unit()?, Dataunit::I(-5));
+        assert_eq!(reader.get_unit()?, Dataunit::Null);
+        assert!(reader.is_end());
+        assert!(reader.get_u8().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+//! `IndexRemap` describes how the row indices change after the deleted rows
+//! are removed from a feed by `Conn::feed_compact`. The kept rows are stored
+//! as sorted ranges of the old indices, so the mapping stays compact even
+//! for billions of rows.
+
+use std::ops::Range;
+
+
+/// Mapping of the old row indices to the new ones. The kept rows keep their
+/// order, so the new index of a row is the number of kept rows before it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexRemap {
+    ranges: Vec<Range<usize>>,
+    starts: Vec<usize>,
+    size: usize,
+}
+
+
+impl IndexRemap {
+    /// Create an empty mapping.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the range of the kept rows. The ranges must be added in
+    /// the increasing order, the adjacent ones are merged.
+    pub fn push(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        match self.ranges.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => {
+                self.starts.push(self.size);
+                self.ranges.push(range.clone());
+            }
+        }
+        self.size += range.len();
+    }
+
+    /// Get the ranges of the old indices of the kept rows.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// Get the number of the kept rows that is the new size of the feed.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get the new index of the row `ix`, it is `None` for the deleted rows.
+    pub fn get(&self, ix: usize) -> Option<usize> {
+        let pos = self.ranges.partition_point(|range| range.end <= ix);
+        match self.ranges.get(pos) {
+            Some(range) if range.start <= ix => {
+                Some(self.starts[pos] + ix - range.start)
+            },
+            _ => None,
+        }
+    }
+
+    /// Get the number of the kept rows located before `ix`.
+    pub fn count_before(&self, ix: usize) -> usize {
+        let pos = self.ranges.partition_point(|range| range.end <= ix);
+        match self.ranges.get(pos) {
+            Some(range) => self.starts[pos] + ix.saturating_sub(range.start),
+            None => self.size,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap() {
+        let mut remap = IndexRemap::new();
+        remap.push(0..2);
+        remap.push(2..3);
+        remap.push(5..5);
+        remap.push(5..8);
+        remap.push(10..11);
+
+        assert_eq!(remap.ranges(), &[0..3, 5..8, 10..11]);
+        assert_eq!(remap.size(), 7);
+
+        assert_eq!(remap.get(1), Some(1));
+        assert_eq!(remap.get(3), None);
+        assert_eq!(remap.get(6), Some(4));
+        assert_eq!(remap.get(10), Some(6));
+        assert_eq!(remap.get(11), None);
+
+        assert_eq!(remap.count_before(0), 0);
+        assert_eq!(remap.count_before(4), 3);
+        assert_eq!(remap.count_before(7), 5);
+        assert_eq!(remap.count_before(9), 6);
+        assert_eq!(remap.count_before(20), 7);
+    }
+}
@@ -0,0 +1,101 @@
+//! `FeedSchema` is a declaration of the columns of a feed, so the feed can be
+//! created or checked at once by `Conn::feed_ensure` instead of checking and
+//! adding the columns one by one.
+
+use std::io::{Error, ErrorKind};
+
+use crate::items::{ColItem, ColOptions};
+
+
+/// Declaration of a column: its name, datatype and options.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColSchema {
+    /// Name of the column.
+    pub name: String,
+
+    /// Datatype of the column as string.
+    pub datatype: String,
+
+    /// Options of the column.
+    pub options: ColOptions,
+}
+
+
+impl ColSchema {
+    /// Get the declaration of the existing column.
+    pub fn from_item(col_item: &ColItem) -> Self {
+        Self {
+            name: col_item.get_name(),
+            datatype: col_item.get_datatype(),
+            options: col_item.get_options(),
+        }
+    }
+}
+
+
+/// Ordered list of the column declarations of a feed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeedSchema {
+    /// Declarations of the columns.
+    pub cols: Vec<ColSchema>,
+}
+
+
+impl FeedSchema {
+    /// Create an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a column with the default options.
+    pub fn col(self, name: &str, datatype: &str) -> Self {
+        self.col_with(name, datatype, &ColOptions::default())
+    }
+
+    /// Add a column with the given options.
+    pub fn col_with(mut self, name: &str, datatype: &str,
+                    options: &ColOptions) -> Self {
+        self.cols.push(ColSchema {
+            name: name.to_string(),
+            datatype: datatype.to_string(),
+            options: options.clone(),
+        });
+        self
+    }
+
+    /// Create the column items of the declarations. It returns an error if
+    /// some declaration is invalid or the names repeat.
+    pub fn col_items(&self) -> std::io::Result<Vec<ColItem>> {
+        let col_items = self.cols.iter()
+            .map(|col| ColItem::with_options(&col.name, &col.datatype, 
+                                             &col.options))
+            .collect::<std::io::Result<Vec<ColItem>>>()?;
+        for (ix, col_item) in col_items.iter().enumerate() {
+            if col_items[..ix].iter().any(|other| other.name == col_item.name) {
+                return Err(Error::new(ErrorKind::InvalidInput, 
+                                      col_item.get_name()));
+            }
+        }
+        Ok(col_items)
+    }
+
+    /// Get the schema of the column items.
+    pub fn from_items(col_items: &[ColItem]) -> Self {
+        Self { cols: col_items.iter().map(ColSchema::from_item).collect() }
+    }
+}
+
+
+/// Changes made by `Conn::feed_ensure`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Whether the feed was created.
+    pub feed_added: bool,
+
+    /// Names of the added columns in the order of the schema.
+    pub cols_added: Vec<String>,
+
+    /// Names of the existing columns that are not declared in the schema.
+    /// They are kept unchanged.
+    pub cols_extra: Vec<String>,
+}
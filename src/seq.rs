@@ -1,12 +1,16 @@
 //! `Seq` is the basic structure to manage the data storing in a file. It 
 //! works exactly with byte blocks, supports asynchronous interface,
-//! allows to fetch, push and update data.
+//! allows to fetch, push and update data. The blocks can also be split into
+//! segments of a fixed number of blocks, one file per segment.
 
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-use tokio::fs::{File, OpenOptions};
-use tokio::io::Result as TokioResult;
+use tokio::fs::{File, OpenOptions, read_dir, remove_file};
+use tokio::io::{Error, ErrorKind, Result as TokioResult};
 use tokio::io::{SeekFrom, AsyncSeekExt, AsyncWriteExt, AsyncReadExt};
+use tokio::sync::Mutex;
+use tokio::task::spawn_blocking;
 
 // TODO: Maybe it is necessary to implement throught tokio_uring 
 // (https://docs.rs/tokio-uring/latest/tokio_uring/) that supports a faster 
@@ -19,23 +23,99 @@ use tokio::io::{SeekFrom, AsyncSeekExt, AsyncWriteExt, AsyncReadExt};
 /// blocks with the same size (`block_size`). Each block can be accessed by
 /// its index.
 pub struct Seq {
-    file: File,
+    layout: Layout,
     block_size: usize,
 }
 
 
+/// Files keeping the blocks of `Seq`.
+enum Layout {
+    /// All the blocks are in one file.
+    Single(File),
+
+    /// The blocks are split into segments.
+    Segmented(Segments),
+}
+
+
+/// Segments of `segment_size` blocks stored in the files `<path>.<n>`, where
+/// `n` is the segment number, so the segment `n` keeps the blocks starting
+/// from `n * segment_size`. The dropped segments are missing in `files`.
+/// The files are locked, so the segments can be removed on resize without
+/// exclusive access to `Seq`.
+struct Segments {
+    path: PathBuf,
+    segment_size: usize,
+    files: Mutex<BTreeMap<usize, Segment>>,
+}
+
+
+/// Segment file. The sealed segments are full and opened as read only.
+struct Segment {
+    file: File,
+    sealed: bool,
+}
+
+
 impl Seq {
     /// Create a `Seq` object located by the given `path` and having the given
     /// `block_size`. If no file exists, it creates an empty one.
     pub async fn new(path: impl AsRef<Path>, block_size: usize) -> 
                      TokioResult<Self> {
-        let file = OpenOptions::new()
-            .write(true)
-            .read(true)
-            .create(true)
-            .open(path)
-            .await?;
-        Ok(Self { file, block_size })
+        let file = Self::_open_file(path).await?;
+        Ok(Self { layout: Layout::Single(file), block_size })
+    }
+
+    /// Create a segmented `Seq` object that stores the blocks in the segments
+    /// of `segment_size` blocks located by `<path>.<n>`. The segments are
+    /// opened unsealed, so they are supposed to be sealed after that.
+    pub async fn new_segmented(path: impl AsRef<Path>, block_size: usize,
+                               segment_size: usize) -> TokioResult<Self> {
+        let mut files = BTreeMap::new();
+        for (n, segment_path) in Self::segment_paths(&path).await? {
+            let file = Self::_open_file(&segment_path).await?;
+            files.insert(n, Segment { file, sealed: false });
+        }
+        let segments = Segments {
+            path: path.as_ref().to_path_buf(), segment_size,
+            files: Mutex::new(files),
+        };
+        Ok(Self { layout: Layout::Segmented(segments), block_size })
+    }
+
+    /// Get the existing segment files of the segmented `Seq` located by
+    /// `path` with their numbers in the increasing order.
+    pub async fn segment_paths(path: impl AsRef<Path>) ->
+                               TokioResult<Vec<(usize, PathBuf)>> {
+        let path = path.as_ref();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let prefix = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => format!("{}.", name),
+            None => return Ok(vec![]),
+        };
+
+        let mut res = vec![];
+        let mut entries = match read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(res),
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let number = name.to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .filter(|s| !s.is_empty() &&
+                            s.chars().all(|c| c.is_ascii_digit()))
+                .and_then(|s| s.parse::<usize>().ok());
+            if let Some(n) = number {
+                res.push((n, entry.path()));
+            }
+        }
+        res.sort();
+        Ok(res)
     }
 
     /// Get block size in bytes.
@@ -43,17 +123,105 @@ impl Seq {
         self.block_size
     }
 
+    /// Get the number of blocks in a segment, it is `None` if the blocks are
+    /// stored in one file.
+    pub fn segment_size(&self) -> Option<usize> {
+        match &self.layout {
+            Layout::Single(_) => None,
+            Layout::Segmented(segments) => Some(segments.segment_size),
+        }
+    }
+
+    /// Get the numbers of the existing segments, it is empty if the blocks
+    /// are stored in one file.
+    pub async fn segment_list(&self) -> Vec<usize> {
+        match &self.layout {
+            Layout::Single(_) => vec![],
+            Layout::Segmented(segments) => {
+                segments.files.lock().await.keys().copied().collect()
+            },
+        }
+    }
+
     /// Get size of the file in the number of units sized with `block_size`.
     pub async fn size(&self) -> TokioResult<usize> {
-        let data = self.file.metadata().await?;
-        Ok(data.len() as usize / self.block_size)
+        match &self.layout {
+            Layout::Single(file) => {
+                let data = file.metadata().await?;
+                Ok(data.len() as usize / self.block_size)
+            },
+            Layout::Segmented(segments) => {
+                match segments.files.lock().await.last_key_value() {
+                    Some((n, segment)) => {
+                        let data = segment.file.metadata().await?;
+                        Ok(n * segments.segment_size +
+                           data.len() as usize / self.block_size)
+                    },
+                    None => Ok(0),
+                }
+            },
+        }
     }
 
     /// Resize the file setting a new size `new_size` in the number of units 
-    /// sized with `block_size`.
+    /// sized with `block_size`. For the segmented layout, the segments
+    /// after the new end are removed. The sealed segments cannot be
+    /// truncated.
     pub async fn resize(&self, new_size: usize) -> TokioResult<()> {
-        let byte_size = (new_size * self.block_size) as u64;
-        self.file.set_len(byte_size).await?;
+        let block_size = self.block_size;
+        let segments = match &self.layout {
+            Layout::Single(file) => {
+                let byte_size = (new_size * block_size) as u64;
+                file.set_len(byte_size).await?;
+                return Ok(());
+            },
+            Layout::Segmented(segments) => segments,
+        };
+        let mut files = segments.files.lock().await;
+
+        // Check the sealed segments stay untouched
+        let sealed = files.iter().rev()
+            .find(|(_, segment)| segment.sealed)
+            .map(|(&n, _)| n);
+        if let Some(n) = sealed && new_size < (n + 1) * segments.segment_size {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("Segment {} is sealed", n)
+            ));
+        }
+
+        // Remove the segments after the new end
+        let last = new_size.checked_sub(1)
+            .map(|ix| ix / segments.segment_size);
+        let removed = files.keys()
+            .filter(|&&n| last.is_none_or(|last| n > last))
+            .copied()
+            .collect::<Vec<usize>>();
+        for n in removed {
+            files.remove(&n);
+            remove_file(segments.segment_path(n)).await?;
+        }
+
+        // Set the sizes of the segments up to the new end
+        if let Some(last) = last {
+            let first = files.last_key_value().map(|(&n, _)| n).unwrap_or(0);
+            for n in first..=last {
+                let size = if n < last {
+                    segments.segment_size
+                } else {
+                    new_size - last * segments.segment_size
+                };
+                let byte_size = (size * block_size) as u64;
+                if let Some(segment) = files.get(&n) &&
+                        segment.file.metadata().await?.len() == byte_size {
+                    continue;
+                }
+                let segment = segments.get_writable(&mut files, n).await?;
+                segment.file.set_len(byte_size).await?;
+            }
+        }
+
+        // Ok
         Ok(())
     }
 
@@ -61,12 +229,21 @@ impl Seq {
     /// in bytes must be multiple of `block_size`, otherwise there can be 
     /// unpredictable behavior.
     pub async fn push(&mut self, block: &[u8]) -> TokioResult<usize> {
-        let pos = SeekFrom::End(0);
-        let offset = self.file.seek(pos).await?;
-        self.file.write_all(block).await?;
-        self.file.flush().await?;
-        let ix = offset as usize / self.block_size;
-        Ok(ix)
+        match &mut self.layout {
+            Layout::Single(file) => {
+                let pos = SeekFrom::End(0);
+                let offset = file.seek(pos).await?;
+                file.write_all(block).await?;
+                file.flush().await?;
+                let ix = offset as usize / self.block_size;
+                Ok(ix)
+            },
+            Layout::Segmented(_) => {
+                let ix = self.size().await?;
+                self.update(ix, block).await?;
+                Ok(ix)
+            },
+        }
     }
 
     /// Get data located by the index `ix` and write it to the `block`.
@@ -74,22 +251,123 @@ impl Seq {
     /// otherwise there can be unpredictable behavior.
     pub async fn get(&mut self, ix: usize, block: &mut [u8]) -> 
                      TokioResult<()> {
-        let byte_ix = (ix * self.block_size) as u64;
-        let pos = SeekFrom::Start(byte_ix);
-        self.file.seek(pos).await?;
-        self.file.read_exact(block).await?;
+        let block_size = self.block_size;
+        match &mut self.layout {
+            Layout::Single(file) => {
+                let byte_ix = (ix * block_size) as u64;
+                let pos = SeekFrom::Start(byte_ix);
+                file.seek(pos).await?;
+                file.read_exact(block).await?;
+            },
+            Layout::Segmented(segments) => {
+                let mut files = segments.files.lock().await;
+                let mut pos = 0;
+                for (n, offset, count) in
+                        segments.pieces(ix, block.len() / block_size) {
+                    let segment = files.get_mut(&n)
+                        .ok_or(Error::new(
+                            ErrorKind::NotFound,
+                            format!("Segment {} is dropped", n)
+                        ))?;
+                    let len = count * block_size;
+                    let byte_ix = (offset * block_size) as u64;
+                    segment.file.seek(SeekFrom::Start(byte_ix)).await?;
+                    segment.file.read_exact(&mut block[pos..pos + len])
+                        .await?;
+                    pos += len;
+                }
+            },
+        }
         Ok(())
     }
 
     /// Update data located by the index `ix` with the bytes in `block`.
     /// The size of `block` in bytes must be multiple of `block_size`, 
-    /// otherwise there can be unpredictable behavior.
+    /// otherwise there can be unpredictable behavior. The sealed segments
+    /// cannot be updated.
     pub async fn update(&mut self, ix: usize, block: &[u8]) -> TokioResult<()> {
-        let byte_ix = (ix * self.block_size) as u64;
-        let pos = SeekFrom::Start(byte_ix);
-        self.file.seek(pos).await?;
-        self.file.write_all(block).await?;
-        self.file.flush().await?;
+        let block_size = self.block_size;
+        match &mut self.layout {
+            Layout::Single(file) => {
+                let byte_ix = (ix * block_size) as u64;
+                let pos = SeekFrom::Start(byte_ix);
+                file.seek(pos).await?;
+                file.write_all(block).await?;
+                file.flush().await?;
+            },
+            Layout::Segmented(segments) => {
+                let mut files = segments.files.lock().await;
+                let mut pos = 0;
+                for (n, offset, count) in
+                        segments.pieces(ix, block.len() / block_size) {
+                    let segment = segments.get_writable(&mut files, n).await?;
+                    let len = count * block_size;
+                    let byte_ix = (offset * block_size) as u64;
+                    segment.file.seek(SeekFrom::Start(byte_ix)).await?;
+                    segment.file.write_all(&block[pos..pos + len]).await?;
+                    segment.file.flush().await?;
+                    pos += len;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Release the disk space of `count` blocks located from `ix` punching
+    /// a hole in the file. The blocks read as zeros after that and the size
+    /// of the file does not change. If the file system does not support it,
+    /// the blocks stay unchanged. For the segmented layout, the segments
+    /// covered by the range entirely are dropped (except the last one),
+    /// the sealed ones are skipped if they are covered partially.
+    pub async fn punch(&mut self, ix: usize, count: usize) -> TokioResult<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        let block_size = self.block_size;
+        match &mut self.layout {
+            Layout::Single(file) => {
+                let offset = ix * block_size;
+                let len = count * block_size;
+                _punch(file, offset as i64, len as i64).await
+            },
+            Layout::Segmented(segments) => {
+                let mut files = segments.files.lock().await;
+                let last = files.last_key_value().map(|(&n, _)| n);
+                for (n, offset, count) in segments.pieces(ix, count) {
+                    if count == segments.segment_size && Some(n) != last {
+                        if files.remove(&n).is_some() {
+                            remove_file(segments.segment_path(n)).await?;
+                        }
+                    } else if let Some(segment) = files.get(&n) &&
+                              !segment.sealed {
+                        _punch(
+                            &segment.file,
+                            (offset * block_size) as i64,
+                            (count * block_size) as i64
+                        ).await?;
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// Seal the segments located before the block `end` entirely: their
+    /// data are synced to the disk and they are reopened as read only.
+    /// It does nothing for one file layout.
+    pub async fn seal(&mut self, end: usize) -> TokioResult<()> {
+        if let Layout::Segmented(segments) = &mut self.layout {
+            let count = end / segments.segment_size;
+            for (n, segment) in segments.files.get_mut().range_mut(..count) {
+                if !segment.sealed {
+                    segment.file.sync_all().await?;
+                    segment.file = File::open(
+                        Segments::_segment_path(&segments.path, *n)
+                    ).await?;
+                    segment.sealed = true;
+                }
+            }
+        }
         Ok(())
     }
 
@@ -99,4 +377,157 @@ impl Seq {
         let ix = self.push(&block).await?;
         Ok(ix)
     }
+
+    async fn _open_file(path: impl AsRef<Path>) -> TokioResult<File> {
+        OpenOptions::new()
+            .write(true)
+            .read(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .await
+    }
+}
+
+
+impl Segments {
+    /// Split `count` blocks located from `ix` into the pieces inside
+    /// the segments as the segment number, the offset in the segment and
+    /// the number of blocks.
+    fn pieces(&self, ix: usize, count: usize) -> Vec<(usize, usize, usize)> {
+        let mut res = vec![];
+        let end = ix + count;
+        let mut pos = ix;
+        while pos < end {
+            let n = pos / self.segment_size;
+            let offset = pos - n * self.segment_size;
+            let count = (self.segment_size - offset).min(end - pos);
+            res.push((n, offset, count));
+            pos += count;
+        }
+        res
+    }
+
+    /// Get the segment `n` among the locked `files` for writing, the sealed
+    /// segments cannot be written. The segments after the last one are
+    /// created.
+    async fn get_writable<'a>(&self, 
+                              files: &'a mut BTreeMap<usize, Segment>,
+                              n: usize) -> TokioResult<&'a mut Segment> {
+        let last = files.last_key_value().map(|(&n, _)| n);
+        if last.is_none_or(|last| n > last) {
+            let file = Seq::_open_file(self.segment_path(n)).await?;
+            files.insert(n, Segment { file, sealed: false });
+        }
+        let segment = files.get_mut(&n).ok_or(Error::new(
+            ErrorKind::NotFound, format!("Segment {} is dropped", n)
+        ))?;
+        if segment.sealed {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("Segment {} is sealed", n)
+            ));
+        }
+        Ok(segment)
+    }
+
+    fn segment_path(&self, n: usize) -> PathBuf {
+        Self::_segment_path(&self.path, n)
+    }
+
+    fn _segment_path(path: &Path, n: usize) -> PathBuf {
+        let mut res = path.as_os_str().to_owned();
+        res.push(format!(".{}", n));
+        PathBuf::from(res)
+    }
+}
+
+
+/// Punch a hole in `file` in a blocking task, so the runtime is not blocked
+/// by the system call.
+async fn _punch(file: &File, offset: i64, len: i64) -> TokioResult<()> {
+    let file = file.try_clone().await?.into_std().await;
+    spawn_blocking(move || _punch_hole(&file, offset, len)).await?
+}
+
+
+#[cfg(target_os = "linux")]
+fn _punch_hole(file: &std::fs::File, offset: i64, len: i64) ->
+               TokioResult<()> {
+    use std::os::fd::AsRawFd;
+
+    let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+    let res = unsafe {
+        libc::fallocate(file.as_raw_fd(), mode, offset, len)
+    };
+    if res == 0 {
+        return Ok(());
+    }
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) => Ok(()),
+        _ => Err(err),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn _punch_hole(_file: &std::fs::File, _offset: i64, _len: i64) ->
+               TokioResult<()> {
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_segmented() -> TokioResult<()> {
+        let path = "./tmp/test_segmented.col";
+        for (_, segment_path) in Seq::segment_paths(path).await? {
+            remove_file(segment_path).await?;
+        }
+
+        let mut seq = Seq::new_segmented(path, 2, 4).await?;
+        assert_eq!(seq.size().await?, 0);
+
+        let block = (0..20u8).collect::<Vec<u8>>();
+        assert_eq!(seq.push(&block).await?, 0);
+        assert_eq!(seq.size().await?, 10);
+        assert_eq!(seq.segment_list().await, vec![0, 1, 2]);
+
+        let mut res = vec![0u8; 8];
+        seq.get(3, &mut res).await?;
+        assert_eq!(res, &block[6..14]);
+
+        seq.seal(10).await?;
+        let err = seq.update(5, &[0, 0]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        seq.update(9, &[1, 1]).await?;
+
+        let err = seq.resize(6).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        seq.resize(8).await?;
+        assert_eq!(seq.segment_list().await, vec![0, 1]);
+        seq.resize(9).await?;
+
+        drop(seq);
+        let mut seq = Seq::new_segmented(path, 2, 4).await?;
+        assert_eq!(seq.size().await?, 9);
+        seq.seal(9).await?;
+        let mut res = vec![0u8; 6];
+        seq.get(6, &mut res).await?;
+        assert_eq!(res, [12, 13, 14, 15, 0, 0]);
+
+        seq.punch(0, 6).await?;
+        assert_eq!(seq.segment_list().await, vec![1, 2]);
+        let err = seq.get(3, &mut res).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        seq.punch(4, 8).await?;
+        assert_eq!(seq.segment_list().await, vec![2]);
+        assert_eq!(seq.size().await?, 9);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,704 @@
+//! `Series` is the storage of a single column of a feed. It keeps the files
+//! of the column and converts the stored bytes into `Dataunit` values and
+//! back according to the datatype of the column.
+
+use tokio::io::{Error, ErrorKind, Result as TokioResult};
+use tokio::fs::{remove_file, rename};
+
+use crate::utils::{to_bytes_many, from_bytes_many, CHUNK_SIZE};
+use crate::seq::Seq;
+use crate::varseq::VarSeq;
+use crate::bitmap::Bitmap;
+use crate::dict::{Dict, Code};
+use crate::items::ColItem;
+use crate::datatype::{Datatype, Dataunit};
+
+
+/// Storage of the column data: fixed size blocks for the common datatypes,
+/// variable length records for `VarBytes` and `VarStr` and dictionary codes
+/// for `Category`.
+enum Storage {
+    Fixed(Seq),
+    Var(VarSeq),
+    Dict(Seq, Dict),
+}
+
+
+impl Storage {
+    async fn size(&self) -> TokioResult<usize> {
+        match self {
+            Self::Fixed(seq) | Self::Dict(seq, _) => seq.size().await,
+            Self::Var(varseq) => varseq.size().await,
+        }
+    }
+
+    async fn resize(&mut self, new_size: usize) -> TokioResult<()> {
+        match self {
+            Self::Fixed(seq) | Self::Dict(seq, _) => seq.resize(new_size).await,
+            Self::Var(varseq) => varseq.resize(new_size).await,
+        }
+    }
+
+    async fn get(&mut self, ix: usize, size: usize, datatype: &Datatype) ->
+                 TokioResult<Vec<Dataunit>> {
+        match self {
+            Self::Fixed(seq) => {
+                let mut block = vec![0u8; size * datatype.size()];
+                seq.get(ix, &mut block).await?;
+                Ok(block.chunks(datatype.size())
+                    .map(|chunk| datatype.from_bytes(chunk))
+                    .collect())
+            },
+            Self::Var(varseq) => {
+                Ok(varseq.get(ix, size).await?.iter()
+                    .map(|record| datatype.from_bytes(record))
+                    .collect())
+            },
+            Self::Dict(seq, dict) => {
+                let mut block = vec![0u8; size * datatype.size()];
+                seq.get(ix, &mut block).await?;
+                Ok(dict.decode(from_bytes_many::<Code>(&block))?.into_iter()
+                    .map(|value| datatype.from_bytes(value))
+                    .collect())
+            },
+        }
+    }
+
+    async fn update(&mut self, ix: usize, records: &[Vec<u8>]) ->
+                    TokioResult<()> {
+        match self {
+            Self::Fixed(seq) => seq.update(ix, &records.concat()).await,
+            Self::Var(varseq) => varseq.update(ix, records).await,
+            Self::Dict(seq, dict) => {
+                let codes = dict.encode(records).await?;
+                seq.update(ix, to_bytes_many(&codes)).await
+            },
+        }
+    }
+
+    async fn punch(&mut self, ix: usize, count: usize) -> TokioResult<()> {
+        match self {
+            Self::Fixed(seq) | Self::Dict(seq, _) => seq.punch(ix, count).await,
+            Self::Var(varseq) => varseq.punch(ix, count).await,
+        }
+    }
+
+    async fn fill(&mut self, ix: usize, size: usize, record: &[u8]) ->
+                  TokioResult<()> {
+        // The dictionary value is encoded once, so the codes are filled
+        let record = match self {
+            Self::Dict(_, dict) => {
+                let code = dict.encode(&[record.to_vec()]).await?;
+                to_bytes_many(&code).to_vec()
+            },
+            _ => record.to_vec(),
+        };
+
+        for start in (ix..ix + size).step_by(CHUNK_SIZE) {
+            let count = CHUNK_SIZE.min(ix + size - start);
+            match self {
+                Self::Fixed(seq) | Self::Dict(seq, _) => {
+                    seq.update(start, &record.repeat(count)).await?;
+                },
+                Self::Var(varseq) => {
+                    varseq.update(start, &vec![record.clone(); count]).await?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// `Series` implements the access to the column data by row indices. The
+/// column is stored in the file `<path>.col`, variable length columns
+/// also keep the heap file `<path>.heap`, categorical columns keep
+/// the dictionary files `<path>.dict` and `<path>.dictheap`, nullable
+/// columns keep the validity bitmap `<path>.valid`. If the column has
+/// a default value, it is used for the new and cleared values instead of
+/// zeros and nulls. The files store the values starting from the row
+/// `start` only, the earlier values are the default ones until some of
+/// them are updated, then the files are rewritten from there. The segmented
+/// series split the files into segments of a fixed number of values.
+pub struct Series {
+    path: String,
+    datatype: Datatype,
+    storage: Storage,
+    validity: Option<Bitmap>,
+    default: Option<Vec<u8>>,
+    start: usize,
+}
+
+
+impl Series {
+    /// Open the series located by `path` (without extension) for the column
+    /// `col_item`. If no files exist, they will be created.
+    pub async fn new(path: &str, col_item: &ColItem) -> TokioResult<Self> {
+        Self::_open(path, col_item, None).await
+    }
+
+    /// Open the segmented series that stores the values in the segments of
+    /// `segment_size` values, one file per segment `<file>.<n>` (like
+    /// `<path>.col.0`). It is supported for fixed size datatypes only and
+    /// `segment_size` must be multiple of 8 for the validity bitmap.
+    pub async fn new_segmented(path: &str, col_item: &ColItem,
+                               segment_size: usize) -> TokioResult<Self> {
+        Self::_open(path, col_item, Some(segment_size)).await
+    }
+
+    /// List all the file paths of the series located by `path` for
+    /// the column `col_item`.
+    pub fn paths(path: &str, col_item: &ColItem) -> Vec<String> {
+        let datatype = col_item.parse_datatype();
+        let mut paths = vec![Self::_get_col_path(path)];
+        if datatype.is_var() {
+            paths.push(Self::_get_heap_path(path));
+        } else if datatype == Datatype::Category {
+            paths.push(Self::_get_dict_path(path));
+            paths.push(Self::_get_dict_heap_path(path));
+        }
+        if col_item.nullable {
+            paths.push(Self::_get_valid_path(path));
+        }
+        paths
+    }
+
+    /// List the existing files of the series located by `path` for
+    /// the column `col_item` including the segment files.
+    pub async fn files(path: &str, col_item: &ColItem) ->
+                       TokioResult<Vec<String>> {
+        let mut files = vec![];
+        for path in Self::paths(path, col_item) {
+            if tokio::fs::try_exists(&path).await? {
+                files.push(path.clone());
+            }
+            for (_, segment_path) in Seq::segment_paths(&path).await? {
+                files.push(segment_path.to_string_lossy().to_string());
+            }
+        }
+        Ok(files)
+    }
+
+    /// Get datatype of the series.
+    pub fn datatype(&self) -> &Datatype {
+        &self.datatype
+    }
+
+    /// Check whether the series can contain null values.
+    pub fn is_nullable(&self) -> bool {
+        self.validity.is_some()
+    }
+
+    /// Get the index of the first stored value.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Get size of the series in the number of values.
+    pub async fn size(&self) -> TokioResult<usize> {
+        Ok(self.start + self.storage.size().await?)
+    }
+
+    /// Resize the series setting a new size `new_size`. New values are
+    /// the default value if it is set, otherwise zeros (empty for variable
+    /// length datatypes) or nulls if the series is nullable.
+    pub async fn resize(&mut self, new_size: usize) -> TokioResult<()> {
+        // The start cannot exceed the size, so the values are stored from 
+        // the end if the series is truncated before the start
+        self.start = self.start.min(new_size);
+        let size = self.storage.size().await?;
+        let new_len = new_size - self.start;
+
+        match self.default.clone() {
+            // The default differs from zeros, so the new values are filled 
+            // chunk by chunk growing the storage at the end
+            Some(record) if new_len > size && 
+                            record != self._null_record() => {
+                for ix in (size..new_len).step_by(CHUNK_SIZE) {
+                    let count = CHUNK_SIZE.min(new_len - ix);
+                    self.storage.resize(ix + count).await?;
+                    self.storage.fill(ix, count, &record).await?;
+                }
+            },
+            _ => self.storage.resize(new_len).await?,
+        }
+
+        if let Some(validity) = &mut self.validity {
+            let size = validity.size();
+            validity.resize(new_size).await?;
+            if self.default.is_some() && new_size > size {
+                validity.fill(size, new_size - size, true).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get `size` values located from `ix`.
+    pub async fn get(&mut self, ix: usize, size: usize) ->
+                     TokioResult<Vec<Dataunit>> {
+        // The values before the start are not stored
+        let from = ix.max(self.start).min(ix + size);
+        let mut values = vec![self._default_value(); from - ix];
+
+        if ix + size > from {
+            let mut stored = self.storage.get(from - self.start, 
+                                              ix + size - from, 
+                                              &self.datatype).await?;
+            if let Some(validity) = &mut self.validity {
+                let bits = validity.get(from, ix + size - from).await?;
+                for (value, valid) in stored.iter_mut().zip(bits) {
+                    if !valid {
+                        *value = Dataunit::Null;
+                    }
+                }
+            }
+            values.extend(stored);
+        }
+
+        Ok(values)
+    }
+
+    /// Check whether `values` can be written to the series: every value
+    /// matches the datatype and the nulls are given only if the series is
+    /// nullable.
+    pub fn validate(&self, values: &[Dataunit]) -> TokioResult<()> {
+        self._records(values).map(|_| ())
+    }
+
+    /// Update the values located from `ix` with `values`. It returns
+    /// an error if some value does not match the datatype. Null values
+    /// are allowed only if the series is nullable.
+    pub async fn update(&mut self, ix: usize, values: &[Dataunit]) ->
+                        TokioResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let records = self._records(values)?;
+
+        if ix < self.start {
+            self._materialize(ix).await?;
+        }
+        self.storage.update(ix - self.start, &records).await?;
+
+        if let Some(validity) = &mut self.validity {
+            let bits = values.iter()
+                .map(|unit| *unit != Dataunit::Null)
+                .collect::<Vec<bool>>();
+            validity.set(ix, &bits).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fill `size` values located from `ix` with the default value if it
+    /// is set, otherwise with zeros (empty values for variable length and
+    /// categorical datatypes) or nulls if the series is nullable.
+    pub async fn clear(&mut self, ix: usize, size: usize) -> TokioResult<()> {
+        // The values before the start are default already
+        let from = ix.max(self.start);
+        if ix + size <= from {
+            return Ok(());
+        }
+
+        let record = self.default.clone()
+            .unwrap_or_else(|| self._null_record());
+        self.storage.fill(from - self.start, ix + size - from, &record).await?;
+        if let Some(validity) = &mut self.validity {
+            validity.fill(from, ix + size - from, 
+                          self.default.is_some()).await?;
+        }
+        Ok(())
+    }
+
+    /// Release the disk space of `count` values located from `ix`. They
+    /// must not be accessed after that, because their content is lost.
+    pub async fn punch(&mut self, ix: usize, count: usize) -> TokioResult<()> {
+        // The values before the start are not stored
+        let from = ix.max(self.start);
+        if ix + count > from {
+            self.storage.punch(from - self.start, ix + count - from).await?;
+        }
+        if let Some(validity) = &mut self.validity {
+            validity.punch(ix, count).await?;
+        }
+        Ok(())
+    }
+
+    /// Seal the full segments of the segmented series, so the values there
+    /// cannot be changed.
+    pub async fn seal(&mut self) -> TokioResult<()> {
+        if let Storage::Fixed(seq) = &mut self.storage {
+            let size = seq.size().await?;
+            seq.seal(size).await?;
+        }
+        if let Some(validity) = &mut self.validity {
+            validity.seal().await?;
+        }
+        Ok(())
+    }
+
+    /// Get the dictionary values ordered by their codes. It is supported
+    /// for categorical datatypes only.
+    pub fn dict(&self) -> TokioResult<Vec<Dataunit>> {
+        match &self.storage {
+            Storage::Dict(_, dict) => Ok(
+                dict.values().iter()
+                    .map(|value| self.datatype.from_bytes(value))
+                    .collect()
+            ),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported, self.datatype.to_string()
+            )),
+        }
+    }
+
+    /// Get raw bytes of `size` values located from `ix`. It is supported
+    /// for fixed size datatypes only, for categorical ones the bytes are
+    /// the dictionary codes. Null values are given as zeros.
+    pub async fn get_raw(&mut self, ix: usize, size: usize) ->
+                         TokioResult<Vec<u8>> {
+        self._fixed_seq()?;
+
+        // The values before the start are not stored
+        let from = ix.max(self.start).min(ix + size);
+        let mut block = self._raw_default().await?.repeat(from - ix);
+
+        if ix + size > from {
+            let start = self.start;
+            let seq = self._fixed_seq()?;
+            let mut stored = vec![0u8; (ix + size - from) * seq.block_size()];
+            seq.get(from - start, &mut stored).await?;
+            block.extend(stored);
+        }
+
+        Ok(block)
+    }
+
+    /// Update raw bytes of the values located from `ix` with `block`. It is
+    /// supported for fixed size datatypes only, for categorical ones
+    /// the bytes are the dictionary codes. The updated values become
+    /// not null.
+    pub async fn update_raw(&mut self, ix: usize, block: &[u8]) ->
+                            TokioResult<()> {
+        let size = self.raw_size(block);
+        self._fixed_seq()?;
+        if size == 0 {
+            return Ok(());
+        }
+        if ix < self.start {
+            self._materialize(ix).await?;
+        }
+        let start = self.start;
+        self._fixed_seq()?.update(ix - start, block).await?;
+        if let Some(validity) = &mut self.validity {
+            validity.fill(ix, size, true).await?;
+        }
+        Ok(())
+    }
+
+    /// Get the number of values that `block` of raw bytes contains.
+    pub fn raw_size(&self, block: &[u8]) -> usize {
+        block.len() / self.datatype.size()
+    }
+
+    async fn _open(path: &str, col_item: &ColItem,
+                   segment_size: Option<usize>) -> TokioResult<Self> {
+        let datatype = col_item.parse_datatype();
+
+        let col_path = Self::_get_col_path(path);
+        let storage = if let Some(segment_size) = segment_size {
+            if !datatype.is_fixed() {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("{} cannot be segmented", datatype)
+                ));
+            }
+            Storage::Fixed(Seq::new_segmented(col_path, datatype.size(),
+                                              segment_size).await?)
+        } else if datatype.is_var() {
+            let heap_path = Self::_get_heap_path(path);
+            Storage::Var(VarSeq::new(col_path, heap_path).await?)
+        } else if datatype == Datatype::Category {
+            let dict = Dict::new(Self::_get_dict_path(path),
+                                 Self::_get_dict_heap_path(path)).await?;
+            Storage::Dict(Seq::new(col_path, datatype.size()).await?, dict)
+        } else {
+            Storage::Fixed(Seq::new(col_path, datatype.size()).await?)
+        };
+
+        let start = col_item.start;
+
+        let validity = if col_item.nullable {
+            let size = start + storage.size().await?;
+            let valid_path = Self::_get_valid_path(path);
+            Some(match segment_size {
+                Some(segment_size) => Bitmap::new_segmented(
+                    valid_path, size, segment_size
+                ).await?,
+                None => Bitmap::new(valid_path, size).await?,
+            })
+        } else {
+            None
+        };
+
+        let default = col_item.get_default_bytes();
+
+        let mut series = Self {
+            path: path.to_string(), datatype, storage, validity, default, 
+            start,
+        };
+        if segment_size.is_some() {
+            series.seal().await?;
+        }
+        Ok(series)
+    }
+
+    fn _fixed_seq(&mut self) -> TokioResult<&mut Seq> {
+        match &mut self.storage {
+            Storage::Fixed(seq) | Storage::Dict(seq, _) => Ok(seq),
+            Storage::Var(_) => Err(Error::new(
+                ErrorKind::Unsupported, self.datatype.to_string()
+            )),
+        }
+    }
+
+    /// Store the values from `ix` moving the start there. The new files
+    /// get the default values first and the stored values after them,
+    /// then they replace the old files, so the stored values are copied
+    /// once instead of shifting them in place.
+    async fn _materialize(&mut self, ix: usize) -> TokioResult<()> {
+        let count = self.start - ix;
+        let col_path = Self::_get_col_path(&self.path);
+        let record = self._raw_default().await?;
+
+        match &mut self.storage {
+            Storage::Fixed(seq) | Storage::Dict(seq, _) => {
+                Self::_prepend_seq(seq, &col_path, count, &record).await?;
+            },
+            Storage::Var(varseq) => {
+                let record = self.default.clone().unwrap_or_default();
+                let heap_path = Self::_get_heap_path(&self.path);
+                Self::_prepend_varseq(varseq, &col_path, &heap_path, count, 
+                                      &record).await?;
+            },
+        }
+
+        if let Some(validity) = &mut self.validity {
+            validity.fill(ix, count, self.default.is_some()).await?;
+        }
+        self.start = ix;
+        Ok(())
+    }
+
+    async fn _prepend_seq(seq: &mut Seq, path: &str, count: usize, 
+                          record: &[u8]) -> TokioResult<()> {
+        // Write the new file
+        let tmp_path = format!("{}~", path);
+        Self::_remove_stale(&tmp_path).await?;
+        let block_size = seq.block_size();
+        let mut seq_new = Seq::new(&tmp_path, block_size).await?;
+        for start in (0..count).step_by(CHUNK_SIZE) {
+            let len = CHUNK_SIZE.min(count - start);
+            seq_new.push(&record.repeat(len)).await?;
+        }
+        let size = seq.size().await?;
+        for start in (0..size).step_by(CHUNK_SIZE) {
+            let mut block = vec![0u8; CHUNK_SIZE.min(size - start) * 
+                                      block_size];
+            seq.get(start, &mut block).await?;
+            seq_new.push(&block).await?;
+        }
+        drop(seq_new);
+
+        // Replace the old file
+        rename(&tmp_path, path).await?;
+        *seq = Seq::new(path, block_size).await?;
+        Ok(())
+    }
+
+    async fn _prepend_varseq(varseq: &mut VarSeq, path: &str, 
+                             heap_path: &str, count: usize, 
+                             record: &[u8]) -> TokioResult<()> {
+        // Write the new files
+        let tmp_path = format!("{}~", path);
+        let tmp_heap_path = format!("{}~", heap_path);
+        Self::_remove_stale(&tmp_path).await?;
+        Self::_remove_stale(&tmp_heap_path).await?;
+        let mut varseq_new = VarSeq::new(&tmp_path, &tmp_heap_path).await?;
+        for start in (0..count).step_by(CHUNK_SIZE) {
+            let len = CHUNK_SIZE.min(count - start);
+            varseq_new.push(&vec![record.to_vec(); len]).await?;
+        }
+        let size = varseq.size().await?;
+        for start in (0..size).step_by(CHUNK_SIZE) {
+            let records = varseq.get(start, 
+                                     CHUNK_SIZE.min(size - start)).await?;
+            varseq_new.push(&records).await?;
+        }
+        drop(varseq_new);
+
+        // Replace the old files
+        rename(&tmp_path, path).await?;
+        rename(&tmp_heap_path, heap_path).await?;
+        *varseq = VarSeq::new(path, heap_path).await?;
+        Ok(())
+    }
+
+    async fn _remove_stale(path: &str) -> TokioResult<()> {
+        // The files left by an interrupted rewrite are removed
+        match remove_file(path).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// The value of the rows that are not stored.
+    fn _default_value(&self) -> Dataunit {
+        match &self.default {
+            Some(record) => self.datatype.from_bytes(record),
+            None if self.is_nullable() => Dataunit::Null,
+            None => self.datatype.from_bytes(&self._null_record()),
+        }
+    }
+
+    /// The raw bytes of the rows that are not stored.
+    async fn _raw_default(&mut self) -> TokioResult<Vec<u8>> {
+        match (&mut self.storage, &self.default) {
+            (Storage::Dict(_, dict), Some(record)) => {
+                let code = dict.encode(std::slice::from_ref(record)).await?;
+                Ok(to_bytes_many(&code).to_vec())
+            },
+            (Storage::Fixed(_), Some(record)) => Ok(record.clone()),
+            _ => Ok(vec![0u8; self.datatype.size()]),
+        }
+    }
+
+    fn _records(&self, values: &[Dataunit]) -> TokioResult<Vec<Vec<u8>>> {
+        let nullable = self.is_nullable();
+        values.iter()
+            .map(|unit| match unit {
+                Dataunit::Null if nullable => Ok(self._null_record()),
+                _ => self.datatype.to_bytes(unit).ok_or_else(
+                    || Error::new(ErrorKind::InvalidData, format!(
+                        "{:?} does not match {}", unit, self.datatype
+                    ))
+                ),
+            })
+            .collect()
+    }
+
+    fn _null_record(&self) -> Vec<u8> {
+        if self.datatype.is_fixed() {
+            vec![0u8; self.datatype.size()]
+        } else {
+            vec![]
+        }
+    }
+
+    fn _get_col_path(path: &str) -> String {
+        format!("{}.col", path)
+    }
+
+    fn _get_heap_path(path: &str) -> String {
+        format!("{}.heap", path)
+    }
+
+    fn _get_dict_path(path: &str) -> String {
+        format!("{}.dict", path)
+    }
+
+    fn _get_dict_heap_path(path: &str) -> String {
+        format!("{}.dictheap", path)
+    }
+
+    fn _get_valid_path(path: &str) -> String {
+        format!("{}.valid", path)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::ColOptions;
+
+    async fn create(path: &str, datatype: &str, options: &ColOptions,
+                    start: usize) -> TokioResult<Series> {
+        let mut col_item = ColItem::with_options("x", datatype, options)?;
+        col_item.start = start;
+        for file in Series::files(path, &col_item).await? {
+            remove_file(file).await?;
+        }
+        Series::new(path, &col_item).await
+    }
+
+    #[tokio::test]
+    async fn test_series_default() -> TokioResult<()> {
+        let options = ColOptions {
+            nullable: false, default: Some(Dataunit::I(7)),
+        };
+        let mut series = create("./tmp/test_series_default", "Int64",
+                                &options, 3).await?;
+        assert_eq!(series.size().await?, 3);
+        assert_eq!(series.get(0, 3).await?, vec![Dataunit::I(7); 3]);
+
+        // New values get the default
+        series.resize(6).await?;
+        series.update(4, &[Dataunit::I(1)]).await?;
+        assert_eq!(series.start(), 3);
+        assert_eq!(series.get(1, 5).await?, vec![
+            Dataunit::I(7), Dataunit::I(7), Dataunit::I(7), Dataunit::I(1),
+            Dataunit::I(7),
+        ]);
+
+        // Updating before the start stores the values from there
+        series.update(1, &[Dataunit::I(2)]).await?;
+        assert_eq!(series.start(), 1);
+        assert_eq!(series.size().await?, 6);
+        assert_eq!(series.get(0, 6).await?, vec![
+            Dataunit::I(7), Dataunit::I(2), Dataunit::I(7), Dataunit::I(7),
+            Dataunit::I(1), Dataunit::I(7),
+        ]);
+
+        // Truncating before the start moves it to the end
+        series.resize(0).await?;
+        assert_eq!(series.start(), 0);
+        series.resize(2).await?;
+        assert_eq!(series.get(0, 2).await?, vec![Dataunit::I(7); 2]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_series_materialize() -> TokioResult<()> {
+        // Variable length values are null before the start
+        let options = ColOptions { nullable: true, default: None };
+        let mut series = create("./tmp/test_series_var", "VarStr",
+                                &options, 2).await?;
+        series.resize(4).await?;
+        series.update(3, &[Dataunit::S("c".to_string())]).await?;
+        series.update(1, &[Dataunit::S("a".to_string())]).await?;
+        assert_eq!(series.start(), 1);
+        assert_eq!(series.get(0, 4).await?, vec![
+            Dataunit::Null, Dataunit::S("a".to_string()), Dataunit::Null,
+            Dataunit::S("c".to_string()),
+        ]);
+
+        // Categorical values get the code of the default
+        let options = ColOptions {
+            nullable: false, default: Some(Dataunit::S("z".to_string())),
+        };
+        let mut series = create("./tmp/test_series_category", "Category",
+                                &options, 2).await?;
+        series.update(0, &[Dataunit::S("a".to_string())]).await?;
+        assert_eq!(series.start(), 0);
+        assert_eq!(series.get(0, 2).await?, vec![
+            Dataunit::S("a".to_string()), Dataunit::S("z".to_string()),
+        ]);
+
+        Ok(())
+    }
+}
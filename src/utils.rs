@@ -2,7 +2,7 @@
 
 use regex::Regex;
 use std::io::{Error, ErrorKind};
-use std::mem::size_of;
+use std::mem::{size_of, size_of_val};
 use std::slice::from_raw_parts;
 
 
@@ -11,13 +11,36 @@ pub const ALLOWED_NAME_PATTERN: &str =
     r"^[a-zA-Z_][a-zA-Z_0-9]*(\.[a-zA-Z_][a-zA-Z_0-9]*)*$";
 
 
+/// Maximum size of a name of a feed or a column in bytes.
+pub const MAX_NAME_SIZE: usize = 1024;
+
+/// Maximum size of a part of a name between the dots in bytes. The parts of
+/// the feed names and the whole column names are used as the names of 
+/// the directories and the files, so they must fit the file system limits 
+/// together with the extensions.
+pub const MAX_NAME_PART_SIZE: usize = 200;
+
+
+/// Number of records that are processed at once by the operations scanning
+/// or filling large ranges of columns.
+pub const CHUNK_SIZE: usize = 65536;
+
+
+/// Get the current time as the number of seconds since the Unix epoch.
+pub fn timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+
 /// Represent `x` as its bytes (without copying).
 pub fn to_bytes<T: Sized>(x: &T) -> &[u8] {
     let ptr = (x as *const T) as *const u8;
-    let data = unsafe {
+    unsafe {
         from_raw_parts(ptr, size_of::<T>())
-    };
-    data
+    }
 }
 
 
@@ -25,7 +48,7 @@ pub fn to_bytes<T: Sized>(x: &T) -> &[u8] {
 pub fn from_bytes<T: Sized>(block: &[u8]) -> &T {
     let ptr = (block as *const [u8]) as *const T;
     let data = unsafe {
-        from_raw_parts(ptr, size_of::<T>())
+        from_raw_parts(ptr, 1)
     };
     data.first().unwrap()
 }
@@ -34,11 +57,10 @@ pub fn from_bytes<T: Sized>(block: &[u8]) -> &T {
 /// Represent slice `x` as its bytes (without copying).
 pub fn to_bytes_many<T: Sized>(x: &[T]) -> &[u8] {
     let ptr = (x as *const [T]) as *const u8;
-    let size = x.len() * size_of::<T>();
-    let data = unsafe {
+    let size = size_of_val(x);
+    unsafe {
         from_raw_parts(ptr, size)
-    };
-    data
+    }
 }
 
 
@@ -46,10 +68,9 @@ pub fn to_bytes_many<T: Sized>(x: &[T]) -> &[u8] {
 pub fn from_bytes_many<T: Sized>(block: &[u8]) -> &[T] {
     let ptr = (block as *const [u8]) as *const T;
     let size = block.len() / size_of::<T>();
-    let data = unsafe {
+    unsafe {
         from_raw_parts(ptr, size)
-    };
-    data
+    }
 }
 
 
@@ -77,6 +98,10 @@ pub fn bytes_to_str(bytes: &[u8]) -> &str {
 /// Each segment:
 /// - May include uppercase and lowercase letters, digits, and underscores (`_`)
 /// - Must **not** start with a digit
+/// - Must **not** be longer than `MAX_NAME_PART_SIZE` bytes
+///
+/// The whole name must **not** be longer than `MAX_NAME_SIZE` bytes. Longer
+/// names are rejected rather than truncated.
 ///
 /// # Examples
 ///
@@ -93,7 +118,15 @@ pub fn bytes_to_str(bytes: &[u8]) -> &str {
 /// - `qwe.2rty`
 /// - `qwe-rty`
 pub fn validate_allowed_name(name: &str) -> std::io::Result<()> {
-    if Regex::new(ALLOWED_NAME_PATTERN).unwrap().is_match(name) {
+    if name.len() > MAX_NAME_SIZE {
+        Err(Error::new(ErrorKind::InvalidInput, format!(
+            "name is longer than {} bytes", MAX_NAME_SIZE
+        )))
+    } else if name.split('.').any(|part| part.len() > MAX_NAME_PART_SIZE) {
+        Err(Error::new(ErrorKind::InvalidInput, format!(
+            "name part is longer than {} bytes", MAX_NAME_PART_SIZE
+        )))
+    } else if Regex::new(ALLOWED_NAME_PATTERN).unwrap().is_match(name) {
         Ok(())
     } else {
         Err(Error::new(ErrorKind::InvalidInput, name))
@@ -165,5 +198,12 @@ mod tests {
         assert!(validate_allowed_name("1qwe").is_err());
         assert!(validate_allowed_name("qwe.2rty").is_err());
         assert!(validate_allowed_name("qwe-rty").is_err());
+
+        let part = "q".repeat(MAX_NAME_PART_SIZE);
+        assert!(validate_allowed_name(&part).is_ok());
+        assert!(validate_allowed_name(&format!("{}w", part)).is_err());
+        let name = [part.as_str(); 6].join(".");
+        assert!(validate_allowed_name(&name[..MAX_NAME_SIZE]).is_ok());
+        assert!(validate_allowed_name(&name).is_err());
     }
 }
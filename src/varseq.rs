@@ -0,0 +1,225 @@
+//! `VarSeq` stores a sequence of variable-length byte records. It keeps two
+//! `Seq` files: the offsets file with the end offsets of the records
+//! (`u64` each) and the heap file with the concatenated record bytes, so
+//! a record is still accessed by its index.
+
+use std::mem::size_of;
+use std::path::Path;
+
+use tokio::io::Result as TokioResult;
+
+use crate::utils::{to_bytes_many, from_bytes_many};
+use crate::seq::Seq;
+
+
+/// `VarSeq` implements a storage for the records of variable length. The
+/// record with index `ix` occupies the bytes of the heap between the end
+/// offsets of the records `ix - 1` and `ix`. Appending and fetching ranges
+/// are cheap, but updating records in the middle rewrites the heap tail
+/// after them, because the heap is kept compact.
+pub struct VarSeq {
+    offsets: Seq,
+    heap: Seq,
+}
+
+
+impl VarSeq {
+    /// Create a `VarSeq` object located by the paths of the offsets file
+    /// `offsets_path` and the heap file `heap_path`. If no files exist, it
+    /// creates empty ones.
+    pub async fn new(offsets_path: impl AsRef<Path>,
+                     heap_path: impl AsRef<Path>) -> TokioResult<Self> {
+        let offsets = Seq::new(offsets_path, size_of::<u64>()).await?;
+        let heap = Seq::new(heap_path, 1).await?;
+        Ok(Self { offsets, heap })
+    }
+
+    /// Get the number of records.
+    pub async fn size(&self) -> TokioResult<usize> {
+        self.offsets.size().await
+    }
+
+    /// Get the size of the heap in bytes.
+    pub async fn heap_size(&self) -> TokioResult<usize> {
+        self.heap.size().await
+    }
+
+    /// Resize the sequence setting a new number of records `new_size`.
+    /// The new records are empty, the heap is truncated to the end of the
+    /// last remaining record.
+    pub async fn resize(&mut self, new_size: usize) -> TokioResult<()> {
+        let size = self.offsets.size().await?;
+        if new_size > size {
+            let end = self._end(size).await? as u64;
+            let ends = vec![end; new_size - size];
+            self.offsets.push(to_bytes_many(&ends)).await?;
+        } else if new_size < size {
+            let end = self._end(new_size).await?;
+            self.offsets.resize(new_size).await?;
+            self.heap.resize(end).await?;
+        }
+        Ok(())
+    }
+
+    /// Push new records to the end. It returns the index of the first
+    /// pushed record.
+    pub async fn push(&mut self, records: &[Vec<u8>]) -> TokioResult<usize> {
+        let ix = self.offsets.size().await?;
+        self.resize(ix + records.len()).await?;
+        self.update(ix, records).await?;
+        Ok(ix)
+    }
+
+    /// Get `count` records located from `ix`.
+    pub async fn get(&mut self, ix: usize, count: usize) ->
+                     TokioResult<Vec<Vec<u8>>> {
+        if count == 0 {
+            return Ok(vec![]);
+        }
+
+        let start = self._end(ix).await?;
+        let ends = self._get_ends(ix, count).await?;
+
+        let mut block = vec![0u8; ends[count - 1] as usize - start];
+        if !block.is_empty() {
+            self.heap.get(start, &mut block).await?;
+        }
+
+        let mut pos = 0;
+        Ok(ends.into_iter().map(|end| {
+            let end = end as usize - start;
+            let record = block[pos..end].to_vec();
+            pos = end;
+            record
+        }).collect())
+    }
+
+    /// Update the records located from `ix` with `records`. The records
+    /// after the updated ones are shifted in the heap if the total length
+    /// changes.
+    pub async fn update(&mut self, ix: usize, records: &[Vec<u8>]) ->
+                        TokioResult<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let size = self.offsets.size().await?;
+        let count = records.len();
+        let start = self._end(ix).await?;
+        let old_end = self._end(ix + count).await?;
+
+        // Read the heap tail after the updated records
+        let heap_size = self.heap.size().await?;
+        let mut tail = vec![0u8; heap_size - old_end];
+        if !tail.is_empty() {
+            self.heap.get(old_end, &mut tail).await?;
+        }
+
+        // Write the new records and the tail
+        let mut block = records.concat();
+        let new_end = start + block.len();
+        block.extend_from_slice(&tail);
+        self.heap.update(start, &block).await?;
+        self.heap.resize(start + block.len()).await?;
+
+        // Update the end offsets of the records
+        let mut end = start as u64;
+        let ends = records.iter().map(|rec| {
+            end += rec.len() as u64;
+            end
+        }).collect::<Vec<u64>>();
+        self.offsets.update(ix, to_bytes_many(&ends)).await?;
+
+        // Shift the end offsets of the records after the updated ones
+        if new_end != old_end && ix + count < size {
+            let ends = self._get_ends(ix + count, size - ix - count).await?
+                .into_iter()
+                .map(|end| end + new_end as u64 - old_end as u64)
+                .collect::<Vec<u64>>();
+            self.offsets.update(ix + count, to_bytes_many(&ends)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Release the disk space of `count` records located from `ix`, they
+    /// read as garbage after that. The end offset of the last released
+    /// record is kept, so the next records stay available.
+    pub async fn punch(&mut self, ix: usize, count: usize) ->
+                       TokioResult<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        let start = self._end(ix).await?;
+        let end = self._end(ix + count).await?;
+        self.heap.punch(start, end - start).await?;
+        self.offsets.punch(ix, count - 1).await
+    }
+
+    async fn _end(&mut self, size: usize) -> TokioResult<usize> {
+        if size > 0 {
+            Ok(self._get_ends(size - 1, 1).await?[0] as usize)
+        } else {
+            Ok(0)
+        }
+    }
+
+    async fn _get_ends(&mut self, ix: usize, count: usize) ->
+                       TokioResult<Vec<u64>> {
+        let mut block = vec![0u8; count * size_of::<u64>()];
+        self.offsets.get(ix, &mut block).await?;
+        Ok(from_bytes_many::<u64>(&block).to_vec())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_varseq() -> TokioResult<()> {
+        let offsets_path = "./tmp/test_varseq.col";
+        let heap_path = "./tmp/test_varseq.heap";
+        let _ = tokio::fs::remove_file(offsets_path).await;
+        let _ = tokio::fs::remove_file(heap_path).await;
+
+        let mut varseq = VarSeq::new(offsets_path, heap_path).await?;
+
+        let ix = varseq.push(&[b"qwe".to_vec(), b"".to_vec(), 
+                               b"asdf".to_vec()]).await?;
+        assert_eq!(ix, 0);
+        assert_eq!(varseq.size().await?, 3);
+        assert_eq!(varseq.heap_size().await?, 7);
+        assert_eq!(varseq.get(1, 2).await?, vec![b"".to_vec(), 
+                                                  b"asdf".to_vec()]);
+
+        varseq.update(0, &[b"z".to_vec(), b"xy".to_vec()]).await?;
+        assert_eq!(varseq.heap_size().await?, 7);
+        varseq.update(1, &[b"xyz".to_vec()]).await?;
+        assert_eq!(varseq.heap_size().await?, 8);
+        assert_eq!(varseq.get(0, 3).await?, vec![b"z".to_vec(), 
+                                                  b"xyz".to_vec(), 
+                                                  b"asdf".to_vec()]);
+
+        varseq.resize(5).await?;
+        assert_eq!(varseq.get(2, 3).await?, vec![b"asdf".to_vec(), 
+                                                  b"".to_vec(), 
+                                                  b"".to_vec()]);
+
+        varseq.resize(2).await?;
+        assert_eq!(varseq.size().await?, 2);
+        assert_eq!(varseq.heap_size().await?, 4);
+        assert_eq!(varseq.get(0, 0).await?, Vec::<Vec<u8>>::new());
+
+        varseq.resize(0).await?;
+        varseq.push(&[b"".to_vec(), b"".to_vec(), b"z".to_vec(), 
+                      b"xyz".to_vec()]).await?;
+
+        varseq.punch(1, 2).await?;
+        assert_eq!(varseq.get(3, 1).await?, vec![b"xyz".to_vec()]);
+        assert_eq!(varseq.heap_size().await?, 4);
+
+        Ok(())
+    }
+}